@@ -0,0 +1,438 @@
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::ParsedBlock;
+
+// ═══════════════════════════════════════════════════════════════
+// ANSI ESCAPES
+// ═══════════════════════════════════════════════════════════════
+//
+// Colored output from cargo, `ls --color`, git etc. is full of escape
+// sequences. Output is either stripped to plain text (then parsed as
+// markdown as usual) or kept as one block per line with the SGR styles
+// turned into marks: `[{ start, end, bold, color, ... }]`, offsets in
+// UTF-16 code units so they index straight into the JS string.
+//
+// Within a line, `\r`, `ESC[K` and cursor moves overwrite text the way
+// a terminal would, so progress output collapses to its final state.
+
+/// What to do with escape sequences in command output (per runner)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnsiMode {
+    /// Drop escapes, parse the plain text as markdown
+    #[default]
+    Strip,
+    /// One block per line, SGR styles stored as marks
+    Marks,
+}
+
+/// Text style from SGR sequences
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    strikethrough: bool,
+    inverse: bool,
+    fg: Option<String>,
+    bg: Option<String>,
+}
+
+/// A styled range of a line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    /// UTF-16 offsets into the line
+    pub start: usize,
+    pub end: usize,
+    style: Style,
+}
+
+impl Mark {
+    /// Block mark object, PlateJS mark names
+    pub fn to_any(&self) -> yrs::Any {
+        let style = &self.style;
+        let (color, background) = if style.inverse {
+            (style.bg.clone().or(Some("#000000".to_string())), style.fg.clone().or(Some("#e5e5e5".to_string())))
+        } else {
+            (style.fg.clone(), style.bg.clone())
+        };
+
+        let mut fields: Vec<(String, yrs::Any)> = vec![
+            ("start".into(), yrs::Any::Number(self.start as f64)),
+            ("end".into(), yrs::Any::Number(self.end as f64)),
+        ];
+        for (name, on) in [
+            ("bold", style.bold),
+            ("italic", style.italic),
+            ("underline", style.underline),
+            ("strikethrough", style.strikethrough),
+        ] {
+            if on {
+                fields.push((name.into(), yrs::Any::Bool(true)));
+            }
+        }
+        if let Some(color) = color {
+            fields.push(("color".into(), yrs::Any::String(color.into())));
+        }
+        if let Some(background) = background {
+            fields.push(("backgroundColor".into(), yrs::Any::String(background.into())));
+        }
+        yrs::Any::Map(Arc::new(fields.into_iter().collect()))
+    }
+}
+
+/// A line of output with escapes resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledLine {
+    pub text: String,
+    pub marks: Vec<Mark>,
+}
+
+/// xterm's default 16-color palette
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// Color from the 256-color table: 16 base colors, a 6x6x6 cube, then grays
+fn color_256(n: usize) -> Option<String> {
+    match n {
+        0..=15 => Some(PALETTE[n].to_string()),
+        16..=231 => {
+            let level = |v: usize| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            Some(format!("#{:02x}{:02x}{:02x}", level(n / 36), level((n / 6) % 6), level(n % 6)))
+        }
+        232..=255 => {
+            let gray = 8 + (n - 232) * 10;
+            Some(format!("#{:02x}{:02x}{:02x}", gray, gray, gray))
+        }
+        _ => None,
+    }
+}
+
+/// Parse an extended color (`38;5;n` or `38;2;r;g;b`) starting after the 38/48
+/// Returns the color and how many parameters it used
+fn extended_color(params: &[usize]) -> (Option<String>, usize) {
+    match params {
+        [5, n, ..] => (color_256(*n), 2),
+        [2, r, g, b, ..] => (Some(format!("#{:02x}{:02x}{:02x}", r.min(&255), g.min(&255), b.min(&255))), 4),
+        _ => (None, params.len()),
+    }
+}
+
+impl Style {
+    /// Apply an SGR parameter list, e.g. "1;31" or "38;5;208"
+    fn apply_sgr(&mut self, params: &str) {
+        let codes: Vec<usize> = params.split([';', ':']).map(|p| p.parse().unwrap_or(0)).collect();
+        let mut i = 0;
+        while i < codes.len() {
+            match codes[i] {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                9 => self.strikethrough = true,
+                22 => self.bold = false,
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                29 => self.strikethrough = false,
+                n @ 30..=37 => self.fg = Some(PALETTE[n - 30].to_string()),
+                n @ 90..=97 => self.fg = Some(PALETTE[n - 90 + 8].to_string()),
+                n @ 40..=47 => self.bg = Some(PALETTE[n - 40].to_string()),
+                n @ 100..=107 => self.bg = Some(PALETTE[n - 100 + 8].to_string()),
+                39 => self.fg = None,
+                49 => self.bg = None,
+                38 => {
+                    let (color, used) = extended_color(&codes[i + 1..]);
+                    self.fg = color;
+                    i += used;
+                }
+                48 => {
+                    let (color, used) = extended_color(&codes[i + 1..]);
+                    self.bg = color;
+                    i += used;
+                }
+                // Dim, blink, fonts etc. have no mark
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Escape sequence parser state
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    Charset,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+/// How far past the end of a line the cursor can be moved - a huge move
+/// would otherwise pad the line with that many spaces
+const MAX_CURSOR_JUMP: usize = 4096;
+
+/// The line being built: styled cells plus a cursor column for overwrites
+#[derive(Default)]
+struct LineBuffer {
+    cells: Vec<(char, Style)>,
+    col: usize,
+}
+
+impl LineBuffer {
+    /// Move the cursor to a column, at most `MAX_CURSOR_JUMP` past the line's end
+    fn move_to(&mut self, col: usize) {
+        self.col = col.min(self.cells.len() + MAX_CURSOR_JUMP);
+    }
+
+    fn print(&mut self, c: char, style: &Style) {
+        if self.col < self.cells.len() {
+            self.cells[self.col] = (c, style.clone());
+        } else {
+            while self.cells.len() < self.col {
+                self.cells.push((' ', Style::default()));
+            }
+            self.cells.push((c, style.clone()));
+        }
+        self.col += 1;
+    }
+
+    fn finish(&mut self) -> StyledLine {
+        let cells = std::mem::take(&mut self.cells);
+        self.col = 0;
+
+        let mut text = String::new();
+        let mut marks: Vec<Mark> = vec![];
+        let mut offset = 0;
+        for (c, style) in cells {
+            let width = c.len_utf16();
+            if style != Style::default() {
+                match marks.last_mut() {
+                    Some(mark) if mark.end == offset && mark.style == style => mark.end += width,
+                    _ => marks.push(Mark { start: offset, end: offset + width, style }),
+                }
+            }
+            text.push(c);
+            offset += width;
+        }
+        StyledLine { text, marks }
+    }
+}
+
+/// Resolve escapes in command output into styled lines
+pub fn parse_lines(text: &str) -> Vec<StyledLine> {
+    let mut lines = vec![];
+    let mut line = LineBuffer::default();
+    let mut style = Style::default();
+    let mut state = State::Ground;
+    let mut params = String::new();
+
+    for c in text.chars() {
+        match state {
+            State::Ground => match c {
+                '\x1b' => state = State::Escape,
+                '\n' => lines.push(line.finish()),
+                '\r' => line.col = 0,
+                '\x08' => line.col = line.col.saturating_sub(1),
+                '\t' => {
+                    let next = (line.col / 8 + 1) * 8;
+                    while line.col < next {
+                        line.print(' ', &style);
+                    }
+                }
+                c if c.is_control() => {}
+                c => line.print(c, &style),
+            },
+            State::Escape => {
+                state = match c {
+                    '[' => {
+                        params.clear();
+                        State::Csi
+                    }
+                    ']' => State::Osc,
+                    '(' | ')' => State::Charset,
+                    _ => State::Ground,
+                };
+            }
+            State::Charset => state = State::Ground,
+            State::Osc => match c {
+                '\x07' => state = State::Ground,
+                '\x1b' => state = State::OscEscape,
+                _ => {}
+            },
+            State::OscEscape => state = State::Ground,
+            State::Csi => {
+                if !('\x40'..='\x7e').contains(&c) {
+                    params.push(c);
+                    continue;
+                }
+                state = State::Ground;
+                let n = params.parse::<usize>().unwrap_or(0);
+                match c {
+                    'm' => style.apply_sgr(&params),
+                    'K' => match n {
+                        0 => line.cells.truncate(line.col),
+                        1 => {
+                            let end = (line.col + 1).min(line.cells.len());
+                            line.cells[..end].fill((' ', Style::default()));
+                        }
+                        _ => line.cells.clear(),
+                    },
+                    'G' => line.move_to(n.max(1) - 1),
+                    'C' => line.move_to(line.col.saturating_add(n.max(1))),
+                    'D' => line.col = line.col.saturating_sub(n.max(1)),
+                    // Anything that moves between lines can't be modelled per line
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if !line.cells.is_empty() {
+        lines.push(line.finish());
+    }
+    lines
+}
+
+/// Remove escapes, keeping the text a terminal would show
+pub fn strip(text: &str) -> String {
+    if !text.contains(['\x1b', '\r', '\x08']) {
+        return text.to_string();
+    }
+    let mut stripped = parse_lines(text)
+        .into_iter()
+        .map(|line| line.text)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.ends_with('\n') {
+        stripped.push('\n');
+    }
+    stripped
+}
+
+/// One block per non-empty line, with styles stored as `marks`
+pub fn styled_blocks(text: &str, base_id: &str, block_type: &str) -> Vec<ParsedBlock> {
+    parse_lines(text)
+        .into_iter()
+        .filter(|line| !line.text.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let mut block = ParsedBlock::new(format!("{}-{}", base_id, i), line.text, block_type);
+            if !line.marks.is_empty() {
+                let marks: Vec<yrs::Any> = line.marks.iter().map(Mark::to_any).collect();
+                block.props.insert("marks".to_string(), yrs::Any::Array(Arc::from(marks)));
+            }
+            block
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output captured from real commands (see tests/fixtures/ansi); the *-pty
+    // files were recorded under script(1), so they have \r\n line endings
+    const LS: &str = include_str!("../tests/fixtures/ansi/ls-color.txt");
+    const GIT_DIFF: &str = include_str!("../tests/fixtures/ansi/git-diff.txt");
+    const GIT_DIFF_256: &str = include_str!("../tests/fixtures/ansi/git-diff-256.txt");
+    const CARGO_PTY: &str = include_str!("../tests/fixtures/ansi/cargo-build-pty.txt");
+    const SPINNER_PTY: &str = include_str!("../tests/fixtures/ansi/spinner-pty.txt");
+
+    fn texts(lines: &[StyledLine]) -> Vec<&str> {
+        lines.iter().map(|l| l.text.as_str()).collect()
+    }
+
+    /// (start, end, fg, bg, bold)
+    type MarkSummary<'a> = (usize, usize, Option<&'a str>, Option<&'a str>, bool);
+
+    fn marks(line: &StyledLine) -> Vec<MarkSummary<'_>> {
+        line.marks.iter()
+            .map(|m| (m.start, m.end, m.style.fg.as_deref(), m.style.bg.as_deref(), m.style.bold))
+            .collect()
+    }
+
+    #[test]
+    fn huge_cursor_moves_are_clamped() {
+        let jumped = strip("ab\x1b[999999999Gx\n");
+        assert_eq!(jumped.len(), 2 + MAX_CURSOR_JUMP + 2);
+        assert!(jumped.ends_with("x\n"));
+
+        let forward = strip(&format!("a\x1b[{}Cx\x1b[{}Cy", usize::MAX, usize::MAX));
+        assert_eq!(forward.len(), 1 + MAX_CURSOR_JUMP + 1 + MAX_CURSOR_JUMP + 1);
+        assert!(forward.ends_with('y'));
+    }
+
+    #[test]
+    fn ls_color() {
+        let lines = parse_lines(LS);
+        assert_eq!(texts(&lines), vec!["README.md", "assets/", "link@", "run.sh*"]);
+        assert_eq!(marks(&lines[0]), vec![]);
+        // `ESC[0m ESC[01;34m` - a reset, then bold blue
+        assert_eq!(marks(&lines[1]), vec![(0, 6, Some("#0000ee"), None, true)]);
+        assert_eq!(marks(&lines[2]), vec![(0, 4, Some("#00cdcd"), None, true)]);
+        assert_eq!(marks(&lines[3]), vec![(0, 6, Some("#00cd00"), None, true)]);
+    }
+
+    #[test]
+    fn git_diff_resets_with_empty_sgr() {
+        let lines = parse_lines(GIT_DIFF);
+        assert_eq!(lines[5].text, " fn main() {");
+        assert_eq!(marks(&lines[5]), vec![]);
+        assert_eq!(marks(&lines[4]), vec![(0, 15, Some("#00cdcd"), None, false)]);
+        assert_eq!(marks(&lines[6]), vec![(0, 20, Some("#cd0000"), None, false)]);
+        // `+` and the rest are colored separately but merge into one mark
+        assert_eq!(lines[7].text, "+    println!(\"hello\");");
+        assert_eq!(marks(&lines[7]), vec![(0, 23, Some("#00cd00"), None, false)]);
+    }
+
+    #[test]
+    fn git_diff_256_and_truecolor() {
+        let lines = parse_lines(GIT_DIFF_256);
+        assert_eq!(marks(&lines[0]), vec![(0, 24, Some("#ff8700"), None, true)]);
+        assert_eq!(marks(&lines[4]), vec![(0, 15, Some("#5fafff"), None, false)]);
+        assert_eq!(marks(&lines[6]), vec![(0, 20, Some("#ff0000"), Some("#5f0000"), false)]);
+        assert_eq!(marks(&lines[7]), vec![(0, 23, Some("#00d75f"), None, false)]);
+        assert_eq!(strip(GIT_DIFF_256), ansi_free(GIT_DIFF));
+    }
+
+    #[test]
+    fn cargo_progress_bar_is_overwritten() {
+        let lines = parse_lines(CARGO_PTY);
+        let texts = texts(&lines);
+        assert!(texts.iter().all(|t| !t.contains("Building")), "{:#?}", texts);
+        assert!(texts.contains(&"   Compiling bad v0.1.0 (/tmp/cap/bad)"));
+
+        let error = lines.iter().find(|l| l.text.starts_with("error[E0308]")).unwrap();
+        assert_eq!(error.text, "error[E0308]: mismatched types");
+        assert_eq!(
+            marks(error),
+            vec![(0, 12, Some("#ff0000"), None, true), (12, 30, None, None, true)]
+        );
+        assert_eq!(
+            texts.last(),
+            Some(&"error: could not compile `bad` (bin \"bad\") due to 1 previous error")
+        );
+    }
+
+    #[test]
+    fn erase_line_and_cursor_modes() {
+        // ESC[2K + \r redraws collapse; ESC[?25l/h (cursor visibility) are ignored.
+        // Moving up a line (ESC[1A) can't be modelled per line - term:: blocks
+        // get that from the emulator in term.rs
+        assert_eq!(texts(&parse_lines(SPINNER_PTY)), vec!["step 3/3", "done"]);
+        assert_eq!(texts(&parse_lines("50%\x1b[3D100%\n")), vec!["100%"]);
+        assert_eq!(texts(&parse_lines("abc\x1b[1Gx\x1b[1K!\n")), vec![" !c"]);
+    }
+
+    /// A fixture with escapes removed by hand
+    fn ansi_free(text: &str) -> String {
+        regex::Regex::new("\x1b\\[[0-9;]*m").unwrap().replace_all(text, "").into_owned()
+    }
+}
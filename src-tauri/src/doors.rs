@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use yrs::{Map, ReadTxn, Transact};
+use crate::{AppState, BlockData, read_block, split_prefix, ancestors};
+use crate::runners::load_runners;
+use crate::structured::split_format;
+
+// ═══════════════════════════════════════════════════════════════
+// DOOR DEFINITIONS
+// ═══════════════════════════════════════════════════════════════
+//
+// Doors are templated aliases defined in the outline itself, so they
+// travel (and sync) with the workspace:
+//
+//   door:: read
+//     template:: floatctl bbs board read {board} {args}
+//     inherits:: board
+//
+// `{args}` is the text after the prefix on the invoking block, every
+// other placeholder must be listed in `inherits::` and is looked up on
+// the ancestors of the invoking block. `{{` and `}}` are literal braces.
+
+/// Prefixes with built-in meaning that a door may not take over
+pub const RESERVED_PREFIXES: &[&str] = &[
+    "sh", "term", "ai", "chat", "ctx", "dispatch", "web", "link",
+    "door", "template", "inherits", "stdin", "session", "sandbox",
+    "every", "cron", "watch", "cwd",
+    // Built-in runners (runners.rs); custom runners' prefixes are checked when indexing
+    "py", "python", "node", "js", "ruby", "rb",
+];
+
+/// Placeholder bound to the invoking block's own text
+const ARGS_PLACEHOLDER: &str = "args";
+
+/// A door alias defined by a `door:: name` block
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorDefinition {
+    pub name: String,
+    pub template: String,
+    pub inherits: Vec<String>,
+    pub block_id: String,
+}
+
+/// A definition block that failed validation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorError {
+    pub block_id: String,
+    pub message: String,
+}
+
+/// Several definition blocks claiming the same door name
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorConflict {
+    pub name: String,
+    pub block_ids: Vec<String>,
+}
+
+/// All door definitions found in a workspace
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoorIndex {
+    /// Valid, unambiguous definitions (sorted by name)
+    pub doors: Vec<DoorDefinition>,
+    /// Names defined more than once - these are not expanded
+    pub conflicts: Vec<DoorConflict>,
+    pub errors: Vec<DoorError>,
+}
+
+impl DoorIndex {
+    fn get(&self, name: &str) -> Option<&DoorDefinition> {
+        self.doors.iter().find(|d| d.name == name)
+    }
+
+    fn conflict(&self, name: &str) -> Option<&DoorConflict> {
+        self.conflicts.iter().find(|c| c.name == name)
+    }
+
+    /// Whether a prefix names a door (valid or conflicting)
+    fn is_door(&self, name: &str) -> bool {
+        self.get(name).is_some() || self.conflict(name).is_some()
+    }
+}
+
+/// A piece of a parsed template
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// Split a template into literals and `{name}` placeholders
+/// Braces that don't wrap a plain name (e.g. awk's `{print $1}`) stay literal
+fn parse_template(template: &str) -> Vec<TemplatePart> {
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut rest = template;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            literal.push(c);
+            rest = &rest[2..];
+            continue;
+        }
+
+        if c == '{' {
+            if let Some(end) = rest.find('}') {
+                let name = &rest[1..end];
+                if is_valid_name(name) {
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(TemplatePart::Placeholder(name.to_lowercase()));
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        literal.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if !literal.is_empty() {
+        parts.push(TemplatePart::Literal(literal));
+    }
+    parts
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Parse a `door::` block and its `template::`/`inherits::` children
+fn parse_definition<T: ReadTxn>(
+    blocks: &yrs::MapRef,
+    txn: &T,
+    block: &BlockData,
+    runner_prefixes: &[String],
+) -> Result<DoorDefinition, String> {
+    let name = split_prefix(block.content())
+        .map(|(_, rest)| rest.to_lowercase())
+        .unwrap_or_default();
+
+    if name.is_empty() {
+        return Err("door:: needs a name, e.g. `door:: read`".to_string());
+    }
+    if !is_valid_name(&name) {
+        return Err(format!("Invalid door name '{}'. Use only letters, numbers, hyphens, and underscores.", name));
+    }
+    if RESERVED_PREFIXES.contains(&name.as_str()) {
+        return Err(format!("'{}::' is a built-in prefix and can't be redefined", name));
+    }
+    if runner_prefixes.contains(&name) {
+        return Err(format!("'{}::' selects a code runner and can't be redefined", name));
+    }
+
+    let mut template: Option<String> = None;
+    let mut inherits: Vec<String> = vec![];
+
+    for child_id in block.child_ids() {
+        let Some(child) = read_block(blocks, txn, &child_id) else { continue };
+        let Some((prefix, value)) = split_prefix(child.content()) else { continue };
+
+        match prefix.as_str() {
+            "template" => {
+                if template.is_some() {
+                    return Err(format!("door '{}' has more than one template:: child", name));
+                }
+                template = Some(value.to_string());
+            }
+            "inherits" => {
+                for param in value.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty()) {
+                    let param = param.to_lowercase();
+                    if !is_valid_name(&param) || param == ARGS_PLACEHOLDER {
+                        return Err(format!("door '{}' can't inherit '{}'", name, param));
+                    }
+                    if !inherits.contains(&param) {
+                        inherits.push(param);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let template = template
+        .filter(|t| !t.is_empty())
+        .ok_or_else(|| format!("door '{}' needs a template:: child", name))?;
+
+    for part in parse_template(&template) {
+        if let TemplatePart::Placeholder(param) = part {
+            if param != ARGS_PLACEHOLDER && !inherits.contains(&param) {
+                return Err(format!(
+                    "door '{}' uses {{{}}} but doesn't list it in inherits::",
+                    name, param
+                ));
+            }
+        }
+    }
+
+    Ok(DoorDefinition {
+        name,
+        template,
+        inherits,
+        block_id: block.id().to_string(),
+    })
+}
+
+/// Index every `door::` block in the blocks map
+pub fn index_doors<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T) -> DoorIndex {
+    let mut index = DoorIndex::default();
+    let mut by_name: HashMap<String, Vec<DoorDefinition>> = HashMap::new();
+    // Runners are resolved before doors, so a door sharing a prefix would never run
+    let runner_prefixes: Vec<String> = load_runners()
+        .map(|table| table.into_values().flat_map(|r| r.prefixes).collect())
+        .unwrap_or_default();
+
+    let keys: Vec<String> = blocks.keys(txn).map(str::to_string).collect();
+    for key in keys {
+        let Some(block) = read_block(blocks, txn, &key) else { continue };
+        if !matches!(split_prefix(block.content()), Some((prefix, _)) if prefix == "door") {
+            continue;
+        }
+
+        match parse_definition(blocks, txn, &block, &runner_prefixes) {
+            Ok(def) => by_name.entry(def.name.clone()).or_default().push(def),
+            Err(message) => index.errors.push(DoorError {
+                block_id: block.id().to_string(),
+                message,
+            }),
+        }
+    }
+
+    for (name, mut defs) in by_name {
+        if defs.len() == 1 {
+            index.doors.extend(defs);
+        } else {
+            let mut block_ids: Vec<String> = defs.drain(..).map(|d| d.block_id).collect();
+            block_ids.sort();
+            index.conflicts.push(DoorConflict { name, block_ids });
+        }
+    }
+
+    index.doors.sort_by(|a, b| a.name.cmp(&b.name));
+    index.conflicts.sort_by(|a, b| a.name.cmp(&b.name));
+    index.errors.sort_by(|a, b| a.block_id.cmp(&b.block_id));
+    index
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXPANSION
+// ═══════════════════════════════════════════════════════════════
+
+/// Find an inherited parameter on the ancestors of a block (nearest first)
+///
+/// `board:: consciousness-tech` binds `board` explicitly. Failing that, the
+/// nearest bare label like `consciousness-tech::` (one that isn't itself a
+/// door or built-in prefix) supplies the value, matching the
+/// `boards:: / consciousness-tech:: / read:: 47` shape.
+fn resolve_param(index: &DoorIndex, ancestors: &[BlockData], param: &str) -> Option<String> {
+    let explicit = ancestors.iter().find_map(|block| match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == param && !value.is_empty() => Some(value.to_string()),
+        _ => None,
+    });
+    if explicit.is_some() {
+        return explicit;
+    }
+
+    ancestors.iter().find_map(|block| match split_prefix(block.content()) {
+        Some((prefix, value))
+            if value.is_empty()
+                && !index.is_door(&prefix)
+                && !RESERVED_PREFIXES.contains(&prefix.as_str()) =>
+        {
+            Some(block.content().trim().trim_end_matches("::").trim().to_string())
+        }
+        _ => None,
+    })
+}
+
+/// Expand a door invocation into its shell command
+/// Returns Ok(None) when the block's prefix isn't a door
+fn expand_door<T: ReadTxn>(
+    index: &DoorIndex,
+    blocks: &yrs::MapRef,
+    txn: &T,
+    block: &BlockData,
+) -> Result<Option<String>, String> {
+    let Some((prefix, args)) = split_prefix(block.content()) else { return Ok(None) };
+
+    if let Some(conflict) = index.conflict(&prefix) {
+        return Err(format!(
+            "door '{}' is defined more than once (blocks {})",
+            prefix,
+            conflict.block_ids.join(", ")
+        ));
+    }
+    let Some(door) = index.get(&prefix) else { return Ok(None) };
+
+    let ancestors = ancestors(blocks, txn, block.id());
+    let mut values: HashMap<&str, String> = HashMap::new();
+    values.insert(ARGS_PLACEHOLDER, args.to_string());
+    for param in &door.inherits {
+        let value = resolve_param(index, &ancestors, param).ok_or_else(|| {
+            format!("{}:: needs `{}` from an ancestor block", door.name, param)
+        })?;
+        values.insert(param.as_str(), value);
+    }
+
+    let command: String = parse_template(&door.template)
+        .into_iter()
+        .map(|part| match part {
+            TemplatePart::Literal(text) => text,
+            TemplatePart::Placeholder(param) => values.get(param.as_str()).cloned().unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(Some(command.trim().to_string()))
+}
+
+/// Straighten smart quotes and dashes (editor typography) that break shell commands
+fn normalize_quotes(command: &str) -> String {
+    command
+        .replace(['\u{201C}', '\u{201D}'], "\"")
+        .replace(['\u{2018}', '\u{2019}'], "'")
+        .replace(['\u{2013}', '\u{2014}'], "-")
+}
+
+/// Resolve the shell command a block runs: `sh::`/`term::` text or a door expansion
+/// Returns Ok(None) for blocks that aren't executable
+pub fn resolve_command<T: ReadTxn>(
+    blocks: &yrs::MapRef,
+    txn: &T,
+    block_id: &str,
+) -> Result<Option<String>, String> {
+    let block = read_block(blocks, txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
+
+    // A format word (`sh::json ...`) is about the output, not the command
+    let (_, content) = split_format(block.content());
+    let command = match split_prefix(&content) {
+        Some((prefix, command)) if prefix == "sh" || prefix == "term" => {
+            Some(command.to_string()).filter(|c| !c.is_empty())
+        }
+        Some(_) => {
+            let index = index_doors(blocks, txn);
+            expand_door(&index, blocks, txn, &block)?
+        }
+        None => None,
+    };
+    Ok(command.map(|c| normalize_quotes(&c)))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// List door definitions in the current workspace, with conflicts and validation errors
+#[tauri::command]
+pub fn list_doors(state: tauri::State<'_, AppState>) -> Result<DoorIndex, String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let txn = doc.transact();
+    let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+    Ok(index_doors(&blocks, &txn))
+}
+
+/// Expand a block into the shell command it would run (None if not executable)
+#[tauri::command]
+pub fn expand_block(state: tauri::State<'_, AppState>, block_id: String) -> Result<Option<String>, String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let txn = doc.transact();
+    let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+    resolve_command(&blocks, &txn, &block_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::doc_with;
+
+    fn resolve(doc: &yrs::Doc, id: &str) -> Result<Option<String>, String> {
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+        resolve_command(&blocks, &txn, id)
+    }
+
+    fn index(doc: &yrs::Doc) -> DoorIndex {
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+        index_doors(&blocks, &txn)
+    }
+
+    /// A `door:: read` definition plus a `boards::` tree to invoke it from
+    fn bbs_doc(invocation: &'static str) -> yrs::Doc {
+        doc_with(&[
+            ("def", None, "door:: read"),
+            ("tpl", Some("def"), "template:: floatctl bbs board read {board} {args}"),
+            ("inh", Some("def"), "inherits:: board"),
+            ("boards", None, "boards::"),
+            ("board", Some("boards"), "consciousness-tech::"),
+            ("call", Some("board"), invocation),
+        ])
+    }
+
+    #[test]
+    fn parses_placeholders_and_literal_braces() {
+        use TemplatePart::*;
+        assert_eq!(
+            parse_template("echo {{x}} {Board} {args}"),
+            vec![
+                Literal("echo {x} ".into()),
+                Placeholder("board".into()),
+                Literal(" ".into()),
+                Placeholder("args".into()),
+            ]
+        );
+        assert_eq!(
+            parse_template("awk '{print $1}'"),
+            vec![Literal("awk '{print $1}'".into())]
+        );
+    }
+
+    #[test]
+    fn expands_bare_label_ancestor_and_args() {
+        let doc = bbs_doc("read:: 47");
+        assert_eq!(
+            resolve(&doc, "call").unwrap().as_deref(),
+            Some("floatctl bbs board read consciousness-tech 47")
+        );
+    }
+
+    #[test]
+    fn explicit_param_beats_bare_label() {
+        let doc = doc_with(&[
+            ("def", None, "door:: read"),
+            ("tpl", Some("def"), "template:: read {board} {args}"),
+            ("inh", Some("def"), "inherits:: board"),
+            ("outer", None, "board:: general"),
+            ("label", Some("outer"), "misc::"),
+            ("call", Some("label"), "read::"),
+        ]);
+        assert_eq!(resolve(&doc, "call").unwrap().as_deref(), Some("read general"));
+    }
+
+    #[test]
+    fn missing_param_is_an_error() {
+        let doc = doc_with(&[
+            ("def", None, "door:: read"),
+            ("tpl", Some("def"), "template:: read {board}"),
+            ("inh", Some("def"), "inherits:: board"),
+            ("call", None, "read:: 1"),
+        ]);
+        let err = resolve(&doc, "call").unwrap_err();
+        assert!(err.contains("needs `board`"), "{}", err);
+    }
+
+    #[test]
+    fn sh_blocks_and_plain_text_bypass_doors() {
+        let doc = doc_with(&[
+            ("sh", None, "sh:: ls -la"),
+            ("fmt", None, "sh::json cat data.json"),
+            ("text", None, "just some notes"),
+            ("label", None, "unknown:: thing"),
+        ]);
+        assert_eq!(resolve(&doc, "sh").unwrap().as_deref(), Some("ls -la"));
+        assert_eq!(resolve(&doc, "fmt").unwrap().as_deref(), Some("cat data.json"));
+        assert_eq!(resolve(&doc, "text").unwrap(), None);
+        assert_eq!(resolve(&doc, "label").unwrap(), None);
+    }
+
+    #[test]
+    fn expansions_get_straight_quotes() {
+        let doc = doc_with(&[
+            ("def", None, "door:: say"),
+            ("tpl", Some("def"), "template:: echo \u{201C}{args}\u{201D}"),
+            ("call", None, "say:: it\u{2019}s \u{2014}done"),
+        ]);
+        assert_eq!(resolve(&doc, "call").unwrap().as_deref(), Some("echo \"it's -done\""));
+    }
+
+    #[test]
+    fn runner_prefixes_are_reserved() {
+        let doc = doc_with(&[
+            ("def", None, "door:: py"),
+            ("tpl", Some("def"), "template:: python3 {args}"),
+        ]);
+        assert!(index(&doc).errors[0].message.contains("built-in prefix"));
+    }
+
+    #[test]
+    fn conflicting_definitions_are_not_expanded() {
+        let doc = doc_with(&[
+            ("a", None, "door:: hi"),
+            ("a-tpl", Some("a"), "template:: echo a"),
+            ("b", None, "door:: hi"),
+            ("b-tpl", Some("b"), "template:: echo b"),
+            ("call", None, "hi::"),
+        ]);
+        let index = index(&doc);
+        assert!(index.doors.is_empty());
+        assert_eq!(index.conflicts[0].block_ids, vec!["a", "b"]);
+        assert!(resolve(&doc, "call").unwrap_err().contains("more than once"));
+    }
+
+    #[test]
+    fn invalid_definitions_are_reported() {
+        let doc = doc_with(&[
+            ("reserved", None, "door:: sh"),
+            ("reserved-tpl", Some("reserved"), "template:: echo"),
+            ("no-tpl", None, "door:: empty"),
+            ("undeclared", None, "door:: loose"),
+            ("undeclared-tpl", Some("undeclared"), "template:: echo {board}"),
+        ]);
+        let index = index(&doc);
+        assert!(index.doors.is_empty());
+        let messages: Vec<&str> = index.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages.iter().any(|m| m.contains("needs a template")));
+        assert!(messages.iter().any(|m| m.contains("built-in prefix")));
+        assert!(messages.iter().any(|m| m.contains("doesn't list it in inherits")));
+    }
+}
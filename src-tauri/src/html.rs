@@ -0,0 +1,565 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+use quick_xml::escape::escape;
+use yrs::{ReadTxn, Transact};
+use crate::markdown::{is_table, options, prop_strings};
+use crate::output::OUTPUT_TYPES;
+use crate::{block_ref_pattern, read_trees, split_prefix, AppState, BlockData, BlockTree};
+
+// ═══════════════════════════════════════════════════════════════
+// HTML
+// ═══════════════════════════════════════════════════════════════
+//
+// A read-only page of blocks. Each block is an element with its ID as
+// the element's id, so `((id))` references become links that land on
+// it, and a block with children folds them away in a <details> (closed
+// if the block is collapsed):
+//
+//   <details class="block block-sh" id="a1b2" open>
+//     <summary><div class="content">...</div></summary>
+//     <div class="children">...</div>
+//   </details>
+//
+// Content is rendered as markdown, with raw HTML shown as text, and
+// links and images kept only for http(s), mailto and relative URLs. The
+// page carries its own styles and script, so it stands alone; a site
+// export is an index plus one page per top-level block, and links to
+// a block on another page go to `page.html#id`.
+
+/// Content prefixes and the block type each means, as the editor reads them
+const PREFIX_TYPES: &[(&str, &str)] = &[
+    ("sh::", "sh"),
+    ("term::", "sh"),
+    ("ai::", "ai"),
+    ("chat::", "ai"),
+    ("ctx::", "ctx"),
+    ("dispatch::", "dispatch"),
+    ("web::", "web"),
+    ("link::", "web"),
+    ("```", "code"),
+];
+
+/// Page file of a site's index
+const INDEX_PAGE: &str = "index.html";
+
+/// Styles, after the app's own block colors
+const STYLE: &str = r#"
+:root { color-scheme: dark; }
+body { margin: 0; background: #0a0a0a; color: #e5e5e5; font: 14px/1.6 'JetBrains Mono', 'SF Mono', monospace; }
+main { max-width: 56rem; margin: 0 auto; padding: 2rem 1.5rem 4rem; }
+nav { max-width: 56rem; margin: 0 auto; padding: 1rem 1.5rem 0; }
+a { color: #22d3ee; }
+h1.title { color: #22d3ee; font-size: 1.6rem; margin: 0 0 1.5rem; }
+.block { margin: 0.15rem 0; scroll-margin-top: 2rem; }
+.block:target > .content, .block:target > summary { background: #164e63; border-radius: 4px; }
+.children { margin-left: 0.45rem; padding-left: 1rem; border-left: 1px solid #262626; }
+summary { cursor: pointer; list-style: none; display: flex; gap: 0.4rem; }
+summary::-webkit-details-marker { display: none; }
+summary::before { content: '▸'; color: #22d3ee; }
+details[open] > summary::before { content: '▾'; }
+div.block::before { content: '•'; color: #525252; float: left; margin-right: 0.5rem; }
+.content { min-width: 0; overflow-wrap: anywhere; }
+.content > p { margin: 0.1rem 0; }
+.content > :is(input, .prefix) + p { display: inline; }
+.content h1 { color: #22d3ee; font-size: 1.4rem; margin: 0.3rem 0; }
+.content h2 { color: #e879f9; font-size: 1.2rem; margin: 0.3rem 0; }
+.content h3 { color: #a78bfa; font-size: 1.05rem; margin: 0.2rem 0; }
+.content strong { color: #f0abfc; }
+.content code { background: #262626; color: #67e8f9; padding: 0.05rem 0.3rem; border-radius: 4px; }
+.content pre { background: #171717; padding: 0.6rem 0.8rem; border-radius: 4px; overflow-x: auto; margin: 0.2rem 0; }
+.content pre code { background: none; padding: 0; }
+.content blockquote { border-left: 2px solid #525252; margin: 0.2rem 0; padding-left: 0.6rem; color: #a3a3a3; font-style: italic; }
+.content table { border-collapse: collapse; margin: 0.2rem 0; }
+.content th, .content td { border: 1px solid #404040; padding: 0.2rem 0.6rem; text-align: left; }
+.prefix { color: #737373; }
+.ref { color: #e879f9; text-decoration: none; border-bottom: 1px dotted; }
+.ref.missing { color: #737373; border: none; }
+.block-sh > .content, .block-sh > summary .content { color: #34d399; }
+.block-sh code { color: #34d399; background: none; padding: 0; }
+.block-output > .content, .block-error > .content, .block-truncated > .content, .block-run > summary .content { font-size: 0.9em; }
+.block-output pre, .block-error pre, .block-truncated pre, .block-run pre { background: none; padding: 0; margin: 0; white-space: pre-wrap; }
+.block-output pre { color: #d4d4d4; }
+.block-error pre { color: #f87171; }
+.block-truncated pre { color: #737373; font-style: italic; }
+.block-run > summary pre { color: #737373; }
+.block-ctx > .content, .block-ctx > summary .content { color: #fbbf24; }
+.block-ai > .content, .block-ai > summary .content { color: #a78bfa; }
+.block-quote .content { border-left: 2px solid #525252; padding-left: 0.5rem; color: #a3a3a3; font-style: italic; }
+ul.pages { padding-left: 1.2rem; }
+"#;
+
+/// Opens the folds around a linked block, so following a link shows it
+const SCRIPT: &str = r#"
+function reveal() {
+  var target = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));
+  for (var el = target && target.parentElement; el; el = el.parentElement) {
+    if (el.tagName === 'DETAILS') el.open = true;
+  }
+  if (target) target.scrollIntoView();
+}
+addEventListener('hashchange', reveal);
+addEventListener('DOMContentLoaded', reveal);
+"#;
+
+/// Link and image schemes that may be rendered - anything else (javascript:, file:, data:...) stays text
+const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Whether a link or image URL may be rendered: a safe scheme, or none (relative, `#id`, `page.html#id`)
+fn safe_url(url: &str) -> bool {
+    // Browsers skip whitespace and control characters in a scheme (`java\tscript:`)
+    let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+    let Some(colon) = url.find(':') else { return true };
+    let scheme = &url[..colon];
+    if scheme.contains(['/', '?', '#']) {
+        return true;
+    }
+    SAFE_SCHEMES.iter().any(|safe| scheme.eq_ignore_ascii_case(safe))
+}
+
+/// Visit a block and all its descendants
+fn walk<'t>(tree: &'t BlockTree, visit: &mut impl FnMut(&'t BlockTree)) {
+    visit(tree);
+    for child in &tree.children {
+        walk(child, visit);
+    }
+}
+
+/// A block's first line, as plain text for links and titles (a code block's first line of code)
+fn link_text(block: &BlockData) -> String {
+    let content = block.content();
+    let skip = usize::from(content.starts_with("```") || content.starts_with("~~~"));
+    let line = content.lines().nth(skip).unwrap_or_default().trim_start_matches('#').trim();
+    if line.is_empty() {
+        block.id().to_string()
+    } else {
+        line.to_string()
+    }
+}
+
+/// A block's type for styling - going by its content when it's stored as plain text
+fn block_kind(block: &BlockData) -> String {
+    let kind = match block.get_str("type") {
+        Some(kind) if !kind.is_empty() && kind != "text" => kind.to_string(),
+        _ => {
+            let content = block.content().trim_start().to_lowercase();
+            let prefixed = PREFIX_TYPES.iter().find(|(prefix, _)| content.starts_with(prefix));
+            prefixed.map(|(_, kind)| *kind).unwrap_or("text").to_string()
+        }
+    };
+    kind.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_').collect()
+}
+
+/// File names for a site's pages, from their titles
+fn page_names(trees: &[BlockTree]) -> Vec<String> {
+    let mut taken: HashSet<String> = HashSet::from(["index".to_string()]);
+    trees
+        .iter()
+        .map(|tree| {
+            let title = link_text(&tree.block).to_lowercase();
+            let words: Vec<&str> = title.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+            let slug: String = words.join("-").chars().take(60).collect();
+            let base = match slug.trim_end_matches('-') {
+                "" => "page".to_string(),
+                slug => slug.to_string(),
+            };
+            let mut name = base.clone();
+            let mut n = 2;
+            while !taken.insert(name.clone()) {
+                name = format!("{}-{}", base, n);
+                n += 1;
+            }
+            format!("{}.html", name)
+        })
+        .collect()
+}
+
+/// Where each exported block is, for `((id))` links
+#[derive(Default)]
+struct Links {
+    /// Link text for each block
+    titles: HashMap<String, String>,
+    /// Page file of each block, in a site export
+    pages: HashMap<String, String>,
+}
+
+impl Links {
+    fn add(&mut self, tree: &BlockTree, page: Option<&str>) {
+        walk(tree, &mut |tree| {
+            let id = tree.block.id().to_string();
+            self.titles.insert(id.clone(), link_text(&tree.block));
+            if let Some(page) = page {
+                self.pages.insert(id, page.to_string());
+            }
+        });
+    }
+
+    /// Where a link to a block goes from a page (None if the block isn't exported)
+    fn href(&self, id: &str, page: Option<&str>) -> Option<String> {
+        if !self.titles.contains_key(id) {
+            return None;
+        }
+        match (self.pages.get(id), page) {
+            (Some(target), Some(page)) if target != page => Some(format!("{}#{}", target, id)),
+            _ => Some(format!("#{}", id)),
+        }
+    }
+}
+
+struct Renderer<'l> {
+    links: &'l Links,
+    refs: regex::Regex,
+    /// The page being written, in a site export
+    page: Option<&'l str>,
+}
+
+impl<'l> Renderer<'l> {
+    fn new(links: &'l Links, page: Option<&'l str>) -> Self {
+        Self { links, refs: block_ref_pattern(), page }
+    }
+
+    /// A `((id))` reference as a link to its block
+    fn ref_html(&self, id: &str) -> String {
+        match (self.links.href(id, self.page), self.links.titles.get(id)) {
+            (Some(href), Some(title)) => format!("<a class=\"ref\" href=\"{}\">{}</a>", escape(&href), escape(title)),
+            _ => format!("<span class=\"ref missing\">(({}))</span>", escape(id)),
+        }
+    }
+
+    /// Markdown as HTML, with references linked and raw HTML shown as text
+    /// A link or image to an unsafe URL keeps only its text
+    fn markdown(&self, text: &str) -> String {
+        let mut events = vec![];
+        // Whether each open link or image was dropped, to drop its end too
+        let mut dropped = vec![];
+        for event in Parser::new_ext(text, options()) {
+            match event {
+                Event::Start(Tag::Link { ref dest_url, .. }) | Event::Start(Tag::Image { ref dest_url, .. }) => {
+                    let safe = safe_url(dest_url);
+                    dropped.push(!safe);
+                    if safe {
+                        events.push(event);
+                    }
+                }
+                Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                    if !dropped.pop().unwrap_or(false) {
+                        events.push(event);
+                    }
+                }
+                Event::Text(text) => {
+                    let text: &str = &text;
+                    let mut last = 0;
+                    for caps in self.refs.captures_iter(text) {
+                        let Some(whole) = caps.get(0) else { continue };
+                        events.push(Event::Text(CowStr::from(text[last..whole.start()].to_string())));
+                        events.push(Event::InlineHtml(CowStr::from(self.ref_html(&caps[1]))));
+                        last = whole.end();
+                    }
+                    events.push(Event::Text(CowStr::from(text[last..].to_string())));
+                }
+                Event::Html(html) | Event::InlineHtml(html) => events.push(Event::Text(html)),
+                other => events.push(other),
+            }
+        }
+        let mut out = String::new();
+        html::push_html(&mut out, events.into_iter());
+        out
+    }
+
+    fn table_html(&self, tree: &BlockTree) -> String {
+        let row = |cells: Vec<String>, tag: &str| -> String {
+            let cells: String = cells.iter().map(|cell| format!("<{}>{}</{}>", tag, escape(cell.as_str()), tag)).collect();
+            format!("<tr>{}</tr>\n", cells)
+        };
+        let mut columns = prop_strings(&tree.block, "columns");
+        if columns.is_empty() {
+            columns = tree.block.content().split(" | ").map(str::to_string).collect();
+        }
+        let rows: String = tree.children.iter().map(|child| row(prop_strings(&child.block, "cells"), "td")).collect();
+        format!("<table>\n<thead>{}</thead>\n<tbody>\n{}</tbody>\n</table>\n", row(columns, "th"), rows)
+    }
+
+    /// A block's own content
+    fn content_html(&self, tree: &BlockTree, kind: &str) -> String {
+        let block = &tree.block;
+        let content = block.content();
+        // Tables (structured output's too) before the rest of run output, shown preformatted
+        if is_table(tree) {
+            return self.table_html(tree);
+        }
+        if OUTPUT_TYPES.contains(&kind) {
+            return format!("<pre>{}</pre>", escape(content));
+        }
+
+        let checkbox = match block.get_bool("checked") {
+            Some(true) => "<input type=\"checkbox\" checked disabled> ",
+            Some(false) => "<input type=\"checkbox\" disabled> ",
+            None => "",
+        };
+        let body = match split_prefix(content) {
+            // Commands as they'd run, not as markdown
+            Some((prefix, command)) if kind == "sh" => {
+                let code = if command.contains('\n') {
+                    format!("<pre><code>{}</code></pre>", escape(command))
+                } else {
+                    format!("<code>{}</code>", escape(command))
+                };
+                format!("<span class=\"prefix\">{}::</span> {}", escape(prefix.as_str()), code)
+            }
+            Some((prefix, rest)) => format!("<span class=\"prefix\">{}::</span> {}", escape(prefix.as_str()), self.markdown(rest)),
+            None => self.markdown(content),
+        };
+        format!("{}{}", checkbox, body)
+    }
+
+    fn block_html(&self, tree: &BlockTree, out: &mut String) {
+        let block = &tree.block;
+        let kind = block_kind(block);
+        let content = self.content_html(tree, &kind);
+        let id = escape(block.id());
+
+        // A table's rows are in its content
+        if tree.children.is_empty() || is_table(tree) {
+            out.push_str(&format!("<div class=\"block block-{}\" id=\"{}\"><div class=\"content\">{}</div></div>\n", kind, id, content));
+            return;
+        }
+
+        let open = if block.get_bool("collapsed").unwrap_or(false) { "" } else { " open" };
+        out.push_str(&format!(
+            "<details class=\"block block-{}\" id=\"{}\"{}>\n<summary><div class=\"content\">{}</div></summary>\n<div class=\"children\">\n",
+            kind, id, open, content
+        ));
+        for child in &tree.children {
+            self.block_html(child, out);
+        }
+        out.push_str("</div>\n</details>\n");
+    }
+
+    fn blocks_html(&self, trees: &[BlockTree]) -> String {
+        let mut out = String::new();
+        for tree in trees {
+            self.block_html(tree, &mut out);
+        }
+        out
+    }
+}
+
+/// A standalone HTML document
+fn page_html(title: &str, header: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}<main>\n{}</main>\n<script>{}</script>\n</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        header,
+        body,
+        SCRIPT
+    )
+}
+
+fn title_html(title: &str) -> String {
+    format!("<h1 class=\"title\">{}</h1>\n", escape(title))
+}
+
+/// Render blocks as one page, headed by its title with `heading`
+fn render_page(trees: &[BlockTree], title: &str, heading: bool) -> String {
+    let mut links = Links::default();
+    for tree in trees {
+        links.add(tree, None);
+    }
+    let body = Renderer::new(&links, None).blocks_html(trees);
+    let header = if heading { title_html(title) } else { String::new() };
+    page_html(title, "", &format!("{}{}", header, body))
+}
+
+/// Render blocks as a site: an index, then a page per block
+/// `index_block` is the block the site is of, when it's a subtree
+/// Returns each page's file name and HTML
+fn render_site(pages: &[BlockTree], title: &str, index_block: Option<&BlockTree>) -> Vec<(String, String)> {
+    let names = page_names(pages);
+    let mut links = Links::default();
+    if let Some(index_block) = index_block {
+        let mut root = index_block.clone();
+        root.children.clear();
+        links.add(&root, Some(INDEX_PAGE));
+    }
+    for (tree, name) in pages.iter().zip(&names) {
+        links.add(tree, Some(name));
+    }
+
+    let items: String = pages
+        .iter()
+        .zip(&names)
+        .map(|(tree, name)| format!("<li><a href=\"{}\">{}</a></li>\n", escape(name.as_str()), escape(link_text(&tree.block).as_str())))
+        .collect();
+    let index = format!("{}<ul class=\"pages\">\n{}</ul>\n", title_html(title), items);
+    let mut files = vec![(INDEX_PAGE.to_string(), page_html(title, "", &index))];
+
+    let nav = format!("<nav><a href=\"{}\">← {}</a></nav>\n", INDEX_PAGE, escape(title));
+    for (tree, name) in pages.iter().zip(&names) {
+        let body = Renderer::new(&links, Some(name)).blocks_html(std::slice::from_ref(tree));
+        files.push((name.clone(), page_html(&link_text(&tree.block), &nav, &body)));
+    }
+    files
+}
+
+/// The blocks to export, and the export's title: the subtree's first line, or the workspace name
+fn export_trees(state: &AppState, block_id: Option<&str>) -> Result<(Vec<BlockTree>, String), String> {
+    let trees = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        read_trees(&blocks, &txn, block_id)?
+    };
+    let title = match (block_id, trees.first()) {
+        (Some(_), Some(tree)) => link_text(&tree.block),
+        _ => state.workspace_name.lock().map_err(|e| e.to_string())?.clone(),
+    };
+    Ok((trees, title))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Export a block's subtree, or the whole workspace, as one self-contained HTML page
+/// Writes it to `path` if given; returns the page either way
+#[tauri::command]
+pub fn export_html(state: tauri::State<'_, AppState>, block_id: Option<String>, path: Option<String>) -> Result<String, String> {
+    let (trees, title) = export_trees(&state, block_id.as_deref())?;
+    // A subtree's own block is its heading
+    let page = render_page(&trees, &title, block_id.is_none());
+    if let Some(path) = path {
+        fs::write(&path, &page).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(page)
+}
+
+/// Export a block's subtree, or the whole workspace, as a folder of HTML pages -
+/// one per top-level block (or per child of the block), plus an index
+/// Returns the paths written
+#[tauri::command]
+pub fn export_html_site(state: tauri::State<'_, AppState>, block_id: Option<String>, path: String) -> Result<Vec<String>, String> {
+    let (trees, title) = export_trees(&state, block_id.as_deref())?;
+    let files = match (&block_id, trees.first()) {
+        (Some(_), Some(tree)) => render_site(&tree.children, &title, Some(tree)),
+        _ => render_site(&trees, &title, None),
+    };
+
+    let dir = Path::new(&path);
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut written = vec![];
+    for (name, page) in files {
+        let file = dir.join(&name);
+        fs::write(&file, page).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+        written.push(file.display().to_string());
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{doc_with, set_field};
+
+    fn trees(blocks: &[(&str, Option<&str>, &str)]) -> Vec<BlockTree> {
+        let doc = doc_with(blocks);
+        let txn = doc.transact();
+        read_trees(&txn.get_map("blocks").unwrap(), &txn, None).unwrap()
+    }
+
+    #[test]
+    fn run_output_renders_by_type() {
+        let doc = doc_with(&[
+            ("ls", None, "sh:: ls -l | jc --ls"),
+            ("run", Some("ls"), "2024-01-05 09:00:00 · exit 0"),
+            ("table", Some("run"), "name | size"),
+            ("row", Some("table"), "a.txt | 12"),
+            ("err", Some("run"), "ls: <missing>: No such file"),
+        ]);
+        for (id, block_type) in [("run", "run"), ("table", "table"), ("row", "row"), ("err", "error")] {
+            set_field(&doc, id, "type", block_type);
+        }
+        set_field(&doc, "table", "columns", yrs::Any::from(vec!["name", "size"]));
+        set_field(&doc, "row", "cells", yrs::Any::from(vec!["a.txt", "12"]));
+
+        let txn = doc.transact();
+        let trees = read_trees(&txn.get_map("blocks").unwrap(), &txn, None).unwrap();
+        let html = render_page(&trees, "Runs", false);
+
+        assert!(html.contains("<summary><div class=\"content\"><pre>2024-01-05 09:00:00 · exit 0</pre></div></summary>"), "{}", html);
+        assert!(html.contains("<thead><tr><th>name</th><th>size</th></tr>\n</thead>\n<tbody>\n<tr><td>a.txt</td><td>12</td></tr>\n"), "{}", html);
+        assert!(html.contains("<pre>ls: &lt;missing&gt;: No such file</pre>"), "{}", html);
+        assert!(!html.contains("id=\"row\""), "{}", html);
+    }
+
+    #[test]
+    fn markdown_is_escaped_and_unsafe_links_stay_text() {
+        let trees = trees(&[
+            ("a", None, "<script>alert(1)</script>\n\nTom & <b>Jerry</b> **bold**"),
+            ("b", None, "[x](javascript:alert(1)) [y](JavaScript:alert(2)) ![pic](data:text/html,hi) [f](file:///etc/passwd)"),
+            ("c", None, "[web](https://example.com) [mail](mailto:me@example.com) [top](#a) [other](other.html#a) [rel](notes/a:b.md)"),
+        ]);
+        let html = render_page(&trees, "Page", false);
+
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"), "{}", html);
+        assert!(html.contains("Tom &amp; &lt;b&gt;Jerry&lt;/b&gt; <strong>bold</strong>"), "{}", html);
+        assert!(!html.contains("<script>alert"), "{}", html);
+
+        assert!(html.contains("<p>x y pic f</p>"), "{}", html);
+        for scheme in ["javascript:", "JavaScript:", "data:", "file:"] {
+            assert!(!html.contains(&format!("=\"{}", scheme)), "{}", html);
+        }
+
+        for link in [
+            "<a href=\"https://example.com\">web</a>",
+            "<a href=\"mailto:me@example.com\">mail</a>",
+            "<a href=\"#a\">top</a>",
+            "<a href=\"other.html#a\">other</a>",
+            "<a href=\"notes/a:b.md\">rel</a>",
+        ] {
+            assert!(html.contains(link), "{} in {}", link, html);
+        }
+    }
+
+    #[test]
+    fn site_refs_link_across_pages() {
+        let trees = trees(&[
+            ("a", None, "Alpha"),
+            ("a1", Some("a"), "See ((b1)), ((a2)) and ((gone))"),
+            ("a2", Some("a"), "Second"),
+            ("b", None, "Beta"),
+            ("b1", Some("b"), "Target"),
+        ]);
+        let files = render_site(&trees, "Site", None);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["index.html", "alpha.html", "beta.html"]);
+
+        let alpha = &files[1].1;
+        assert!(alpha.contains("<a class=\"ref\" href=\"beta.html#b1\">Target</a>"), "{}", alpha);
+        assert!(alpha.contains("<a class=\"ref\" href=\"#a2\">Second</a>"), "{}", alpha);
+        assert!(alpha.contains("<span class=\"ref missing\">((gone))</span>"), "{}", alpha);
+        assert!(!alpha.contains("id=\"b1\""), "{}", alpha);
+        assert!(files[2].1.contains("id=\"b1\""), "{}", files[2].1);
+        assert!(files[0].1.contains("<a href=\"alpha.html\">Alpha</a>"), "{}", files[0].1);
+    }
+
+    #[test]
+    fn page_names_are_unique() {
+        let trees = trees(&[
+            ("a", None, "Notes"),
+            ("b", None, "notes!"),
+            ("c", None, "# Notes"),
+            ("d", None, "Index"),
+            ("e", None, "!!!"),
+            ("f", None, "???"),
+            ("g", None, "Notes 2"),
+        ]);
+        assert_eq!(
+            page_names(&trees),
+            ["notes.html", "notes-2.html", "notes-3.html", "index-2.html", "page.html", "page-2.html", "notes-2-2.html"]
+        );
+    }
+}
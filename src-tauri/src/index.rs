@@ -0,0 +1,153 @@
+use std::collections::{BTreeSet, HashSet};
+use std::sync::{Arc, Mutex};
+use yrs::types::{Event, PathSegment};
+use yrs::{DeepObservable, Doc, Map, ReadTxn, Subscription, Transact, TransactionMut};
+use crate::{read_block, BlockData};
+
+// ═══════════════════════════════════════════════════════════════
+// BLOCK INDEX
+// ═══════════════════════════════════════════════════════════════
+//
+// The scheduler and the watcher look at their blocks every tick. Instead
+// of reading the whole doc each time, they keep an index of the blocks
+// that matter to them: a deep observer on the `blocks` map records which
+// blocks changed, and only those are re-read on the next look. The doc is
+// scanned in full once when the index first sees it (startup, or after a
+// workspace switch replaces it).
+
+/// IDs of the blocks matching a predicate, kept current from doc changes
+pub struct BlockIndex {
+    matches: fn(&BlockData) -> bool,
+    state: Mutex<IndexState>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    /// GUID of the doc being followed
+    doc: Option<String>,
+    /// Keeps the observer on that doc alive
+    _subscription: Option<Subscription>,
+    /// Blocks added, changed or removed since the last look
+    changed: Arc<Mutex<HashSet<String>>>,
+    ids: BTreeSet<String>,
+}
+
+impl BlockIndex {
+    pub fn new(matches: fn(&BlockData) -> bool) -> Self {
+        Self { matches, state: Mutex::new(IndexState::default()) }
+    }
+
+    /// The matching block IDs, caught up with changes to the doc
+    /// Call with no transaction open on `doc`
+    pub fn ids(&self, doc: &Doc) -> Result<Vec<String>, String> {
+        let mut state = self.state.lock().map_err(|e| e.to_string())?;
+        let guid = doc.guid().to_string();
+
+        if state.doc.as_deref() != Some(guid.as_str()) {
+            let blocks = doc.get_or_insert_map("blocks");
+            let changed = Arc::new(Mutex::new(HashSet::new()));
+            let sink = changed.clone();
+            let subscription = blocks.observe_deep(move |txn, events| {
+                if let Ok(mut sink) = sink.lock() {
+                    events.iter().for_each(|event| record_change(txn, event, &mut sink));
+                }
+            });
+
+            let txn = doc.transact();
+            let ids = blocks
+                .keys(&txn)
+                .filter(|id| read_block(&blocks, &txn, id).is_some_and(|b| (self.matches)(&b)))
+                .map(str::to_string)
+                .collect();
+            *state = IndexState { doc: Some(guid), _subscription: Some(subscription), changed, ids };
+        } else {
+            let changed: Vec<String> = state.changed.lock().map_err(|e| e.to_string())?.drain().collect();
+            if !changed.is_empty() {
+                let txn = doc.transact();
+                let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+                for id in changed {
+                    if read_block(&blocks, &txn, &id).is_some_and(|b| (self.matches)(&b)) {
+                        state.ids.insert(id);
+                    } else {
+                        state.ids.remove(&id);
+                    }
+                }
+            }
+        }
+
+        Ok(state.ids.iter().cloned().collect())
+    }
+}
+
+/// Note which block an event on the `blocks` map (or inside one of its blocks) touched
+fn record_change(txn: &TransactionMut, event: &Event, changed: &mut HashSet<String>) {
+    match event.path().front() {
+        // Inside a block stored as a Y.Map
+        Some(PathSegment::Key(id)) => {
+            changed.insert(id.to_string());
+        }
+        Some(PathSegment::Index(_)) => {}
+        // The map itself: blocks set (plain objects are replaced whole) or removed
+        None => {
+            if let Event::Map(event) = event {
+                changed.extend(event.keys(txn).keys().map(|id| id.to_string()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{MapPrelim, WriteTxn};
+    use crate::split_prefix;
+    use crate::testing::{doc_with, set_field};
+
+    fn is_every(block: &BlockData) -> bool {
+        matches!(split_prefix(block.content()), Some((prefix, _)) if prefix == "every")
+    }
+
+    #[test]
+    fn follows_edits_additions_and_removals() {
+        let doc = doc_with(&[("a", None, "every:: 1m"), ("b", None, "notes")]);
+        let index = BlockIndex::new(is_every);
+        assert_eq!(index.ids(&doc).unwrap(), vec!["a"]);
+
+        set_field(&doc, "b", "content", "every:: 5m");
+        set_field(&doc, "a", "content", "done");
+        assert_eq!(index.ids(&doc).unwrap(), vec!["b"]);
+
+        {
+            let mut txn = doc.transact_mut();
+            txn.get_or_insert_map("blocks").remove(&mut txn, "b");
+        }
+        assert!(index.ids(&doc).unwrap().is_empty());
+    }
+
+    #[test]
+    fn follows_blocks_stored_as_y_maps() {
+        let doc = doc_with(&[]);
+        let index = BlockIndex::new(is_every);
+        assert!(index.ids(&doc).unwrap().is_empty());
+
+        let block = {
+            let mut txn = doc.transact_mut();
+            let blocks = txn.get_or_insert_map("blocks");
+            blocks.insert(&mut txn, "m", MapPrelim::<String>::from([
+                ("id".to_string(), "m".to_string()),
+                ("content".to_string(), "text".to_string()),
+            ]))
+        };
+        assert!(index.ids(&doc).unwrap().is_empty());
+
+        block.insert(&mut doc.transact_mut(), "content", "every:: 1h");
+        assert_eq!(index.ids(&doc).unwrap(), vec!["m"]);
+    }
+
+    #[test]
+    fn rescans_a_replaced_doc() {
+        let index = BlockIndex::new(is_every);
+        assert_eq!(index.ids(&doc_with(&[("a", None, "every:: 1m")])).unwrap(), vec!["a"]);
+        assert_eq!(index.ids(&doc_with(&[("b", None, "every:: 1m")])).unwrap(), vec!["b"]);
+    }
+}
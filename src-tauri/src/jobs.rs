@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::sync::Semaphore;
+use yrs::{ReadTxn, Transact};
+use crate::output::OUTPUT_TYPES;
+use crate::{execute, read_block, resolve_execution, walk_subtree, AppState, Placement};
+
+// ═══════════════════════════════════════════════════════════════
+// JOB QUEUE
+// ═══════════════════════════════════════════════════════════════
+//
+// `run_subtree` turns an outline into a runnable notebook: every executable
+// block under a block becomes a job, started in document order, one at a
+// time or up to `concurrency` at once. Jobs go through the same policy as a
+// single run, and each one reports through `job-status` events carrying the
+// doc update, so the UI can follow along and apply the output.
+
+/// Event emitted when a job changes state
+const JOB_STATUS_EVENT: &str = "job-status";
+
+/// Event emitted when every job in a queue is done
+const QUEUE_DONE_EVENT: &str = "job-queue-done";
+
+/// Where a job is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Complete,
+    /// Exited non-zero, or couldn't run at all
+    Failed,
+    /// Held by the workspace policy until approved
+    Confirm,
+    /// Not run because an earlier job failed, or the block stopped being executable
+    Skipped,
+    Cancelled,
+}
+
+/// How a queue runs its jobs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueueOptions {
+    /// Jobs running at once (1 = in order, each waiting for the last)
+    pub concurrency: usize,
+    /// Don't start more jobs once one fails (or waits for confirmation)
+    pub stop_on_failure: bool,
+    /// Where each job's output goes
+    pub placement: Placement,
+}
+
+impl Default for QueueOptions {
+    fn default() -> Self {
+        Self { concurrency: 1, stop_on_failure: false, placement: Placement::default() }
+    }
+}
+
+/// A queue as started
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueInfo {
+    pub queue_id: String,
+    /// Blocks that will run, in document order
+    pub block_ids: Vec<String>,
+}
+
+/// Payload of a `job-status` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobEvent {
+    pub queue_id: String,
+    pub block_id: String,
+    /// Position in the queue
+    pub index: usize,
+    pub status: JobStatus,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    /// Updated Y.Doc state as base64, once the job has run
+    pub state: Option<String>,
+}
+
+/// Payload of a `job-queue-done` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueDone {
+    pub queue_id: String,
+    /// Final status of each job, in queue order
+    pub statuses: Vec<JobStatus>,
+}
+
+/// Queues still running, with the flag that cancels them
+#[derive(Default)]
+pub struct JobQueues {
+    queues: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+/// Executable blocks under (and including) a block, in document order
+fn collect_jobs(state: &AppState, block_id: &str) -> Result<Vec<String>, String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let txn = doc.transact();
+    let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+    if read_block(&blocks, &txn, block_id).is_none() {
+        return Err(format!("Block {} not found", block_id));
+    }
+
+    let mut candidates = vec![];
+    walk_subtree(&blocks, &txn, block_id, &mut |block, _| {
+        if !OUTPUT_TYPES.contains(&block.get_str("type").unwrap_or_default()) {
+            candidates.push(block.id().to_string());
+        }
+    });
+
+    // A block that fails to resolve (a broken door) is still a job - it fails when its turn comes
+    Ok(candidates
+        .into_iter()
+        .filter(|id| !matches!(resolve_execution(&blocks, &txn, id), Ok(None)))
+        .collect())
+}
+
+impl JobEvent {
+    fn new(queue_id: &str, block_id: &str, index: usize, status: JobStatus) -> Self {
+        Self {
+            queue_id: queue_id.to_string(),
+            block_id: block_id.to_string(),
+            index,
+            status,
+            exit_code: None,
+            error: None,
+            state: None,
+        }
+    }
+}
+
+fn emit<S: Serialize + Clone>(app: &tauri::AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        log::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// How a run went, from the status and exit code it left on its block
+fn outcome(state: &AppState, block_id: &str) -> Result<(JobStatus, Option<i32>), String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let txn = doc.transact();
+    let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+    let block = read_block(&blocks, &txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
+
+    let status = match block.get_str("status") {
+        Some("complete") => JobStatus::Complete,
+        Some("confirm") => JobStatus::Confirm,
+        _ => JobStatus::Failed,
+    };
+    Ok((status, block.get_i64("exitCode").map(|code| code as i32)))
+}
+
+/// Run one job, resolving the block again so edits made while it was queued count
+async fn run_job(app: &tauri::AppHandle, queue_id: &str, index: usize, block_id: &str, placement: Placement) -> JobEvent {
+    let state = app.state::<AppState>();
+    let mut event = JobEvent::new(queue_id, block_id, index, JobStatus::Failed);
+
+    let result = async {
+        let execution = {
+            let doc = state.doc.lock().map_err(|e| e.to_string())?;
+            let txn = doc.transact();
+            let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+            resolve_execution(&blocks, &txn, block_id)?
+        };
+        match execution {
+            Some(execution) => execute(app, &state, block_id, execution, placement).await.map(Some),
+            None => Ok(None),
+        }
+    }
+    .await;
+
+    match result {
+        Ok(Some(doc_state)) => match outcome(&state, block_id) {
+            Ok((status, exit_code)) => {
+                event.status = status;
+                event.exit_code = exit_code;
+                event.state = Some(doc_state);
+            }
+            Err(e) => event.error = Some(e),
+        },
+        Ok(None) => event.status = JobStatus::Skipped,
+        Err(e) => event.error = Some(e),
+    }
+    event
+}
+
+/// Start each job in order as a slot frees up, then report the queue as done
+async fn run_queue(app: tauri::AppHandle, queue_id: String, block_ids: Vec<String>, options: QueueOptions, cancelled: Arc<AtomicBool>) {
+    let slots = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let halted = Arc::new(AtomicBool::new(false));
+    let mut statuses = vec![JobStatus::Queued; block_ids.len()];
+    let mut running = vec![];
+
+    for (index, block_id) in block_ids.into_iter().enumerate() {
+        let Ok(slot) = slots.clone().acquire_owned().await else { break };
+
+        // Checked after waiting for a slot, so an in-order queue sees the previous job's result
+        let held = if cancelled.load(Ordering::SeqCst) {
+            Some(JobStatus::Cancelled)
+        } else if halted.load(Ordering::SeqCst) {
+            Some(JobStatus::Skipped)
+        } else {
+            None
+        };
+        if let Some(status) = held {
+            statuses[index] = status;
+            emit(&app, JOB_STATUS_EVENT, JobEvent::new(&queue_id, &block_id, index, status));
+            continue;
+        }
+
+        emit(&app, JOB_STATUS_EVENT, JobEvent::new(&queue_id, &block_id, index, JobStatus::Running));
+        let (app, queue_id, halted) = (app.clone(), queue_id.clone(), halted.clone());
+        let (stop_on_failure, placement) = (options.stop_on_failure, options.placement);
+        running.push((index, tokio::spawn(async move {
+            let event = run_job(&app, &queue_id, index, &block_id, placement).await;
+            if stop_on_failure && matches!(event.status, JobStatus::Failed | JobStatus::Confirm) {
+                halted.store(true, Ordering::SeqCst);
+            }
+            let status = event.status;
+            emit(&app, JOB_STATUS_EVENT, event);
+            drop(slot);
+            status
+        })));
+    }
+
+    for (index, job) in running {
+        statuses[index] = job.await.unwrap_or(JobStatus::Failed);
+    }
+
+    if let Ok(mut queues) = app.state::<AppState>().jobs.queues.lock() {
+        queues.remove(&queue_id);
+    }
+    emit(&app, QUEUE_DONE_EVENT, QueueDone { queue_id, statuses });
+}
+
+/// Queue every executable block under (and including) a block and start running them
+pub fn start_queue(app: tauri::AppHandle, state: &AppState, block_id: &str, options: QueueOptions) -> Result<QueueInfo, String> {
+    let block_ids = collect_jobs(state, block_id)?;
+    let queue_id = uuid::Uuid::new_v4().simple().to_string();
+    let cancelled = Arc::new(AtomicBool::new(false));
+
+    state.jobs.queues.lock().map_err(|e| e.to_string())?.insert(queue_id.clone(), cancelled.clone());
+    tokio::spawn(run_queue(app, queue_id.clone(), block_ids.clone(), options, cancelled));
+
+    Ok(QueueInfo { queue_id, block_ids })
+}
+
+impl JobQueues {
+    /// Whether a queue still has jobs to run or finish
+    pub fn is_running(&self, queue_id: &str) -> bool {
+        self.queues.lock().map(|queues| queues.contains_key(queue_id)).unwrap_or(false)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Queue every executable block under (and including) a block and start running them
+/// Returns right away - progress arrives as `job-status` and `job-queue-done` events
+#[tauri::command]
+pub async fn run_subtree(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    block_id: String,
+    options: Option<QueueOptions>,
+) -> Result<QueueInfo, String> {
+    start_queue(app, &state, &block_id, options.unwrap_or_default())
+}
+
+/// Stop a queue from starting more jobs (running ones finish)
+#[tauri::command]
+pub fn cancel_queue(state: tauri::State<'_, AppState>, queue_id: String) -> Result<(), String> {
+    let queues = state.jobs.queues.lock().map_err(|e| e.to_string())?;
+    let cancelled = queues.get(&queue_id).ok_or_else(|| format!("No running queue {}", queue_id))?;
+    cancelled.store(true, Ordering::SeqCst);
+    Ok(())
+}
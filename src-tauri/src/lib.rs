@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Mutex;
 use std::path::PathBuf;
 use std::fs;
@@ -9,9 +10,35 @@ use yrs::types::ToJson;
 use serde_json::Value as JsonValue;
 use chrono::Utc;
 use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use std::process::Stdio;
-use pulldown_cmark::{Parser, Event, Tag, TagEnd, HeadingLevel};
+
+mod ansi;
+mod doors;
+mod html;
+mod index;
+mod jobs;
+mod logseq;
+mod markdown;
+mod notify;
+mod opml;
+mod org;
+mod output;
+mod policy;
+mod roam;
+mod runners;
+mod runs;
+mod sandbox;
+mod schedule;
+mod sessions;
+mod settings;
+mod structured;
+mod term;
+mod watch;
+
+#[cfg(test)]
+mod testing;
 
 // ═══════════════════════════════════════════════════════════════
 // PERSISTENCE
@@ -30,6 +57,42 @@ fn get_workspace_path(name: &str) -> PathBuf {
     get_data_dir().join(format!("{}.yjs", name))
 }
 
+/// Get the directory for a workspace's side files (e.g. spilled command output)
+fn get_workspace_dir(name: &str) -> PathBuf {
+    get_data_dir().join(name)
+}
+
+/// Append a record as one JSON line, creating the file (and its directory) if needed
+fn append_jsonl<T: serde::Serialize>(path: &std::path::Path, record: &T) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    }
+
+    let mut line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, line.as_bytes()))
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// Read every record from a JSON lines file (empty if it doesn't exist)
+fn read_jsonl<T: serde::de::DeserializeOwned>(path: &std::path::Path) -> Result<Vec<T>, String> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(text
+        .lines()
+        // A line cut short by a crash shouldn't hide the rest of the file
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
 /// Try to load Y.Doc from a workspace file
 fn load_doc_from_file(name: &str) -> Option<Doc> {
     let path = get_workspace_path(name);
@@ -63,6 +126,13 @@ fn load_doc_from_file(name: &str) -> Option<Doc> {
 pub struct AppState {
     doc: Mutex<Doc>,
     workspace_name: Mutex<String>,
+    sessions: sessions::SessionRegistry,
+    terminals: term::TermRegistry,
+    policy: policy::PendingRuns,
+    jobs: jobs::JobQueues,
+    schedules: schedule::Schedules,
+    watches: watch::Watches,
+    activity: notify::Activity,
 }
 
 /// Create a fresh empty document with default structure
@@ -117,6 +187,7 @@ fn create_empty_doc() -> Doc {
 impl Default for AppState {
     fn default() -> Self {
         let default_workspace = "default";
+        policy::trust_existing_workspaces();
         
         // Try to load from file first
         if let Some(doc) = load_doc_from_file(default_workspace) {
@@ -124,10 +195,18 @@ impl Default for AppState {
             return Self { 
                 doc: Mutex::new(doc),
                 workspace_name: Mutex::new(default_workspace.to_string()),
+                sessions: sessions::SessionRegistry::default(),
+                terminals: term::TermRegistry::default(),
+                policy: policy::PendingRuns::default(),
+                jobs: jobs::JobQueues::default(),
+                schedules: schedule::Schedules::default(),
+                watches: watch::Watches::default(),
+                activity: notify::Activity::default(),
             };
         }
 
         println!("📝 Creating new workspace '{}'", default_workspace);
+        policy::trust_new_workspace(default_workspace);
         let doc = Doc::new();
 
         // Initialize with Y.Doc schema:
@@ -183,8 +262,255 @@ impl Default for AppState {
         Self {
             doc: Mutex::new(doc),
             workspace_name: Mutex::new(default_workspace.to_string()),
+            sessions: sessions::SessionRegistry::default(),
+            terminals: term::TermRegistry::default(),
+            policy: policy::PendingRuns::default(),
+            jobs: jobs::JobQueues::default(),
+            schedules: schedule::Schedules::default(),
+            watches: watch::Watches::default(),
+            activity: notify::Activity::default(),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// BLOCK ACCESS
+// ═══════════════════════════════════════════════════════════════
+
+/// A block's fields, decoded from either a plain object or a Y.Map
+#[derive(Debug, Clone, Default)]
+struct BlockData(HashMap<String, yrs::Any>);
+
+impl BlockData {
+    fn get_str(&self, key: &str) -> Option<&str> {
+        match self.0.get(key) {
+            Some(yrs::Any::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Integer field, whether stored as a BigInt or a JS number
+    fn get_i64(&self, key: &str) -> Option<i64> {
+        match self.0.get(key) {
+            Some(yrs::Any::BigInt(n)) => Some(*n),
+            Some(yrs::Any::Number(n)) => Some(*n as i64),
+            _ => None,
+        }
+    }
+
+    /// Boolean field, None when unset
+    fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.0.get(key) {
+            Some(yrs::Any::Bool(b)) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> &str {
+        self.get_str("id").unwrap_or("")
+    }
+
+    fn content(&self) -> &str {
+        self.get_str("content").unwrap_or("")
+    }
+
+    fn parent_id(&self) -> Option<&str> {
+        self.get_str("parentId")
+    }
+
+    fn child_ids(&self) -> Vec<String> {
+        match self.0.get("childIds") {
+            Some(yrs::Any::Array(arr)) => arr
+                .iter()
+                .filter_map(|a| if let yrs::Any::String(s) = a { Some(s.to_string()) } else { None })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn set(&mut self, key: &str, value: impl Into<yrs::Any>) {
+        self.0.insert(key.to_string(), value.into());
+    }
+
+    fn into_any(self) -> yrs::Any {
+        yrs::Any::Map(Arc::new(self.0))
+    }
+}
+
+/// Read a block by ID
+fn read_block<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, id: &str) -> Option<BlockData> {
+    match blocks.get(txn, id)?.to_json(txn) {
+        yrs::Any::Map(map) => Some(BlockData((*map).clone())),
+        _ => None,
+    }
+}
+
+/// Write a block back as a plain object (the shape the frontend writes too)
+fn write_block(blocks: &yrs::MapRef, txn: &mut yrs::TransactionMut, block: BlockData) {
+    let id = block.id().to_string();
+    blocks.insert(txn, id.as_str(), block.into_any());
+}
+
+/// Change one block's fields in place
+/// Returns the updated Y.Doc state as base64
+fn update_block(state: &AppState, block_id: &str, change: impl FnOnce(&mut BlockData)) -> Result<String, String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let mut txn = doc.transact_mut();
+    let blocks = txn.get_or_insert_map("blocks");
+
+    let mut block = read_block(&blocks, &txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
+    change(&mut block);
+    block.set("updatedAt", yrs::Any::BigInt(Utc::now().timestamp_millis()));
+    write_block(&blocks, &mut txn, block);
+
+    drop(txn);
+
+    let txn = doc.transact();
+    let new_state = txn.encode_state_as_update_v1(&StateVector::default());
+    Ok(BASE64.encode(&new_state))
+}
+
+/// Convert a list of IDs into a childIds value
+fn ids_to_any(ids: &[String]) -> yrs::Any {
+    yrs::Any::Array(Arc::from(
+        ids.iter()
+            .map(|s| yrs::Any::String(s.clone().into()))
+            .collect::<Vec<_>>()
+    ))
+}
+
+/// Split `prefix:: rest` into a lowercased prefix and the trimmed rest
+/// e.g. "Read:: 47" → ("read", "47"), "consciousness-tech::" → ("consciousness-tech", "")
+fn split_prefix(content: &str) -> Option<(String, &str)> {
+    let trimmed = content.trim();
+    let (prefix, rest) = trimmed.split_once("::")?;
+    if prefix.is_empty() || !prefix.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return None;
+    }
+    Some((prefix.to_lowercase(), rest.trim()))
+}
+
+/// Ancestors of a block, nearest first (the block itself is not included)
+fn ancestors<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, id: &str) -> Vec<BlockData> {
+    let mut result = vec![];
+    let mut current = read_block(blocks, txn, id).and_then(|b| b.parent_id().map(str::to_string));
+
+    while let Some(parent_id) = current {
+        // Guard against cycles from concurrent moves
+        if result.iter().any(|b: &BlockData| b.id() == parent_id) {
+            break;
+        }
+        let Some(parent) = read_block(blocks, txn, &parent_id) else { break };
+        current = parent.parent_id().map(str::to_string);
+        result.push(parent);
+    }
+
+    result
+}
+
+/// Depth-first walk of a block and its descendants in document order
+/// The visitor gets each block and its depth relative to `id` (0 = the block itself)
+fn walk_subtree<T: ReadTxn>(
+    blocks: &yrs::MapRef,
+    txn: &T,
+    id: &str,
+    visit: &mut impl FnMut(&BlockData, usize),
+) {
+    fn walk<T: ReadTxn>(
+        blocks: &yrs::MapRef,
+        txn: &T,
+        id: &str,
+        depth: usize,
+        seen: &mut Vec<String>,
+        visit: &mut impl FnMut(&BlockData, usize),
+    ) {
+        // Guard against cycles from concurrent moves
+        if seen.iter().any(|s| s == id) {
+            return;
+        }
+        seen.push(id.to_string());
+
+        let Some(block) = read_block(blocks, txn, id) else { return };
+        visit(&block, depth);
+        for child_id in block.child_ids() {
+            walk(blocks, txn, &child_id, depth + 1, seen, visit);
+        }
+    }
+
+    walk(blocks, txn, id, 0, &mut vec![], visit);
+}
+
+/// IDs of a block and its siblings in order (the parent's childIds, or rootIds at the top)
+fn sibling_ids<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block: &BlockData) -> Vec<String> {
+    match block.parent_id().and_then(|p| read_block(blocks, txn, p)) {
+        Some(parent) => parent.child_ids(),
+        None => txn
+            .get_array("rootIds")
+            .map(|arr| arr.iter(txn).map(|v| v.to_string(txn)).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Delete a block and all its descendants from the blocks map
+/// (the caller updates the parent's childIds)
+fn remove_subtree(blocks: &yrs::MapRef, txn: &mut yrs::TransactionMut, id: &str) {
+    let mut ids = vec![];
+    walk_subtree(blocks, txn, id, &mut |block, _| ids.push(block.id().to_string()));
+    for id in ids {
+        blocks.remove(txn, &id);
+    }
+}
+
+/// A block and its descendants, read out of the doc (for exporting)
+#[derive(Debug, Clone)]
+struct BlockTree {
+    block: BlockData,
+    children: Vec<BlockTree>,
+}
+
+/// Read a block's subtree, or every top-level block's with no block given
+/// (the synthetic root block stands in for its children)
+fn read_trees<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: Option<&str>) -> Result<Vec<BlockTree>, String> {
+    let top_ids = match block_id {
+        Some(id) => vec![id.to_string()],
+        None => txn
+            .get_array("rootIds")
+            .map(|arr| arr.iter(txn).map(|v| v.to_string(txn)).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|id| match read_block(blocks, txn, &id) {
+                Some(root) if id == "root" => root.child_ids(),
+                _ => vec![id],
+            })
+            .collect(),
+    };
+
+    let mut trees = vec![];
+    for id in top_ids {
+        // The walk is depth-first, so a stack of open blocks rebuilds the nesting
+        let mut stack: Vec<BlockTree> = vec![];
+        walk_subtree(blocks, txn, &id, &mut |block, depth| {
+            while stack.len() > depth {
+                let Some(done) = stack.pop() else { break };
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(done);
+                }
+            }
+            stack.push(BlockTree { block: block.clone(), children: vec![] });
+        });
+        while let Some(done) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(done),
+                None => trees.push(done),
+            }
         }
     }
+
+    if let (Some(id), true) = (block_id, trees.is_empty()) {
+        return Err(format!("Block {} not found", id));
+    }
+    Ok(trees)
 }
 
 // ═══════════════════════════════════════════════════════════════
@@ -316,6 +642,7 @@ fn load_workspace(state: tauri::State<'_, AppState>, name: String) -> Result<Str
         doc
     } else {
         println!("📝 Creating new workspace '{}'", name);
+        policy::trust_new_workspace(&name);
         create_empty_doc()
     };
     
@@ -352,6 +679,7 @@ fn new_workspace(state: tauri::State<'_, AppState>, name: String) -> Result<Stri
     
     // Create fresh document
     let new_doc = create_empty_doc();
+    policy::trust_new_workspace(&name);
     
     // Update state
     let mut doc = state.doc.lock().map_err(|e| e.to_string())?;
@@ -381,9 +709,43 @@ fn clear_workspace(state: tauri::State<'_, AppState>) -> Result<String, String>
 }
 
 // ═══════════════════════════════════════════════════════════════
-// MARKDOWN TREE PARSER
+// PARSED BLOCKS
 // ═══════════════════════════════════════════════════════════════
 
+/// Fields a block itself owns - imported properties can't overwrite them
+const BLOCK_FIELDS: &[&str] = &["id", "parentId", "childIds", "content", "type", "collapsed", "createdAt", "updatedAt"];
+
+/// Properties the app acts on or keeps up to date itself - an imported
+/// `watch:: src/**` must not start watching, nor a `status` set one
+const APP_PROPS: &[&str] = &[
+    "status",
+    "exitCode",
+    "runCount",
+    "lastRun",
+    "marks",
+    "ansi",
+    "outputType",
+    "spillPath",
+    "spillOffset",
+    "spillBaseId",
+    "shownLines",
+    "totalLines",
+    "paused",
+    "watch",
+];
+
+/// Property name for a property read from an imported file: its own name,
+/// or behind the format's prefix if the block or the app owns that name
+/// (a Logseq `watch::` is `logseqWatch`)
+fn imported_prop(format: &str, key: &str) -> String {
+    if !BLOCK_FIELDS.contains(&key) && !APP_PROPS.contains(&key) {
+        return key.to_string();
+    }
+    let mut chars = key.chars();
+    let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+    format!("{}{}{}", format, first, chars.as_str())
+}
+
 /// A parsed block with potential children (for heading hierarchy)
 #[derive(Debug, Clone)]
 struct ParsedBlock {
@@ -391,6 +753,20 @@ struct ParsedBlock {
     content: String,
     block_type: String,
     children: Vec<ParsedBlock>,
+    /// Extra fields written onto the block as-is (e.g. `marks`)
+    props: BTreeMap<String, yrs::Any>,
+}
+
+impl ParsedBlock {
+    fn new(id: String, content: String, block_type: &str) -> Self {
+        Self {
+            id,
+            content,
+            block_type: block_type.to_string(),
+            children: vec![],
+            props: BTreeMap::new(),
+        }
+    }
 }
 
 /// Clean up tacky emojis with tasteful alternatives
@@ -414,315 +790,747 @@ fn detackify(content: &str) -> String {
         .replace("🚀", "→")
 }
 
-/// Convert heading level to numeric depth (H1=1, H2=2, etc.)
-fn heading_level_to_depth(level: HeadingLevel) -> usize {
-    match level {
-        HeadingLevel::H1 => 1,
-        HeadingLevel::H2 => 2,
-        HeadingLevel::H3 => 3,
-        HeadingLevel::H4 => 4,
-        HeadingLevel::H5 => 5,
-        HeadingLevel::H6 => 6,
-    }
+/// Recursively insert parsed blocks into Y.Doc
+/// Returns the IDs of the top-level blocks, in order
+fn insert_parsed_blocks(
+    blocks: &yrs::MapRef,
+    txn: &mut yrs::TransactionMut,
+    parsed: &[ParsedBlock],
+    parent_id: &str,
+    now: i64,
+) -> Vec<String> {
+    insert_unique(blocks, txn, parsed, parent_id, now, &mut HashSet::new())
 }
 
-/// Parse markdown content into a tree of blocks based on heading hierarchy
-/// Returns a flat list if no headings found, or nested structure if headings present
-fn parse_markdown_tree(content: &str, base_id: &str, block_type: &str) -> Vec<ParsedBlock> {
-    let parser = Parser::new(content);
-
-    // First, check if there are any headings
-    let has_headings = Parser::new(content).any(|event| {
-        matches!(event, Event::Start(Tag::Heading { .. }))
-    });
+/// Insert blocks, giving any whose ID is empty or already taken (a re-run
+/// appending output under the same base, an import of the same file twice)
+/// a fresh one
+fn insert_unique(
+    blocks: &yrs::MapRef,
+    txn: &mut yrs::TransactionMut,
+    parsed: &[ParsedBlock],
+    parent_id: &str,
+    now: i64,
+    taken: &mut HashSet<String>,
+) -> Vec<String> {
+    let mut child_ids = vec![];
 
-    // If no headings, just return flat blocks per line (original behavior)
-    if !has_headings {
-        return content
-            .lines()
-            .enumerate()
-            .filter(|(_, line)| !line.trim().is_empty())
-            .map(|(i, line)| ParsedBlock {
-                id: format!("{}-{}", base_id, i),
-                content: line.to_string(),
-                block_type: block_type.to_string(),
-                children: vec![],
-            })
-            .collect();
-    }
+    for block in parsed {
+        let id = if block.id.is_empty() || taken.contains(&block.id) || blocks.contains_key(txn, &block.id) {
+            uuid::Uuid::new_v4().to_string()
+        } else {
+            block.id.clone()
+        };
+        taken.insert(id.clone());
 
-    // Parse with heading hierarchy
-    let mut root_blocks: Vec<ParsedBlock> = vec![];
+        // Recursively insert children first to get their IDs
+        let grandchild_ids = insert_unique(blocks, txn, &block.children, &id, now, taken);
 
-    // Stack: (heading_level, block_index_in_parent_children)
-    // Level 0 = root (no heading), Level 1 = H1, etc.
-    let mut heading_stack: Vec<(usize, usize)> = vec![(0, 0)]; // Start at root level
+        let mut fields: HashMap<String, yrs::Any> = [
+            ("id".into(), yrs::Any::String(id.clone().into())),
+            ("parentId".into(), yrs::Any::String(parent_id.into())),
+            ("childIds".into(), ids_to_any(&grandchild_ids)),
+            ("content".into(), yrs::Any::String(detackify(&block.content).into())),
+            ("type".into(), yrs::Any::String(block.block_type.clone().into())),
+            ("collapsed".into(), yrs::Any::Bool(false)), // Don't auto-collapse - let user see content first
+            ("createdAt".into(), yrs::Any::BigInt(now)),
+            ("updatedAt".into(), yrs::Any::BigInt(now)),
+        ].into_iter().collect();
+        fields.extend(block.props.clone());
 
-    let mut current_text = String::new();
-    let mut in_heading = false;
-    let mut current_heading_level = 0usize;
-    let mut block_counter = 0usize;
+        blocks.insert(txn, id.as_str(), yrs::Any::Map(Arc::new(fields)));
+        child_ids.push(id);
+    }
 
-    // Helper to get mutable ref to block at path
-    fn get_parent_children<'a>(blocks: &'a mut Vec<ParsedBlock>, stack: &[(usize, usize)]) -> &'a mut Vec<ParsedBlock> {
-        if stack.len() <= 1 {
-            return blocks;
-        }
+    child_ids
+}
 
-        let mut current = blocks;
-        for (_, idx) in stack.iter().skip(1) {
-            if *idx < current.len() {
-                current = &mut current[*idx].children;
+/// Insert a parsed tree at the end of a block's children, or at the end of
+/// the top level when there's no parent
+/// Returns the IDs of the inserted top-level blocks
+fn insert_tree(
+    blocks: &yrs::MapRef,
+    txn: &mut yrs::TransactionMut,
+    parsed: &[ParsedBlock],
+    parent_id: Option<&str>,
+    now: i64,
+) -> Result<Vec<String>, String> {
+    let Some(parent_id) = parent_id else {
+        let ids = insert_parsed_blocks(blocks, txn, parsed, "", now);
+        for id in &ids {
+            if let Some(mut block) = read_block(blocks, txn, id) {
+                block.set("parentId", yrs::Any::Null);
+                write_block(blocks, txn, block);
             }
         }
-        current
-    }
-
-    for event in parser {
-        match event {
-            Event::Start(Tag::Heading { level, .. }) => {
-                // Flush any pending text first
-                if !current_text.trim().is_empty() && !in_heading {
-                    let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                    parent.push(ParsedBlock {
-                        id: format!("{}-{}", base_id, block_counter),
-                        content: current_text.trim().to_string(),
-                        block_type: block_type.to_string(),
-                        children: vec![],
-                    });
-                    block_counter += 1;
-                }
-                current_text.clear();
+        let root_ids = txn.get_or_insert_array("rootIds");
+        for id in &ids {
+            root_ids.push_back(txn, yrs::Any::String(id.as_str().into()));
+        }
+        return Ok(ids);
+    };
 
-                in_heading = true;
-                current_heading_level = heading_level_to_depth(level);
+    let mut parent = read_block(blocks, txn, parent_id)
+        .ok_or_else(|| format!("Block {} not found", parent_id))?;
+    let ids = insert_parsed_blocks(blocks, txn, parsed, parent_id, now);
+    let mut child_ids = parent.child_ids();
+    child_ids.extend(ids.iter().cloned());
+    parent.set("childIds", ids_to_any(&child_ids));
+    parent.set("updatedAt", now);
+    write_block(blocks, txn, parent);
+    Ok(ids)
+}
 
-                // Pop stack to find correct parent level
-                while heading_stack.len() > 1 && heading_stack.last().map(|(l, _)| *l).unwrap_or(0) >= current_heading_level {
-                    heading_stack.pop();
+/// A `((id))` reference from one block to another
+fn block_ref_pattern() -> regex::Regex {
+    regex::Regex::new(r"\(\(([^()\s]+)\)\)").expect("valid block reference pattern")
+}
+
+/// Keep imported blocks' own IDs where they're free, give the rest fresh ones,
+/// and point `((id))` references at the new IDs
+fn claim_ids<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, parsed: &mut [ParsedBlock]) {
+    fn claim<T: ReadTxn>(
+        blocks: &yrs::MapRef,
+        txn: &T,
+        parsed: &mut [ParsedBlock],
+        taken: &mut HashSet<String>,
+        renamed: &mut HashMap<String, String>,
+    ) {
+        for block in parsed.iter_mut() {
+            // A repeat within the import keeps referring to the first block with the ID
+            let in_doc = blocks.contains_key(txn, &block.id);
+            if block.id.is_empty() || in_doc || taken.contains(&block.id) {
+                let id = uuid::Uuid::new_v4().to_string();
+                if in_doc {
+                    renamed.entry(block.id.clone()).or_insert_with(|| id.clone());
                 }
+                block.id = id;
             }
-            Event::End(TagEnd::Heading(_)) => {
-                in_heading = false;
-
-                // Create the heading block - preserve # prefix for editor styling
-                let heading_prefix = "#".repeat(current_heading_level);
-                let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                let new_idx = parent.len();
-                parent.push(ParsedBlock {
-                    id: format!("{}-{}", base_id, block_counter),
-                    content: format!("{} {}", heading_prefix, current_text.trim()),
-                    block_type: block_type.to_string(),
-                    children: vec![],
-                });
-                block_counter += 1;
-
-                // Push this heading onto stack as new parent
-                heading_stack.push((current_heading_level, new_idx));
-                current_text.clear();
-            }
-            Event::Text(text) | Event::Code(text) => {
-                current_text.push_str(&text);
+            taken.insert(block.id.clone());
+            claim(blocks, txn, &mut block.children, taken, renamed);
+        }
+    }
+
+    fn repoint(parsed: &mut [ParsedBlock], pattern: &regex::Regex, renamed: &HashMap<String, String>) {
+        for block in parsed.iter_mut() {
+            if block.content.contains("((") {
+                block.content = pattern
+                    .replace_all(&block.content, |caps: &regex::Captures| match renamed.get(&caps[1]) {
+                        Some(id) => format!("(({}))", id),
+                        None => caps[0].to_string(),
+                    })
+                    .into_owned();
             }
-            Event::SoftBreak | Event::HardBreak => {
-                if !in_heading && !current_text.trim().is_empty() {
-                    let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                    parent.push(ParsedBlock {
-                        id: format!("{}-{}", base_id, block_counter),
-                        content: current_text.trim().to_string(),
-                        block_type: block_type.to_string(),
-                        children: vec![],
-                    });
-                    block_counter += 1;
-                    current_text.clear();
-                }
+            repoint(&mut block.children, pattern, renamed);
+        }
+    }
+
+    let mut renamed = HashMap::new();
+    claim(blocks, txn, parsed, &mut HashSet::new(), &mut renamed);
+    if !renamed.is_empty() {
+        repoint(parsed, &block_ref_pattern(), &renamed);
+    }
+}
+
+/// Insert imported blocks into the current workspace
+/// Returns the updated Y.Doc state as base64
+fn import_blocks(state: &AppState, mut parsed: Vec<ParsedBlock>, parent_id: Option<&str>) -> Result<String, String> {
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let mut txn = doc.transact_mut();
+    let blocks = txn.get_or_insert_map("blocks");
+    claim_ids(&blocks, &txn, &mut parsed);
+    insert_tree(&blocks, &mut txn, &parsed, parent_id, Utc::now().timestamp_millis())?;
+
+    drop(txn);
+
+    let txn = doc.transact();
+    let new_state = txn.encode_state_as_update_v1(&StateVector::default());
+    Ok(BASE64.encode(&new_state))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// STDIN SOURCES
+// ═══════════════════════════════════════════════════════════════
+//
+// An sh:: block reads stdin from the outline via a `stdin::` child:
+//
+//   sh:: jq .foo
+//     stdin:: previous            ← previous sibling (or its output, if it ran)
+//     stdin:: parent markdown     ← parent subtree as nested bullets
+//     stdin:: ((block-id)) text   ← any block by ID
+
+/// Which blocks feed a command's stdin
+#[derive(Debug, Clone, PartialEq)]
+enum StdinSource {
+    Previous,
+    Parent,
+    Block(String),
+}
+
+/// How the source subtree is rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StdinFormat {
+    /// Block contents one per line, no indentation (reconstructs command output)
+    Text,
+    /// Nested `- ` bullets, two spaces per level
+    Markdown,
+}
+
+/// Parse the value of a `stdin::` block, e.g. "previous", "((abc)) markdown"
+fn parse_stdin_spec(value: &str) -> Result<(StdinSource, StdinFormat), String> {
+    let mut words = value.split_whitespace();
+    let source = match words.next() {
+        Some("previous") | Some("prev") => StdinSource::Previous,
+        Some("parent") => StdinSource::Parent,
+        Some(reference) => {
+            let id = reference
+                .strip_prefix("((")
+                .and_then(|r| r.strip_suffix("))"))
+                .filter(|id| !id.is_empty() && !id.contains(['(', ')']))
+                .ok_or_else(|| format!(
+                    "Unknown stdin source '{}'. Use previous, parent or ((block-id)).",
+                    reference
+                ))?;
+            StdinSource::Block(id.to_string())
+        }
+        None => return Err("stdin:: needs a source: previous, parent or ((block-id))".to_string()),
+    };
+    let format = match words.next() {
+        None | Some("text") => StdinFormat::Text,
+        Some("markdown") | Some("md") => StdinFormat::Markdown,
+        Some(other) => return Err(format!("Unknown stdin format '{}'. Use text or markdown.", other)),
+    };
+    Ok((source, format))
+}
+
+fn is_stdin_block(block: &BlockData) -> bool {
+    matches!(split_prefix(block.content()), Some((prefix, _)) if prefix == "stdin")
+}
+
+/// Render blocks as stdin text, skipping `skip_id`'s subtree and any `stdin::` blocks
+fn render_stdin_blocks<T: ReadTxn>(
+    blocks: &yrs::MapRef,
+    txn: &T,
+    ids: &[String],
+    format: StdinFormat,
+    skip_id: &str,
+) -> String {
+    let mut out = String::new();
+    for id in ids {
+        let mut skip_below: Option<usize> = None;
+        walk_subtree(blocks, txn, id, &mut |block, depth| {
+            if skip_below.is_some_and(|d| depth > d) {
+                return;
             }
-            Event::End(TagEnd::Paragraph) => {
-                if !in_heading && !current_text.trim().is_empty() {
-                    let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                    parent.push(ParsedBlock {
-                        id: format!("{}-{}", base_id, block_counter),
-                        content: current_text.trim().to_string(),
-                        block_type: block_type.to_string(),
-                        children: vec![],
-                    });
-                    block_counter += 1;
-                    current_text.clear();
-                }
+            skip_below = None;
+            if block.id() == skip_id || is_stdin_block(block) {
+                skip_below = Some(depth);
+                return;
             }
-            Event::End(TagEnd::Item) => {
-                if !current_text.trim().is_empty() {
-                    let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                    parent.push(ParsedBlock {
-                        id: format!("{}-{}", base_id, block_counter),
-                        content: current_text.trim().to_string(), // No bullet - block dot is enough
-                        block_type: block_type.to_string(),
-                        children: vec![],
-                    });
-                    block_counter += 1;
-                    current_text.clear();
+            match format {
+                StdinFormat::Text => {
+                    out.push_str(block.content());
+                    out.push('\n');
                 }
-            }
-            Event::End(TagEnd::CodeBlock) => {
-                if !current_text.trim().is_empty() {
-                    let parent = get_parent_children(&mut root_blocks, &heading_stack);
-                    parent.push(ParsedBlock {
-                        id: format!("{}-{}", base_id, block_counter),
-                        content: format!("```\n{}\n```", current_text.trim()),
-                        block_type: block_type.to_string(),
-                        children: vec![],
-                    });
-                    block_counter += 1;
-                    current_text.clear();
+                StdinFormat::Markdown => {
+                    let indent = "  ".repeat(depth);
+                    let mut lines = block.content().lines();
+                    out.push_str(&format!("{}- {}\n", indent, lines.next().unwrap_or("")));
+                    for line in lines {
+                        out.push_str(&format!("{}  {}\n", indent, line));
+                    }
                 }
             }
-            _ => {}
-        }
-    }
-
-    // Flush any remaining text
-    if !current_text.trim().is_empty() {
-        let parent = get_parent_children(&mut root_blocks, &heading_stack);
-        parent.push(ParsedBlock {
-            id: format!("{}-{}", base_id, block_counter),
-            content: current_text.trim().to_string(),
-            block_type: block_type.to_string(),
-            children: vec![],
         });
     }
-
-    root_blocks
+    out
 }
 
-/// Recursively insert parsed blocks into Y.Doc
-fn insert_parsed_blocks(
+/// Render a source block: an executed block contributes its output children,
+/// anything else contributes itself and its subtree
+fn render_stdin_source<T: ReadTxn>(
     blocks: &yrs::MapRef,
-    txn: &mut yrs::TransactionMut,
-    parsed: &[ParsedBlock],
-    parent_id: &str,
-    now: i64,
-) -> Vec<String> {
-    let mut child_ids = vec![];
-
-    for block in parsed {
-        // Recursively insert children first to get their IDs
-        let grandchild_ids = insert_parsed_blocks(blocks, txn, &block.children, &block.id, now);
+    txn: &T,
+    source: &BlockData,
+    format: StdinFormat,
+    skip_id: &str,
+) -> String {
+    if source.get_str("status").is_some() {
+        render_stdin_blocks(blocks, txn, &source.child_ids(), format, skip_id)
+    } else {
+        render_stdin_blocks(blocks, txn, &[source.id().to_string()], format, skip_id)
+    }
+}
 
-        let block_data = yrs::Any::Map(Arc::new([
-            ("id".into(), yrs::Any::String(block.id.clone().into())),
-            ("parentId".into(), yrs::Any::String(parent_id.into())),
-            ("childIds".into(), yrs::Any::Array(Arc::from(
-                grandchild_ids.iter()
-                    .map(|s| yrs::Any::String(s.clone().into()))
-                    .collect::<Vec<_>>()
-            ))),
-            ("content".into(), yrs::Any::String(detackify(&block.content).into())),
-            ("type".into(), yrs::Any::String(block.block_type.clone().into())),
-            ("collapsed".into(), yrs::Any::Bool(false)), // Don't auto-collapse - let user see content first
-            ("createdAt".into(), yrs::Any::BigInt(now)),
-            ("updatedAt".into(), yrs::Any::BigInt(now)),
-        ].into_iter().collect()));
+/// Resolve the stdin text for a block from its `stdin::` child, if it has one
+fn resolve_stdin<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: &str) -> Result<Option<String>, String> {
+    let block = read_block(blocks, txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
 
-        blocks.insert(txn, block.id.as_str(), block_data);
-        child_ids.push(block.id.clone());
-    }
+    let spec = block.child_ids().iter().find_map(|id| {
+        read_block(blocks, txn, id)
+            .filter(is_stdin_block)
+            .and_then(|b| split_prefix(b.content()).map(|(_, value)| value.to_string()))
+    });
+    let Some(spec) = spec else { return Ok(None) };
+    let (source, format) = parse_stdin_spec(&spec)?;
+
+    let text = match source {
+        StdinSource::Previous => {
+            let siblings = sibling_ids(blocks, txn, &block);
+            let position = siblings.iter().position(|id| id == block_id);
+            let previous = position
+                .and_then(|i| i.checked_sub(1))
+                .and_then(|i| read_block(blocks, txn, &siblings[i]))
+                .ok_or("stdin:: previous - there is no previous sibling block")?;
+            render_stdin_source(blocks, txn, &previous, format, block_id)
+        }
+        StdinSource::Parent => {
+            let parent = block.parent_id()
+                .and_then(|p| read_block(blocks, txn, p))
+                .ok_or("stdin:: parent - block has no parent")?;
+            render_stdin_blocks(blocks, txn, &[parent.id().to_string()], format, block_id)
+        }
+        StdinSource::Block(id) => {
+            let source = read_block(blocks, txn, &id)
+                .ok_or_else(|| format!("stdin:: block {} not found", id))?;
+            render_stdin_source(blocks, txn, &source, format, block_id)
+        }
+    };
 
-    child_ids
+    Ok(Some(text))
 }
 
 // ═══════════════════════════════════════════════════════════════
 // SH:: EXECUTOR
 // ═══════════════════════════════════════════════════════════════
 
-/// Execute shell command and append output as child blocks
-/// Returns the updated Y.Doc state as base64
-#[tauri::command]
-async fn execute_shell(
-    state: tauri::State<'_, AppState>,
-    block_id: String,
-    command: String,
-) -> Result<String, String> {
-    // Run the shell command
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg(&command)
+/// Captured output of a finished process
+struct RunOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    /// Signal that killed the process (unix)
+    signal: Option<i32>,
+    /// Directory the process ran in, when known
+    cwd: Option<String>,
+}
+
+/// Exit code (-1 if there is none) and the signal that killed the process, if any
+fn exit_details(status: std::process::ExitStatus) -> (i32, Option<i32>) {
+    #[cfg(unix)]
+    let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+    #[cfg(not(unix))]
+    let signal = None;
+    (status.code().unwrap_or(-1), signal)
+}
+
+fn cwd_value(block: &BlockData) -> Option<PathBuf> {
+    match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == "cwd" && !value.trim().is_empty() => {
+            let value = value.trim();
+            let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+            Some(match value.strip_prefix("~") {
+                Some("") => home(),
+                Some(rest) if rest.starts_with('/') => home().join(&rest[1..]),
+                _ => PathBuf::from(value),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The working directory a block's commands run in, from its `cwd::` ancestors
+/// A relative `cwd::` builds on the next one out; with none, processes use the app's own
+fn resolve_cwd<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: &str) -> Option<PathBuf> {
+    ancestors(blocks, txn, block_id)
+        .iter()
+        .rev()
+        .filter_map(cwd_value)
+        .fold(None, |outer: Option<PathBuf>, dir| Some(match outer {
+            Some(outer) => outer.join(dir),
+            None => dir,
+        }))
+}
+
+/// The app's own working directory, which spawned processes inherit
+fn current_dir() -> Option<String> {
+    std::env::current_dir().ok().map(|dir| dir.to_string_lossy().into_owned())
+}
+
+/// Spawn a process with piped output, feeding it stdin if given
+async fn run_process(mut command: Command, stdin_text: Option<String>) -> Result<RunOutput, String> {
+    let cwd = match command.as_std().get_current_dir() {
+        Some(dir) => Some(dir.to_string_lossy().into_owned()),
+        None => current_dir(),
+    };
+
+    let mut child = command
+        .stdin(if stdin_text.is_some() { Stdio::piped() } else { Stdio::null() })
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .output()
+        .spawn()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    // Feed stdin from a separate task so a chatty command can't deadlock on full pipes
+    if let (Some(text), Some(mut stdin)) = (stdin_text, child.stdin.take()) {
+        tokio::spawn(async move {
+            // The command may exit without reading everything - that's fine
+            let _ = stdin.write_all(text.as_bytes()).await;
+        });
+    }
+
+    let output = child
+        .wait_with_output()
         .await
         .map_err(|e| format!("Failed to execute command: {}", e))?;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let exit_code = output.status.code().unwrap_or(-1);
+    let (exit_code, signal) = exit_details(output.status);
+    Ok(RunOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        exit_code,
+        signal,
+        cwd,
+    })
+}
+
+/// Turn command output into blocks, handling ANSI escapes per the runner's mode
+fn parse_output(text: &str, base_id: &str, block_type: &str, ansi: ansi::AnsiMode) -> Vec<ParsedBlock> {
+    match ansi {
+        ansi::AnsiMode::Marks if text.contains('\x1b') => ansi::styled_blocks(text, base_id, block_type),
+        _ => markdown::parse_markdown_tree(&ansi::strip(text), base_id, block_type),
+    }
+}
+
+/// Where a run's output goes among the executed block's children
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Placement {
+    /// Drop the output of earlier runs first
+    replace: bool,
+    /// Put the output under a timestamped `run` block instead of directly under the block
+    grouped: bool,
+}
 
-    // Get the doc and update
+/// Append a run's output as child blocks of the executed block and record its status
+/// Returns the updated Y.Doc state as base64
+fn append_run_output(
+    state: &AppState,
+    block_id: &str,
+    output: &RunOutput,
+    ansi: ansi::AnsiMode,
+    placement: Placement,
+    mut record: runs::RunRecord,
+) -> Result<String, String> {
+    let mut options = output::OutputOptions {
+        workspace: state.workspace_name.lock().map_err(|e| e.to_string())?.clone(),
+        ansi,
+        format: None,
+        limits: settings::load_settings()?.output,
+    };
     let doc = state.doc.lock().map_err(|e| e.to_string())?;
     let mut txn = doc.transact_mut();
     let blocks = txn.get_or_insert_map("blocks");
     let now = Utc::now().timestamp_millis();
 
     // Get existing childIds from parent block
-    let parent_block = blocks.get(&txn, &block_id)
+    let mut parent_block = read_block(&blocks, &txn, block_id)
         .ok_or_else(|| format!("Block {} not found", block_id))?;
 
-    let existing_child_ids: Vec<String> = if let yrs::Value::YMap(map) = parent_block {
-        if let Some(yrs::Value::Any(yrs::Any::Array(arr))) = map.get(&txn, "childIds") {
-            arr.iter()
-                .filter_map(|a| if let yrs::Any::String(s) = a { Some(s.to_string()) } else { None })
-                .collect()
-        } else {
-            vec![]
+    let mut new_child_ids = parent_block.child_ids();
+    options.format = structured::split_format(parent_block.content()).0;
+    record.run = parent_block.get_i64("runCount").unwrap_or(0) + 1;
+
+    if placement.replace {
+        let (old_output, kept): (Vec<String>, Vec<String>) = new_child_ids.into_iter().partition(|id| {
+            read_block(&blocks, &txn, id)
+                .is_some_and(|child| output::OUTPUT_TYPES.contains(&child.get_str("type").unwrap_or_default()))
+        });
+        for id in old_output {
+            remove_subtree(&blocks, &mut txn, &id);
         }
-    } else {
-        vec![]
-    };
+        new_child_ids = kept;
+    }
 
-    let mut new_child_ids = existing_child_ids.clone();
+    // A group gets its own ID per run, so earlier groups' output keeps its IDs
+    let output_parent = if placement.grouped { format!("{}-run-{}", block_id, record.run) } else { block_id.to_string() };
 
-    // Parse stdout with smart markdown indentation (headings become parents)
-    if !stdout.trim().is_empty() {
-        let parsed_stdout = parse_markdown_tree(&stdout, &format!("{}-out", block_id), "output");
-        let stdout_ids = insert_parsed_blocks(&blocks, &mut txn, &parsed_stdout, &block_id, now);
-        new_child_ids.extend(stdout_ids);
-    }
+    // Parse stdout with smart markdown indentation (headings become parents),
+    // past the output limits the rest is saved to a file behind a marker block
+    let mut output_ids = output::insert_output(&blocks, &mut txn, &output_parent, output::Stream::Stdout, &output.stdout, &options, now)?;
 
     // Parse stderr (typically not markdown, but still use the parser for consistency)
-    if !stderr.trim().is_empty() {
-        let parsed_stderr = parse_markdown_tree(&stderr, &format!("{}-err", block_id), "error");
-        let stderr_ids = insert_parsed_blocks(&blocks, &mut txn, &parsed_stderr, &block_id, now);
-        new_child_ids.extend(stderr_ids);
-    }
-
-    // Update parent block with new childIds and status
-    let status = if exit_code == 0 { "complete" } else { "error" };
-    let updated_parent = yrs::Any::Map(Arc::new([
-        ("id".into(), yrs::Any::String(block_id.clone().into())),
-        ("parentId".into(), yrs::Any::String("root".into())), // Assume root for now
-        ("childIds".into(), yrs::Any::Array(Arc::from(
-            new_child_ids.iter()
-                .map(|s| yrs::Any::String(s.clone().into()))
-                .collect::<Vec<_>>()
-        ))),
-        ("content".into(), yrs::Any::String(format!("sh:: {}", command).into())),
-        ("type".into(), yrs::Any::String("sh".into())),
-        ("status".into(), yrs::Any::String(status.into())),
-        ("exitCode".into(), yrs::Any::BigInt(exit_code as i64)),
-        ("collapsed".into(), yrs::Any::Bool(false)),
-        ("createdAt".into(), yrs::Any::BigInt(now)),
-        ("updatedAt".into(), yrs::Any::BigInt(now)),
-    ].into_iter().collect()));
-
-    blocks.insert(&mut txn, block_id.as_str(), updated_parent);
+    let stderr_ids = output::insert_output(&blocks, &mut txn, &output_parent, output::Stream::Stderr, &output.stderr, &options, now)?;
+    output_ids.extend(stderr_ids);
+
+    if placement.grouped {
+        let started = chrono::DateTime::from_timestamp_millis(record.started_at)
+            .map(|at| at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let mut group = BlockData::default();
+        group.set("id", output_parent.as_str());
+        group.set("parentId", block_id);
+        group.set("childIds", ids_to_any(&output_ids));
+        group.set("content", format!("{} · exit {}", started, output.exit_code));
+        group.set("type", "run");
+        group.set("collapsed", false);
+        group.set("createdAt", yrs::Any::BigInt(now));
+        group.set("updatedAt", yrs::Any::BigInt(now));
+        write_block(&blocks, &mut txn, group);
+        new_child_ids.push(output_parent);
+    } else {
+        new_child_ids.extend(output_ids);
+    }
+
+    // Update parent block with new childIds and status, keeping its content
+    // (door blocks like `read:: 47` must not be rewritten into their expansion)
+    let status = if output.exit_code == 0 { "complete" } else { "error" };
+    parent_block.set("childIds", ids_to_any(&new_child_ids));
+    parent_block.set("status", status);
+    parent_block.set("exitCode", yrs::Any::BigInt(output.exit_code as i64));
+    parent_block.set("runCount", yrs::Any::BigInt(record.run));
+    parent_block.set("lastRun", record.to_any()?);
+    parent_block.set("updatedAt", yrs::Any::BigInt(now));
+    write_block(&blocks, &mut txn, parent_block);
 
     drop(txn);
 
+    // The run already landed in the doc - a history write failure shouldn't undo that
+    if let Err(e) = runs::append_history(&options.workspace, &record) {
+        log::warn!("{}", e);
+    }
+
     // Return updated state
     let txn = doc.transact();
     let new_state = txn.encode_state_as_update_v1(&StateVector::default());
     Ok(BASE64.encode(&new_state))
 }
 
+/// What a block runs when executed
+enum Execution {
+    Shell(String),
+    Code(runners::CodeRun),
+}
+
+impl Execution {
+    /// Runner name, matched against a `session::` ancestor's kind
+    fn runner(&self) -> &str {
+        match self {
+            Execution::Shell(_) => "shell",
+            Execution::Code(code) => &code.runner,
+        }
+    }
+
+    /// How the runner wants escape sequences in its output handled
+    fn ansi_mode(&self) -> Result<ansi::AnsiMode, String> {
+        let table = runners::load_runners()?;
+        Ok(table.get(self.runner()).map(|r| r.ansi).unwrap_or_default())
+    }
+
+    fn source(&self) -> &str {
+        match self {
+            Execution::Shell(command) => command,
+            Execution::Code(code) => &code.code,
+        }
+    }
+}
+
+/// Work out what a block runs: code for a registered runner, `sh::`/`term::`, or a door alias
+/// Returns Ok(None) for blocks that aren't executable
+fn resolve_execution<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: &str) -> Result<Option<Execution>, String> {
+    let block = read_block(blocks, txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
+
+    let table = runners::load_runners()?;
+    match runners::resolve_code(&table, block.content()) {
+        Some(code) => Ok(Some(Execution::Code(code))),
+        None => Ok(doors::resolve_command(blocks, txn, block_id)?.map(Execution::Shell)),
+    }
+}
+
+/// Run a block's execution: in its sandbox if it has one, term:: under a PTY, in its
+/// `session::` if one matches, otherwise as a fresh process
+/// Returns the output and the name of the session it ran in, if any
+async fn run_execution(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    block_id: &str,
+    execution: Execution,
+) -> Result<(RunOutput, Option<String>), String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    let workspace_sandbox = policy::load_policy(&workspace)?.sandbox;
+
+    // Read what we need from the outline up front (the doc lock can't be held across awaits)
+    let (stdin_text, session, is_term, sandbox, cwd) = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        let is_term = read_block(&blocks, &txn, block_id)
+            .and_then(|b| split_prefix(b.content()).map(|(prefix, _)| prefix == "term"))
+            .unwrap_or(false);
+        (
+            resolve_stdin(&blocks, &txn, block_id)?,
+            sessions::find_session(&blocks, &txn, block_id)?,
+            is_term,
+            sandbox::find_sandbox(&blocks, &txn, block_id, workspace_sandbox)?,
+            resolve_cwd(&blocks, &txn, block_id),
+        )
+    };
+
+    // Never fall back to running unsandboxed - anything the sandbox can't cover is an error
+    if let Some(config) = sandbox {
+        let Execution::Shell(command) = &execution else {
+            return Err(format!("sandbox:: only covers shell commands - {} code can't run inside one", execution.runner()));
+        };
+        if is_term {
+            return Err("term:: blocks can't run inside a sandbox::".to_string());
+        }
+        if session.is_some_and(|s| s.kind == execution.runner()) {
+            return Err("A session:: can't run inside a sandbox::".to_string());
+        }
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        if let Some(dir) = &cwd {
+            shell.current_dir(dir);
+        }
+        sandbox::apply(&mut shell, &config)?;
+        return Ok((run_process(shell, stdin_text).await?, None));
+    }
+
+    if let (true, Execution::Shell(command)) = (is_term, &execution) {
+        let output = term::run_in_terminal(app, &state.terminals, block_id, command, stdin_text, cwd).await?;
+        return Ok((output, None));
+    }
+
+    if let Some(session) = session.filter(|s| s.kind == execution.runner()) {
+        if stdin_text.is_some() {
+            return Err("stdin:: can't be used inside a session".to_string());
+        }
+        let table = runners::load_runners()?;
+        let output = sessions::run_in_session(&state.sessions, &session, &table, execution.source()).await?;
+        return Ok((output, Some(session.name)));
+    }
+
+    let output = match execution {
+        Execution::Shell(command) => {
+            let mut shell = Command::new("sh");
+            shell.arg("-c").arg(&command);
+            if let Some(dir) = &cwd {
+                shell.current_dir(dir);
+            }
+            run_process(shell, stdin_text).await?
+        }
+        Execution::Code(code) => runners::run_code(&code, stdin_text, cwd).await?,
+    };
+    Ok((output, None))
+}
+
+/// Check a block's execution against the workspace policy, then run it (or hold it
+/// for confirmation)
+/// Returns the updated Y.Doc state as base64
+async fn execute(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    block_id: &str,
+    execution: Execution,
+    placement: Placement,
+) -> Result<String, String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    let workspace_policy = policy::load_policy(&workspace)?;
+
+    match policy::check(&workspace_policy, execution.source())? {
+        policy::Decision::Allow => {}
+        policy::Decision::Deny(reason) => {
+            policy::audit(&workspace, block_id, &execution, policy::AuditDecision::Denied, Some(&reason));
+            return Err(reason);
+        }
+        policy::Decision::Confirm(reason) => {
+            let doc_state = policy::request_confirmation(state, &workspace, block_id, execution, placement, reason)?;
+            state.activity.pending_changed(app, state);
+            return Ok(doc_state);
+        }
+    }
+
+    policy::audit(&workspace, block_id, &execution, policy::AuditDecision::Allowed, None);
+    run_and_record(app, state, block_id, execution, placement).await
+}
+
+/// Run a block's execution and record its output and run metadata on the block
+/// Returns the updated Y.Doc state as base64
+async fn run_and_record(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    block_id: &str,
+    execution: Execution,
+    placement: Placement,
+) -> Result<String, String> {
+    let ansi = execution.ansi_mode()?;
+    let runner = execution.runner().to_string();
+    let command = execution.source().to_string();
+
+    let started_at = Utc::now().timestamp_millis();
+    state.activity.started(app, state);
+    let result = run_execution(app, state, block_id, execution).await;
+    let ended_at = Utc::now().timestamp_millis();
+
+    let finish = match &result {
+        Ok((output, _)) => notify::Finish::Exited { exit_code: output.exit_code, signal: output.signal },
+        Err(e) => notify::Finish::Error(e),
+    };
+    state.activity.finished(app, state, &command, finish, ended_at - started_at);
+    let (output, session) = result?;
+
+    let record = runs::RunRecord {
+        block_id: block_id.to_string(),
+        run: 0, // numbered from the block's runCount when the output is appended
+        runner,
+        command,
+        cwd: output.cwd.clone(),
+        session,
+        started_at,
+        ended_at,
+        duration_ms: ended_at - started_at,
+        exit_code: output.exit_code,
+        signal: output.signal,
+        stdout_bytes: output.stdout.len(),
+        stderr_bytes: output.stderr.len(),
+    };
+    append_run_output(state, block_id, &output, ansi, placement, record)
+}
+
+/// Execute shell command and append output as child blocks
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+async fn execute_shell(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    block_id: String,
+    command: String,
+) -> Result<String, String> {
+    execute(&app, &state, &block_id, Execution::Shell(command), Placement::default()).await
+}
+
+/// Execute whatever a block describes: code for a registered runner (`py::`, a fenced
+/// code block), `sh::`/`term::`, or a door alias
+/// Returns the updated Y.Doc state as base64, or None if the block isn't executable
+#[tauri::command]
+async fn execute_block(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    block_id: String,
+) -> Result<Option<String>, String> {
+    let execution = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        resolve_execution(&blocks, &txn, &block_id)?
+    };
+
+    let Some(execution) = execution else { return Ok(None) };
+    execute(&app, &state, &block_id, execution, Placement::default()).await.map(Some)
+}
+
 // ═══════════════════════════════════════════════════════════════
 // TAURI APP
 // ═══════════════════════════════════════════════════════════════
@@ -730,6 +1538,7 @@ async fn execute_shell(
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
         .manage(AppState::default())
         .invoke_handler(tauri::generate_handler![
             get_initial_state,
@@ -739,11 +1548,50 @@ pub fn run() {
             get_diff,
             save_doc,
             execute_shell,
+            execute_block,
             get_current_workspace,
             list_workspaces,
             load_workspace,
             new_workspace,
             clear_workspace,
+            doors::list_doors,
+            doors::expand_block,
+            runners::list_runners,
+            sessions::list_sessions,
+            sessions::kill_session,
+            sessions::restart_session,
+            term::send_term_input,
+            term::resize_term,
+            output::expand_output,
+            output::open_output,
+            settings::get_settings,
+            runs::get_run_history,
+            policy::get_workspace_policy,
+            policy::set_workspace_policy,
+            policy::list_pending_runs,
+            policy::approve_execution,
+            policy::reject_execution,
+            policy::get_audit_log,
+            jobs::run_subtree,
+            jobs::cancel_queue,
+            schedule::list_schedules,
+            schedule::set_schedule_paused,
+            watch::list_watches,
+            watch::set_block_watch,
+            watch::set_watch_paused,
+            notify::get_run_summary,
+            notify::clear_run_summary,
+            markdown::import_markdown,
+            markdown::import_markdown_dir,
+            markdown::export_markdown,
+            opml::import_opml,
+            opml::export_opml,
+            logseq::import_logseq,
+            roam::import_roam,
+            org::import_org,
+            org::export_org,
+            html::export_html,
+            html::export_html_site,
         ])
         .setup(|app| {
             if cfg!(debug_assertions) {
@@ -753,8 +1601,90 @@ pub fn run() {
                         .build(),
                 )?;
             }
+            tauri::async_runtime::spawn(schedule::run_scheduler(app.handle().clone()));
+            tauri::async_runtime::spawn(watch::run_watcher(app.handle().clone()));
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::doc_with;
+
+    fn stdin_for(doc: &Doc, id: &str) -> Result<Option<String>, String> {
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+        resolve_stdin(&blocks, &txn, id)
+    }
+
+    #[test]
+    fn claimed_ids_stay_unique_and_references_follow() {
+        let doc = doc_with(&[("a", None, "A"), ("b", None, "B")]);
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+
+        let mut first = ParsedBlock::new("a".into(), "see ((a)), ((b)) and ((new))".into(), "text");
+        first.children.push(ParsedBlock::new("new".into(), "((new)) ((missing))".into(), "text"));
+        first.children.push(ParsedBlock::new("a".into(), "again".into(), "text"));
+        let mut parsed = vec![first, ParsedBlock::new(String::new(), "no id".into(), "text")];
+        claim_ids(&blocks, &txn, &mut parsed);
+
+        let [first, unnamed] = &parsed[..] else { panic!("{:?}", parsed) };
+        let [new, again] = &first.children[..] else { panic!("{:?}", first.children) };
+        // `a` is taken: the import's first `a` gets a new ID and the references follow it
+        assert!(first.id != "a" && again.id != "a" && again.id != first.id);
+        assert_eq!(first.content, format!("see (({})), ((b)) and ((new))", first.id));
+        // Free IDs are kept; references to other blocks are left alone
+        assert_eq!(new.id, "new");
+        assert_eq!(new.content, "((new)) ((missing))");
+        assert!(!unnamed.id.is_empty());
+    }
+
+    #[test]
+    fn parses_stdin_sources() {
+        assert_eq!(parse_stdin_spec("previous"), Ok((StdinSource::Previous, StdinFormat::Text)));
+        assert_eq!(parse_stdin_spec("prev md"), Ok((StdinSource::Previous, StdinFormat::Markdown)));
+        assert_eq!(parse_stdin_spec("parent markdown"), Ok((StdinSource::Parent, StdinFormat::Markdown)));
+        assert_eq!(
+            parse_stdin_spec("((abc-123)) text"),
+            Ok((StdinSource::Block("abc-123".to_string()), StdinFormat::Text))
+        );
+    }
+
+    #[test]
+    fn rejects_bare_words_and_bad_formats() {
+        let err = parse_stdin_spec("hello").unwrap_err();
+        assert!(err.contains("Unknown stdin source 'hello'"), "{}", err);
+        assert!(parse_stdin_spec("(())").is_err());
+        assert!(parse_stdin_spec("((abc)").is_err());
+        assert!(parse_stdin_spec("").unwrap_err().contains("needs a source"));
+        assert!(parse_stdin_spec("previous yaml").unwrap_err().contains("Unknown stdin format"));
+    }
+
+    #[test]
+    fn resolves_stdin_from_the_outline() {
+        let doc = doc_with(&[
+            ("list", None, "fruit"),
+            ("apple", Some("list"), "apple"),
+            ("pear", Some("list"), "pear"),
+            ("cmd", None, "sh:: sort"),
+            ("src", Some("cmd"), "stdin:: previous"),
+            ("byref", None, "sh:: wc -l"),
+            ("ref-src", Some("byref"), "stdin:: ((list)) markdown"),
+            ("child", Some("list"), "sh:: cat"),
+            ("parent-src", Some("child"), "stdin:: parent"),
+        ]);
+
+        assert_eq!(stdin_for(&doc, "cmd").unwrap().as_deref(), Some("fruit\napple\npear\nsh:: cat\n"));
+        assert_eq!(
+            stdin_for(&doc, "byref").unwrap().as_deref(),
+            Some("- fruit\n  - apple\n  - pear\n  - sh:: cat\n")
+        );
+        // The invoking block's own subtree is left out of its parent
+        assert_eq!(stdin_for(&doc, "child").unwrap().as_deref(), Some("fruit\napple\npear\n"));
+        assert_eq!(stdin_for(&doc, "apple").unwrap(), None);
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::{import_blocks, imported_prop, split_prefix, AppState, ParsedBlock};
+
+// ═══════════════════════════════════════════════════════════════
+// LOGSEQ
+// ═══════════════════════════════════════════════════════════════
+//
+// A Logseq graph is a folder of markdown pages (`pages/`, `journals/`),
+// each an outline of `- ` items. Every page becomes a top-level block
+// titled by its `title::` or file name, holding its outline:
+//
+//   tags:: ops, [[deploy]]         → "Deploy" (tags: [ops, deploy])
+//
+//   - TODO ship it                 →   "ship it" (checked: false)
+//     id:: 6651a2c4-...            →     (keeps its ID)
+//     collapsed:: true
+//   	- see ((6651a2c4-...))        →     "see ((6651a2c4-...))"
+//
+// `key:: value` lines under an item are its properties; `id::` is kept as
+// the block's ID, so `((id))` references keep pointing at the same block.
+// A property named like one the app acts on (`watch::`, `status::`, ...)
+// is kept under a `logseq` prefix instead, e.g. `logseqWatch`.
+
+/// Leading whitespace width, counting a tab as a full level of indent
+const TAB_WIDTH: usize = 4;
+
+/// Task markers, and whether each means done
+const MARKERS: &[(&str, bool)] = &[
+    ("TODO ", false),
+    ("LATER ", false),
+    ("NOW ", false),
+    ("DOING ", false),
+    ("WAITING ", false),
+    ("DONE ", true),
+];
+
+/// Page properties that list pages
+const LIST_PROPS: &[&str] = &["tags", "alias"];
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+/// Drop up to `width` columns of leading whitespace
+fn dedent(line: &str, width: usize) -> &str {
+    let mut columns = 0;
+    for (i, c) in line.char_indices() {
+        if columns >= width || !c.is_whitespace() {
+            return &line[i..];
+        }
+        columns += if c == '\t' { TAB_WIDTH } else { 1 };
+    }
+    ""
+}
+
+/// A property value: `true`/`false` as booleans, page lists as lists
+fn property_value(key: &str, value: &str) -> yrs::Any {
+    match value {
+        "true" => yrs::Any::Bool(true),
+        "false" => yrs::Any::Bool(false),
+        _ if LIST_PROPS.contains(&key) => yrs::Any::Array(Arc::from(
+            value
+                .split(',')
+                .map(|item| item.trim().trim_start_matches('#').trim_start_matches("[[").trim_end_matches("]]").trim())
+                .filter(|item| !item.is_empty())
+                .map(|item| yrs::Any::String(item.into()))
+                .collect::<Vec<_>>(),
+        )),
+        _ => yrs::Any::String(value.into()),
+    }
+}
+
+/// Set a block property from a `key:: value` line
+fn set_property(block: &mut ParsedBlock, key: &str, value: &str) {
+    match key {
+        "id" if !value.is_empty() => block.id = value.to_string(),
+        "collapsed" => {
+            block.props.insert("collapsed".to_string(), yrs::Any::Bool(value == "true"));
+        }
+        _ => {
+            block.props.insert(imported_prop("logseq", key), property_value(key, value));
+        }
+    }
+}
+
+/// Turn a leading task marker into the `checked` property
+fn take_marker(block: &mut ParsedBlock) {
+    for (marker, done) in MARKERS {
+        if let Some(rest) = block.content.strip_prefix(marker) {
+            block.content = rest.to_string();
+            block.props.insert("checked".to_string(), yrs::Any::Bool(*done));
+            return;
+        }
+    }
+}
+
+/// An item being read
+struct Item {
+    /// Column of its `-`
+    indent: usize,
+    block: ParsedBlock,
+    /// The fence of a code block still open in its content
+    fence: Option<String>,
+}
+
+impl Item {
+    /// Add a line of content, tracking code fences so nothing inside one is parsed
+    fn push_line(&mut self, line: &str) {
+        if !self.block.content.is_empty() {
+            self.block.content.push('\n');
+        }
+        self.block.content.push_str(line);
+        self.track_fence(line);
+    }
+
+    fn track_fence(&mut self, line: &str) {
+        let trimmed = line.trim_start();
+        let marker: String = trimmed.chars().take_while(|c| *c == '`' || *c == '~').collect();
+        match &self.fence {
+            Some(open) if trimmed.trim_end() == open => self.fence = None,
+            None if marker.len() >= 3 => self.fence = Some(marker),
+            _ => {}
+        }
+    }
+}
+
+/// Parse a page's outline into its blocks, and its page properties
+fn parse_page(text: &str, base_id: &str) -> (Vec<ParsedBlock>, BTreeMap<String, String>) {
+    let mut roots: Vec<ParsedBlock> = vec![];
+    let mut open: Vec<Item> = vec![];
+    let mut page_props = BTreeMap::new();
+    let mut next = 0;
+
+    fn close(open: &mut Vec<Item>, roots: &mut Vec<ParsedBlock>) {
+        if let Some(mut item) = open.pop() {
+            take_marker(&mut item.block);
+            match open.last_mut() {
+                Some(parent) => parent.block.children.push(item.block),
+                None => roots.push(item.block),
+            }
+        }
+    }
+
+    for line in text.lines() {
+        // Inside a code block, every line is the item's content
+        if let Some(item) = open.last_mut().filter(|item| item.fence.is_some()) {
+            let content = dedent(line, item.indent + 2).to_string();
+            item.push_line(&content);
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if trimmed == "-" || trimmed.starts_with("- ") {
+            let indent = indent_width(line);
+            while open.last().is_some_and(|item| item.indent >= indent) {
+                close(&mut open, &mut roots);
+            }
+            let block = ParsedBlock::new(format!("{}-{}", base_id, next), String::new(), "text");
+            next += 1;
+            let mut item = Item { indent, block, fence: None };
+            item.push_line(trimmed[1..].trim_start());
+            open.push(item);
+            continue;
+        }
+
+        match (open.last_mut(), split_prefix(line)) {
+            (Some(item), Some((key, value))) => set_property(&mut item.block, &key, value),
+            (Some(item), None) => {
+                let content = dedent(line, item.indent + 2).to_string();
+                item.push_line(&content);
+            }
+            // Before the first item: page properties, or loose text
+            (None, Some((key, value))) => {
+                page_props.insert(key, value.to_string());
+            }
+            (None, None) => {
+                roots.push(ParsedBlock::new(format!("{}-{}", base_id, next), trimmed.to_string(), "text"));
+                next += 1;
+            }
+        }
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    (roots, page_props)
+}
+
+/// A page's title from its file name: `a___b.md` and `a%2Fb.md` are "a/b",
+/// `2024_01_05.md` in journals is "2024-01-05"
+fn page_title(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let is_journal = stem.len() == 10 && stem.chars().all(|c| c.is_ascii_digit() || c == '_');
+    if is_journal {
+        return stem.replace('_', "-");
+    }
+
+    let stem = stem.replace("___", "/");
+    let bytes = stem.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A page file as one block holding its outline
+fn page_block(path: &Path) -> Result<ParsedBlock, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let base_id = uuid::Uuid::new_v4().to_string();
+    let (children, mut props) = parse_page(&text.replace("\r\n", "\n"), &base_id);
+
+    let title = props.remove("title").filter(|title| !title.is_empty()).unwrap_or_else(|| page_title(path));
+    let mut page = ParsedBlock::new(base_id, title, "text");
+    for (key, value) in props {
+        set_property(&mut page, &key, &value);
+    }
+    page.children = children;
+    Ok(page)
+}
+
+/// Markdown files directly in a folder, by name
+fn page_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Every page of a graph folder - `pages/` then `journals/`, or the folder's own files
+fn graph_pages(dir: &Path) -> Result<Vec<ParsedBlock>, String> {
+    let sections: Vec<PathBuf> = ["pages", "journals"].iter().map(|name| dir.join(name)).filter(|path| path.is_dir()).collect();
+    let files = if sections.is_empty() {
+        page_files(dir)?
+    } else {
+        let mut files = vec![];
+        for section in sections {
+            files.extend(page_files(&section)?);
+        }
+        files
+    };
+    files.iter().map(|path| page_block(path)).collect()
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Import a Logseq graph folder, or a single page file, under a block or at the top level
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_logseq(state: tauri::State<'_, AppState>, parent_id: Option<String>, path: String) -> Result<String, String> {
+    let path = Path::new(&path);
+    let pages = if path.is_dir() { graph_pages(path)? } else { vec![page_block(path)?] };
+    if pages.is_empty() {
+        return Err(format!("No Logseq pages in {}", path.display()));
+    }
+    import_blocks(&state, pages, parent_id.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{ReadTxn, Transact};
+    use crate::testing::doc_with;
+
+    const REF: &str = "6651a2c4-0000-4000-8000-000000000001";
+
+    fn page() -> String {
+        format!(
+            "\
+title:: Deploy Plan
+tags:: ops, [[deploy]], #prod
+watch:: src/**
+
+- TODO ship it
+  id:: {0}
+  collapsed:: true
+\t- DONE tag release
+\t- see (({0}))
+\t  status:: blocked
+- LATER write notes
+  over two lines
+  ```sh
+  - not an item
+  id:: not-a-prop
+  ```
+- plain
+",
+            REF
+        )
+    }
+
+    fn string(value: &str) -> yrs::Any {
+        yrs::Any::String(value.into())
+    }
+
+    #[test]
+    fn items_nest_and_keep_their_ids() {
+        let (roots, _) = parse_page(&page(), "p");
+        assert_eq!(roots.len(), 3);
+
+        let ship = &roots[0];
+        assert_eq!((ship.id.as_str(), ship.content.as_str()), (REF, "ship it"));
+        assert_eq!(ship.props.get("checked"), Some(&yrs::Any::Bool(false)));
+        assert_eq!(ship.props.get("collapsed"), Some(&yrs::Any::Bool(true)));
+
+        let [tag, see] = &ship.children[..] else { panic!("{:?}", ship.children) };
+        assert_eq!(tag.content, "tag release");
+        assert_eq!(tag.props.get("checked"), Some(&yrs::Any::Bool(true)));
+        // The reference is kept as written, and the block it names kept its ID
+        assert_eq!(see.content, format!("see (({}))", REF));
+        assert!(see.id.starts_with("p-"));
+
+        // Nothing inside a code block is a property or an item
+        assert_eq!(roots[1].content, "write notes\nover two lines\n```sh\n- not an item\nid:: not-a-prop\n```");
+        assert_eq!(roots[1].props.get("checked"), Some(&yrs::Any::Bool(false)));
+        assert!(roots[1].children.is_empty());
+        assert_eq!(roots[2].content, "plain");
+        assert!(roots[2].props.is_empty());
+    }
+
+    #[test]
+    fn references_follow_a_reimported_block() {
+        let doc = doc_with(&[(REF, None, "already here")]);
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+        let (mut roots, _) = parse_page(&page(), "p");
+        crate::claim_ids(&blocks, &txn, &mut roots);
+
+        let ship = &roots[0];
+        assert_ne!(ship.id, REF);
+        assert_eq!(ship.children[1].content, format!("see (({}))", ship.id));
+    }
+
+    #[test]
+    fn properties_the_app_acts_on_are_prefixed() {
+        let (roots, page_props) = parse_page(&page(), "p");
+        let see = &roots[0].children[1];
+        assert_eq!(see.props.get("logseqStatus"), Some(&string("blocked")));
+        assert!(!see.props.contains_key("status"));
+
+        let mut block = ParsedBlock::new("b".into(), String::new(), "text");
+        for (key, value) in [("paused", "true"), ("runCount", "3"), ("type", "book"), ("content", "x"), ("owner", "ana")] {
+            set_property(&mut block, key, value);
+        }
+        assert_eq!(block.props.get("logseqPaused"), Some(&yrs::Any::Bool(true)));
+        assert_eq!(block.props.get("logseqRunCount"), Some(&string("3")));
+        assert_eq!(block.props.get("logseqType"), Some(&string("book")));
+        assert_eq!(block.props.get("logseqContent"), Some(&string("x")));
+        assert_eq!(block.props.get("owner"), Some(&string("ana")));
+        assert_eq!((block.content.as_str(), block.block_type.as_str()), ("", "text"));
+        assert_eq!(page_props.get("watch").map(String::as_str), Some("src/**"));
+    }
+
+    #[test]
+    fn pages_take_their_properties() {
+        let dir = std::env::temp_dir().join(format!("float-logseq-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("pages")).unwrap();
+        fs::create_dir_all(dir.join("journals")).unwrap();
+        fs::write(dir.join("pages").join("deploy.md"), page()).unwrap();
+        fs::write(dir.join("pages").join("ops___runbooks%3F.md"), "- first\n").unwrap();
+        fs::write(dir.join("journals").join("2024_01_05.md"), "- standup\n").unwrap();
+        fs::write(dir.join("pages").join("notes.txt"), "- skipped\n").unwrap();
+        let pages = graph_pages(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let titles: Vec<&str> = pages.iter().map(|page| page.content.as_str()).collect();
+        assert_eq!(titles, ["Deploy Plan", "ops/runbooks?", "2024-01-05"]);
+        let deploy = &pages[0];
+        assert_eq!(deploy.props.get("tags"), Some(&yrs::Any::Array(Arc::from(vec![string("ops"), string("deploy"), string("prod")]))));
+        assert_eq!(deploy.props.get("logseqWatch"), Some(&string("src/**")));
+        assert!(!deploy.props.contains_key("watch") && !deploy.props.contains_key("title"));
+        assert_eq!(deploy.children.len(), 3);
+    }
+}
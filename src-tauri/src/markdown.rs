@@ -0,0 +1,1551 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use yrs::{ReadTxn, Transact};
+use crate::output::OUTPUT_TYPES;
+use crate::{import_blocks, imported_prop, read_trees, split_prefix, AppState, BlockData, BlockTree, ParsedBlock};
+
+// ═══════════════════════════════════════════════════════════════
+// MARKDOWN TREE PARSER
+// ═══════════════════════════════════════════════════════════════
+//
+// Markdown becomes a tree of blocks:
+//
+//   # Setup                  → "# Setup"
+//   Install the tools:       →   "Install the tools:"
+//   - rust                   →   "rust"
+//     1. rustup              →     "1. rustup"
+//     2. cargo               →     "2. cargo"
+//   ## Run                   →   "## Run"
+//   > careful                →     "careful" (quote)
+//   | a | b |                →     "a | b" (table, `columns`)
+//   | 1 | 2 |                →       "1 | 2" (row, `cells`)
+//   - [x] done               →     "done" (`checked`)
+//   ```py                    →     "```py\n...\n```" (code, `language`)
+//
+// Headings hold everything up to the next heading of the same or a higher
+// level, list items hold their sub-lists, and each line of a paragraph is
+// a block of its own. Text with no headings, lists, quotes, tables, code
+// fences or footnotes stays one block per line. Inline markup (emphasis,
+// strong, ~~strikethrough~~, `code`, links and images) is written back into
+// the content as markdown. Footnote definitions move under the block that
+// first refers to them.
+//
+// Markdown written by the exporter reads back as the same blocks: a code
+// block, table or quote opening a list item is that item, `output`/`error`
+// fences become output blocks again under the block they follow, and
+// `<!-- collapsed -->` collapses the block it follows.
+//
+// Code keeps its exact text inside the fence, so a fence whose language
+// has a runner can be run as it is. Inside command output, quotes and code
+// stay output blocks - only their nesting shows - so nothing a command
+// prints is mistaken for a block of the user's.
+
+/// Marks a collapsed block in exported markdown, on its own line after the block
+const COLLAPSED_MARKER: &str = "<!-- collapsed -->";
+
+/// Fence languages that hold a block's run output in exported markdown
+const OUTPUT_FENCES: &[&str] = &["output", "error"];
+
+/// Convert heading level to numeric depth (H1=1, H2=2, etc.)
+fn heading_level_to_depth(level: HeadingLevel) -> usize {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// Parser options for everything read here (GitHub-flavored)
+pub fn options() -> Options {
+    Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TABLES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_GFM
+}
+
+fn strings_to_any(values: &[String]) -> yrs::Any {
+    yrs::Any::Array(Arc::from(
+        values.iter().map(|s| yrs::Any::String(s.clone().into())).collect::<Vec<_>>()
+    ))
+}
+
+/// Find a block anywhere in a tree
+fn find_block<'b>(blocks: &'b mut [ParsedBlock], id: &str) -> Option<&'b mut ParsedBlock> {
+    for block in blocks {
+        if block.id == id {
+            return Some(block);
+        }
+        if let Some(found) = find_block(&mut block.children, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// Length of the longest run of backticks in some text
+fn longest_backticks(text: &str) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    longest
+}
+
+/// Inline code with enough backticks around it to hold any inside
+fn inline_code(code: &str) -> String {
+    let fence = "`".repeat(longest_backticks(code) + 1);
+    // A space keeps a backtick at either end from joining the fence
+    let pad = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+    format!("{}{}{}{}{}", fence, pad, code, pad, fence)
+}
+
+/// A fenced code block around the exact code, fenced with more backticks than
+/// any run inside it
+pub fn fence_code(info: &str, code: &str) -> String {
+    let fence = "`".repeat((longest_backticks(code) + 1).max(3));
+    let newline = if code.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}\n{}{}{}", fence, info, code, newline, fence)
+}
+
+/// The `](url "title")` that ends a link or image
+fn link_end(dest_url: &str, title: &str) -> String {
+    // Angle brackets keep spaces and parentheses in the URL from ending it
+    let url = if dest_url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", dest_url)
+    } else {
+        dest_url.to_string()
+    };
+    if title.is_empty() {
+        format!("]({})", url)
+    } else {
+        format!("]({} \"{}\")", url, title.replace('"', "\\\""))
+    }
+}
+
+/// What an open block holds on to
+#[derive(Debug, Clone, PartialEq)]
+enum Container {
+    /// A heading of this depth, until a heading at the same depth or above
+    Heading(usize),
+    /// A list item, until the item ends
+    Item,
+    Quote,
+    /// A footnote definition, set aside until the end
+    Footnote(String),
+}
+
+impl Container {
+    /// Whether the first text inside becomes the block's own content
+    fn takes_text(&self) -> bool {
+        !matches!(self, Container::Heading(_))
+    }
+}
+
+/// A table being read
+struct TableState {
+    block: ParsedBlock,
+    /// Cells of the current row
+    cells: Vec<String>,
+}
+
+/// A block that later blocks may still nest under
+struct Open {
+    kind: Container,
+    block: ParsedBlock,
+}
+
+/// Builds the block tree from parser events
+struct TreeBuilder<'a> {
+    base_id: &'a str,
+    block_type: &'a str,
+    next: usize,
+    roots: Vec<ParsedBlock>,
+    open: Vec<Open>,
+    /// Next number of each open list (None for bullet lists)
+    lists: Vec<Option<u64>>,
+    /// Whether each open quote is the list item it opened (rather than a block of its own)
+    quotes: Vec<bool>,
+    /// What closes each open link or image
+    link_ends: Vec<String>,
+    /// Emphasis markers open on the current line
+    marks: Vec<&'static str>,
+    table: Option<TableState>,
+    /// Info string of the code block being read ("" for an indented one)
+    code_info: Option<String>,
+    /// Footnotes referenced in the pending text
+    refs: Vec<String>,
+    /// Block that first referenced each footnote
+    referenced: HashMap<String, String>,
+    /// Footnote definitions, by label
+    footnotes: Vec<(String, ParsedBlock)>,
+    /// Text of the line being read
+    text: String,
+}
+
+impl<'a> TreeBuilder<'a> {
+    fn new(base_id: &'a str, block_type: &'a str) -> Self {
+        Self {
+            base_id,
+            block_type,
+            next: 0,
+            roots: vec![],
+            open: vec![],
+            lists: vec![],
+            quotes: vec![],
+            link_ends: vec![],
+            marks: vec![],
+            table: None,
+            code_info: None,
+            refs: vec![],
+            referenced: HashMap::new(),
+            footnotes: vec![],
+            text: String::new(),
+        }
+    }
+
+    /// A new block with the next ID in document order
+    fn block(&mut self, content: String) -> ParsedBlock {
+        let id = format!("{}-{}", self.base_id, self.next);
+        self.next += 1;
+        ParsedBlock::new(id, content, self.block_type)
+    }
+
+    /// The type for a structural block (quote, code) - but inside command output
+    /// everything stays output
+    fn kind_type(&self, kind: &str) -> String {
+        if OUTPUT_TYPES.contains(&self.block_type) {
+            self.block_type.to_string()
+        } else {
+            kind.to_string()
+        }
+    }
+
+    /// Remember which block the pending text's footnote references ended up in
+    fn note_refs(&mut self, block_id: &str) {
+        for label in self.refs.drain(..) {
+            self.referenced.entry(label).or_insert_with(|| block_id.to_string());
+        }
+    }
+
+    /// Add a finished block under the innermost open block
+    fn push(&mut self, block: ParsedBlock) {
+        match self.open.last_mut() {
+            Some(open) => open.block.children.push(block),
+            None => self.roots.push(block),
+        }
+    }
+
+    /// Add run output under the block it follows: the innermost open block, or
+    /// the last block added in it (a paragraph's output, in heading markdown)
+    fn push_output(&mut self, output: Vec<ParsedBlock>) {
+        let siblings = match self.open.last_mut() {
+            Some(open) => &mut open.block.children,
+            None => &mut self.roots,
+        };
+        match siblings.last_mut() {
+            Some(last) if !OUTPUT_TYPES.contains(&last.block_type.as_str()) => last.children.extend(output),
+            _ => match self.open.last_mut() {
+                Some(open) => open.block.children.extend(output),
+                None => self.roots.extend(output),
+            },
+        }
+    }
+
+    /// Whether the innermost open block is still waiting for its own text
+    fn wants_text(&self) -> bool {
+        matches!(self.open.last(), Some(Open { kind, block }) if kind.takes_text() && block.content.is_empty() && block.children.is_empty())
+    }
+
+    /// The list item that's still waiting for its own text - a code block,
+    /// table or quote at the very start of an item becomes the item itself
+    fn item_awaiting(&mut self) -> Option<&mut ParsedBlock> {
+        if !self.wants_text() {
+            return None;
+        }
+        match self.open.last_mut() {
+            Some(Open { kind: Container::Item, block }) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// Turn the pending text into a block - the content of a list item (or quote,
+    /// footnote) that has none yet, otherwise a child of whatever is open
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+
+        if self.wants_text() {
+            if let Some(open) = self.open.last_mut() {
+                open.block.content.push_str(text);
+                let id = open.block.id.clone();
+                self.note_refs(&id);
+                return;
+            }
+        }
+        let block = self.block(text.to_string());
+        self.note_refs(&block.id);
+        self.push(block);
+    }
+
+    /// End the current line, carrying any emphasis still open over to the next one
+    fn line_break(&mut self) {
+        for mark in self.marks.iter().rev() {
+            self.text.push_str(mark);
+        }
+        self.flush();
+        self.text = self.marks.concat();
+    }
+
+    /// Open or close an emphasis marker
+    fn mark(&mut self, mark: &'static str, opening: bool) {
+        self.text.push_str(mark);
+        if opening {
+            self.marks.push(mark);
+        } else {
+            self.marks.pop();
+        }
+    }
+
+    fn open(&mut self, kind: Container, content: String) {
+        let block = self.block(content);
+        self.open.push(Open { kind, block });
+    }
+
+    /// Close the innermost open block, dropping it if nothing ended up in it
+    fn close(&mut self) -> Option<Container> {
+        let Open { kind, mut block } = self.open.pop()?;
+        if let Container::Footnote(label) = &kind {
+            block.content = format!("[^{}]: {}", label, block.content).trim_end().to_string();
+            self.footnotes.push((label.clone(), block));
+        } else if !block.content.is_empty() || !block.children.is_empty() {
+            self.push(block);
+        }
+        Some(kind)
+    }
+
+    /// Close headings that a new heading of this depth ends
+    fn close_headings(&mut self, depth: usize) {
+        while matches!(self.open.last(), Some(Open { kind: Container::Heading(open), .. }) if *open >= depth) {
+            self.close();
+        }
+    }
+
+    /// Close everything up to and including the innermost container of a kind
+    fn close_through(&mut self, matches: fn(&Container) -> bool) {
+        while let Some(kind) = self.close() {
+            if matches(&kind) {
+                break;
+            }
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                self.flush();
+                let depth = heading_level_to_depth(level);
+                self.close_headings(depth);
+                self.open(Container::Heading(depth), String::new());
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                // Keep the # prefix for editor styling
+                let text = std::mem::take(&mut self.text);
+                let prefix = "#".repeat(heading_level_to_depth(level));
+                if let Some(open) = self.open.last_mut() {
+                    open.block.content = format!("{} {}", prefix, text.trim());
+                    let id = open.block.id.clone();
+                    self.note_refs(&id);
+                }
+            }
+            Event::Start(Tag::List(start)) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                self.flush();
+                // Ordered items keep their number - the outline has no other way to show it
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::new(),
+                };
+                self.open(Container::Item, String::new());
+                self.text = marker;
+            }
+            Event::End(TagEnd::Item) => {
+                self.flush();
+                self.close_through(|kind| *kind == Container::Item);
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(Open { kind: Container::Item, block }) = self.open.last_mut() {
+                    block.props.insert("checked".to_string(), yrs::Any::Bool(checked));
+                }
+            }
+            Event::Start(Tag::BlockQuote(callout)) => {
+                self.flush();
+                let block_type = self.kind_type("quote");
+                let opens_item = self.item_awaiting().is_some();
+                if !opens_item {
+                    self.open(Container::Quote, String::new());
+                }
+                self.quotes.push(opens_item);
+                if let Some(open) = self.open.last_mut() {
+                    open.block.block_type = block_type;
+                    // GitHub alerts: > [!NOTE], > [!WARNING], ...
+                    if let Some(callout) = callout {
+                        let name = format!("{:?}", callout).to_lowercase();
+                        open.block.props.insert("callout".to_string(), yrs::Any::String(name.into()));
+                    }
+                }
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                self.flush();
+                // A quote that is its item ends with the item instead
+                if !self.quotes.pop().unwrap_or(false) {
+                    self.close_through(|kind| *kind == Container::Quote);
+                }
+            }
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                self.flush();
+                self.open(Container::Footnote(label.to_string()), String::new());
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                self.flush();
+                self.close_through(|kind| matches!(kind, Container::Footnote(_)));
+            }
+            Event::FootnoteReference(label) => {
+                self.text.push_str(&format!("[^{}]", label));
+                self.refs.push(label.to_string());
+            }
+            Event::Start(Tag::Table(_)) => {
+                self.flush();
+                let mut block = self.block(String::new());
+                block.block_type = "table".to_string();
+                self.table = Some(TableState { block, cells: vec![] });
+            }
+            Event::End(TagEnd::TableCell) => {
+                let cell = std::mem::take(&mut self.text).trim().to_string();
+                if let Some(table) = self.table.as_mut() {
+                    table.cells.push(cell);
+                }
+            }
+            Event::End(TagEnd::TableHead) => {
+                if let Some(table) = self.table.as_mut() {
+                    let columns = std::mem::take(&mut table.cells);
+                    table.block.content = columns.join(" | ");
+                    table.block.props.insert("columns".to_string(), strings_to_any(&columns));
+                    let id = table.block.id.clone();
+                    self.note_refs(&id);
+                }
+            }
+            Event::End(TagEnd::TableRow) => {
+                let mut row = self.block(String::new());
+                row.block_type = "row".to_string();
+                self.note_refs(&row.id);
+                if let Some(table) = self.table.as_mut() {
+                    let cells = std::mem::take(&mut table.cells);
+                    row.content = cells.join(" | ");
+                    row.props.insert("cells".to_string(), strings_to_any(&cells));
+                    table.block.children.push(row);
+                }
+            }
+            Event::End(TagEnd::Table) => {
+                let Some(table) = self.table.take() else { return };
+                match self.item_awaiting() {
+                    Some(item) => {
+                        item.content = table.block.content;
+                        item.block_type = table.block.block_type;
+                        item.children = table.block.children;
+                        item.props.extend(table.block.props);
+                    }
+                    None => self.push(table.block),
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                self.flush();
+                self.code_info = Some(match kind {
+                    CodeBlockKind::Fenced(info) => info.trim().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                });
+            }
+            Event::Text(text) | Event::InlineHtml(text) => self.text.push_str(&text),
+            Event::Code(code) => self.text.push_str(&inline_code(&code)),
+            Event::Start(Tag::Emphasis) => self.mark("*", true),
+            Event::End(TagEnd::Emphasis) => self.mark("*", false),
+            Event::Start(Tag::Strong) => self.mark("**", true),
+            Event::End(TagEnd::Strong) => self.mark("**", false),
+            Event::Start(Tag::Strikethrough) => self.mark("~~", true),
+            Event::End(TagEnd::Strikethrough) => self.mark("~~", false),
+            // Autolinks are just their URL
+            Event::Start(Tag::Link { link_type: LinkType::Autolink | LinkType::Email, .. }) => self.link_ends.push(String::new()),
+            Event::Start(Tag::Link { dest_url, title, .. }) => {
+                self.text.push('[');
+                self.link_ends.push(link_end(&dest_url, &title));
+            }
+            Event::Start(Tag::Image { dest_url, title, .. }) => {
+                self.text.push_str("![");
+                self.link_ends.push(link_end(&dest_url, &title));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                let end = self.link_ends.pop().unwrap_or_default();
+                self.text.push_str(&end);
+            }
+            // A wrapped list item (or quote) is still one block, and keeps its line breaks
+            Event::SoftBreak if self.wants_text() => self.text.push(' '),
+            Event::HardBreak if self.wants_text() => self.text.push('\n'),
+            Event::Html(html) if html.trim() == COLLAPSED_MARKER => {
+                self.flush();
+                if let Some(open) = self.open.last_mut() {
+                    open.block.props.insert("collapsed".to_string(), yrs::Any::Bool(true));
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => self.line_break(),
+            Event::End(TagEnd::Paragraph) => self.flush(),
+            Event::End(TagEnd::CodeBlock) => {
+                // The text is exactly what was between the fences - no trimming
+                let code = std::mem::take(&mut self.text);
+                let info = self.code_info.take().unwrap_or_default();
+                if code.trim().is_empty() {
+                    return;
+                }
+
+                // Exported run output goes back to being output blocks
+                if OUTPUT_FENCES.contains(&info.as_str()) && !OUTPUT_TYPES.contains(&self.block_type) {
+                    let base_id = format!("{}-{}", self.base_id, self.next);
+                    self.next += 1;
+                    self.push_output(parse_markdown_tree(&code, &base_id, &info));
+                    return;
+                }
+
+                // An exported multi-line `prefix::` block goes back to being one
+                if let Some(content) = prefix_from_fence(&info, &code).filter(|_| !OUTPUT_TYPES.contains(&self.block_type)) {
+                    match self.item_awaiting() {
+                        Some(item) => item.content = content,
+                        None => {
+                            let block = self.block(content);
+                            self.push(block);
+                        }
+                    }
+                    return;
+                }
+
+                let content = fence_code(&info, &code);
+                let block_type = self.kind_type("code");
+                let mut props = BTreeMap::new();
+                if let Some(language) = info.split_whitespace().next() {
+                    props.insert("language".to_string(), yrs::Any::String(language.to_lowercase().into()));
+                }
+                match self.item_awaiting() {
+                    Some(item) => {
+                        item.content = content;
+                        item.block_type = block_type;
+                        item.props.extend(props);
+                    }
+                    None => {
+                        let mut block = self.block(content);
+                        block.block_type = block_type;
+                        block.props = props;
+                        self.push(block);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<ParsedBlock> {
+        self.flush();
+        while self.close().is_some() {}
+
+        // Footnotes go under the block referring to them (or at the end if nothing does)
+        for (label, footnote) in std::mem::take(&mut self.footnotes) {
+            let target = self.referenced.get(&label).and_then(|id| find_block(&mut self.roots, id));
+            match target {
+                Some(block) => block.children.push(footnote),
+                None => self.roots.push(footnote),
+            }
+        }
+        self.roots
+    }
+}
+
+/// Parse markdown content into a tree of blocks following its headings, lists, quotes,
+/// tables, code fences and footnotes
+/// Returns a flat list of lines if there are none
+pub fn parse_markdown_tree(content: &str, base_id: &str, block_type: &str) -> Vec<ParsedBlock> {
+    // Indented code doesn't count - indented lines are common in command output
+    let has_structure = Parser::new_ext(content, options()).any(|event| {
+        matches!(
+            event,
+            Event::Start(
+                Tag::Heading { .. }
+                    | Tag::List(_)
+                    | Tag::BlockQuote(_)
+                    | Tag::Table(_)
+                    | Tag::CodeBlock(CodeBlockKind::Fenced(_))
+                    | Tag::FootnoteDefinition(_)
+            )
+        )
+    });
+
+    // Plain text (most command output) keeps one block per line
+    if !has_structure {
+        return content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| ParsedBlock::new(format!("{}-{}", base_id, i), line.to_string(), block_type))
+            .collect();
+    }
+
+    parse_markdown_document(content, base_id, block_type)
+}
+
+/// Parse a markdown document into a tree of blocks, however little structure it has
+fn parse_markdown_document(content: &str, base_id: &str, block_type: &str) -> Vec<ParsedBlock> {
+    let mut builder = TreeBuilder::new(base_id, block_type);
+    for event in Parser::new_ext(content, options()) {
+        builder.event(event);
+    }
+    builder.finish()
+}
+
+// ═══════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════
+//
+// A markdown file (or pasted text) goes in under any block, or at the top
+// level. YAML front matter turns the document into one block - titled by
+// its `title`, or the file name - holding the front matter as properties
+// (behind a `markdown` prefix for names the app acts on, so `paused: true`
+// is `markdownPaused`), with the document's tree under it:
+//
+//   ---
+//   title: Deploy notes        → "Deploy notes" (tags: [ops, prod])
+//   tags: [ops, prod]          →   "# Steps"
+//   ---                        →     ...
+//   # Steps
+//
+// Importing a directory brings in every `.md` file in it as a subtree of
+// its own, and each sub-directory as a block holding its files.
+
+/// Markdown file extensions picked up from a directory
+const EXTENSIONS: &[&str] = &["md", "markdown"];
+
+/// Split YAML front matter off the top of a document
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text.strip_prefix("---\n") else { return (None, text) };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+/// A front matter scalar: quoted or bare strings, numbers and booleans
+fn yaml_scalar(value: &str) -> yrs::Any {
+    let value = value.trim();
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"')) || (value.starts_with('\'') && value.ends_with('\'')));
+    if quoted {
+        return yrs::Any::String(value[1..value.len() - 1].into());
+    }
+    match value {
+        "true" | "yes" => yrs::Any::Bool(true),
+        "false" | "no" => yrs::Any::Bool(false),
+        "" | "~" | "null" => yrs::Any::Null,
+        _ => match value.parse::<f64>() {
+            Ok(number) if value.chars().all(|c| c.is_ascii_digit() || "+-.".contains(c)) => yrs::Any::Number(number),
+            _ => yrs::Any::String(value.into()),
+        },
+    }
+}
+
+/// A front matter value: `[a, b]` lists, or a scalar
+fn yaml_value(value: &str) -> yrs::Any {
+    match value.trim().strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => yrs::Any::Array(Arc::from(
+            items.split(',').filter(|item| !item.trim().is_empty()).map(yaml_scalar).collect::<Vec<_>>(),
+        )),
+        None => yaml_scalar(value),
+    }
+}
+
+/// Read front matter's top-level keys
+/// Handles scalars, inline and `- item` lists, and `|`/`>` text; anything
+/// nested deeper is kept as its YAML text
+fn parse_front_matter(yaml: &str) -> BTreeMap<String, yrs::Any> {
+    let mut props = BTreeMap::new();
+    let lines: Vec<&str> = yaml.lines().collect();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        if line.trim().is_empty() || line.starts_with('#') || line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let (key, value) = (key.trim().to_string(), value.trim());
+
+        // The indented lines that belong to this key
+        let start = i;
+        while i < lines.len() && (lines[i].trim().is_empty() || lines[i].starts_with([' ', '\t'])) {
+            i += 1;
+        }
+        let nested: Vec<&str> = lines[start..i].iter().map(|l| l.trim()).filter(|l| !l.is_empty()).collect();
+
+        let value = match value {
+            "|" | "|-" => yrs::Any::String(nested.join("\n").into()),
+            ">" | ">-" => yrs::Any::String(nested.join(" ").into()),
+            "" if !nested.is_empty() && nested.iter().all(|l| l.starts_with("- ") || *l == "-") => yrs::Any::Array(Arc::from(
+                nested.iter().map(|l| yaml_scalar(l.trim_start_matches('-'))).collect::<Vec<_>>(),
+            )),
+            "" if !nested.is_empty() => yrs::Any::String(lines[start..i].join("\n").trim_end().into()),
+            _ => yaml_value(value),
+        };
+        props.insert(key, value);
+    }
+    props
+}
+
+/// A markdown document as blocks
+/// With front matter (or when `always_wrap`), the document becomes a single
+/// block titled by its `title` or `name`, holding the rest of the tree
+fn document_blocks(text: &str, name: &str, always_wrap: bool) -> Vec<ParsedBlock> {
+    let text = text.replace("\r\n", "\n");
+    let (front_matter, body) = split_front_matter(&text);
+    let base_id = uuid::Uuid::new_v4().to_string();
+    // A file is markdown even without headings or lists - no plain-text fallback
+    let tree = parse_markdown_document(body, &base_id, "text");
+
+    let Some(front_matter) = front_matter.or(always_wrap.then_some("")) else { return tree };
+    let mut props = parse_front_matter(front_matter);
+    let title = match props.remove("title") {
+        Some(yrs::Any::String(title)) if !title.trim().is_empty() => title.to_string(),
+        _ => name.to_string(),
+    };
+
+    let mut document = ParsedBlock::new(base_id, title, "text");
+    document.children = tree;
+    document.props = props.into_iter().map(|(key, value)| (imported_prop("markdown", &key), value)).collect();
+    vec![document]
+}
+
+/// Name of a file or directory without its extension
+pub fn file_title(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+}
+
+/// Every markdown file under a directory, one subtree per file and a block per sub-directory
+/// Hidden entries and directories without markdown files are skipped
+fn directory_blocks(dir: &Path) -> Result<Vec<ParsedBlock>, String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| !path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')))
+        .collect();
+    entries.sort();
+
+    let mut parsed = vec![];
+    for path in entries {
+        if path.is_dir() {
+            let children = directory_blocks(&path)?;
+            if !children.is_empty() {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                let mut folder = ParsedBlock::new(uuid::Uuid::new_v4().to_string(), name, "text");
+                folder.children = children;
+                parsed.push(folder);
+            }
+        } else if path.extension().is_some_and(|ext| EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())) {
+            let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            parsed.extend(document_blocks(&text, &file_title(&path), true));
+        }
+    }
+    Ok(parsed)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════
+//
+// Blocks go out as nested bullets, or as headings for anything with
+// children and any text after a heading (paragraphs for the rest, bullets
+// again past `######`):
+//
+//   - cwd:: ~/code/app           # app
+//     - sh:: cargo test          ## cwd:: ~/code/app  ...
+//       ```output
+//       test result: ok
+//       ```
+//
+// Content is written as it is - `prefix::` and all - with anything that
+// would read as markdown structure escaped; quotes are written as `>`
+// quotes. Multi-line `prefix::` content goes in a fence with `prefix::` as
+// its info string, so code keeps its exact text. Run output goes in `output` and `error` fences under its block.
+// Either style reads back through the import as the same tree (headings
+// keep their `#`s). A whole-workspace export starts at the root block's
+// children.
+
+/// How blocks are laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportStyle {
+    /// Every block a list item, nested as in the outline
+    #[default]
+    Bullets,
+    /// Blocks with children as headings, the rest as paragraphs
+    Headings,
+}
+
+/// How to write the markdown
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    pub style: ExportStyle,
+    /// Write `<!-- collapsed -->` after collapsed blocks
+    pub mark_collapsed: bool,
+    /// Write run output in `output`/`error` fences
+    pub include_output: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self { style: ExportStyle::Bullets, mark_collapsed: false, include_output: true }
+    }
+}
+
+/// Run output, written as fences rather than as blocks - except tables,
+/// which stay tables
+fn is_output(tree: &BlockTree) -> bool {
+    OUTPUT_TYPES.contains(&tree.block.get_str("type").unwrap_or_default()) && !is_table(tree)
+}
+
+fn is_fence(content: &str) -> bool {
+    content.starts_with("```") || content.starts_with("~~~")
+}
+
+/// Multi-line `prefix::` content as the fence it's exported in: `prefix::`
+/// for the info string and the value as the code, so indentation and
+/// backslashes survive
+fn prefix_fence(content: &str) -> Option<(String, &str)> {
+    if !content.contains('\n') || split_prefix(content).is_none() {
+        return None;
+    }
+    let (prefix, value) = content.split_once("::")?;
+    Some((format!("{}::", prefix.trim_start()), value.strip_prefix(' ').unwrap_or(value)))
+}
+
+/// The `prefix::` content held by a fence written by `prefix_fence`
+fn prefix_from_fence(info: &str, code: &str) -> Option<String> {
+    let prefix = info.trim();
+    if !prefix.ends_with("::") || !matches!(split_prefix(prefix), Some((_, ""))) {
+        return None;
+    }
+    let code = code.strip_suffix('\n').unwrap_or(code);
+    let space = if code.starts_with('\n') { "" } else { " " };
+    Some(format!("{}{}{}", prefix, space, code))
+}
+
+/// Escape what would start a heading, list, quote or other structure at the start of a line
+fn escape_line(line: &str) -> String {
+    let line = line.trim_start();
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let after_digits = &line[digits..];
+    if digits > 0 && (after_digits.starts_with(". ") || after_digits.starts_with(") ") || after_digits == "." || after_digits == ")") {
+        return format!("{}\\{}", &line[..digits], after_digits);
+    }
+
+    let space_or_end = |c: Option<char>| matches!(c, None | Some(' ' | '\t'));
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    let rest = line.chars().nth(1);
+    let heading = (1..=6).contains(&hashes) && space_or_end(line[hashes..].chars().next());
+    let bullet = line.starts_with(['-', '+', '*']) && space_or_end(rest);
+    // Thematic breaks and setext underlines: ---, ***, ___, ===
+    let rule = line.starts_with(['-', '*', '_', '=']) && line.chars().all(|c| c.is_whitespace() || line.starts_with(c));
+    let fence = line.starts_with("```") || line.starts_with("~~~");
+    let html = line.starts_with('<') && rest.is_some_and(|c| c.is_ascii_alphabetic() || "/!?".contains(c));
+    let definition = line.starts_with('[') && (line.contains("]:") || line.starts_with("[ ]") || line.starts_with("[x]"));
+
+    if heading || bullet || rule || fence || html || definition || line.starts_with('>') {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Cells of a table row, with pipes escaped
+fn table_row(cells: &[String]) -> String {
+    let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+    format!("| {} |", cells.join(" | "))
+}
+
+/// Strings in a list prop (`columns`, `cells`)
+pub fn prop_strings(block: &BlockData, key: &str) -> Vec<String> {
+    match block.0.get(key) {
+        Some(yrs::Any::Array(values)) => values.iter().map(|v| match v {
+            yrs::Any::String(s) => s.to_string(),
+            other => other.to_string(),
+        }).collect(),
+        _ => vec![],
+    }
+}
+
+/// A table block and its rows as a GFM table
+fn table_lines(tree: &BlockTree) -> Vec<String> {
+    let mut columns = prop_strings(&tree.block, "columns");
+    if columns.is_empty() {
+        columns = tree.block.content().split(" | ").map(str::to_string).collect();
+    }
+    let mut lines = vec![table_row(&columns), table_row(&vec!["---".to_string(); columns.len()])];
+    for row in &tree.children {
+        lines.push(table_row(&prop_strings(&row.block, "cells")));
+    }
+    lines
+}
+
+pub fn is_table(tree: &BlockTree) -> bool {
+    tree.block.get_str("type") == Some("table") && tree.children.iter().all(|row| row.block.get_str("type") == Some("row"))
+}
+
+/// A block's own content as markdown lines
+/// Several lines of text are joined with hard breaks, so they stay one block
+fn content_lines(tree: &BlockTree) -> Vec<String> {
+    let content = tree.block.content();
+    if is_table(tree) {
+        return table_lines(tree);
+    }
+    if is_fence(content) {
+        return content.lines().map(str::to_string).collect();
+    }
+    if let Some((info, value)) = prefix_fence(content).filter(|_| tree.block.get_str("type") != Some("quote")) {
+        return fence_code(&info, value).lines().map(str::to_string).collect();
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    let last = lines.len().saturating_sub(1);
+    let lines = lines.iter().enumerate().map(|(i, line)| {
+        let mut escaped = escape_line(line);
+        // A following line keeps its indentation behind an entity, which markdown doesn't strip
+        let indent = &line[..line.len() - line.trim_start().len()];
+        if let Some(first) = indent.chars().next().filter(|_| i > 0) {
+            escaped = format!("&#{};{}{}", first as u32, &indent[first.len_utf8()..], escaped);
+        }
+        if i == last {
+            return escaped;
+        }
+        // A backslash of the line's own would turn the hard break into an escape
+        if escaped.ends_with('\\') {
+            escaped.push('\\');
+        }
+        format!("{}\\", escaped)
+    });
+
+    if tree.block.get_str("type") != Some("quote") {
+        return lines.collect();
+    }
+    let callout = tree.block.get_str("callout").map(|name| format!("[!{}]", name.to_uppercase()));
+    callout.into_iter().chain(lines).map(|line| format!("> {}", line).trim_end().to_string()).collect()
+}
+
+/// The children written as blocks of their own (not output, not table rows)
+fn child_blocks(tree: &BlockTree) -> Vec<&BlockTree> {
+    if is_table(tree) {
+        return vec![];
+    }
+    tree.children.iter().filter(|child| !is_output(child)).collect()
+}
+
+/// Run output under a block as `output`/`error` fences
+fn output_lines(tree: &BlockTree) -> Vec<String> {
+    // Each stream's consecutive lines share a fence
+    let mut runs: Vec<(&str, String)> = vec![];
+    fn collect<'t>(tree: &'t BlockTree, runs: &mut Vec<(&'t str, String)>) {
+        let stream = if tree.block.get_str("type") == Some("error") { "error" } else { "output" };
+        match runs.last_mut() {
+            Some((last, text)) if *last == stream => text.push_str(tree.block.content()),
+            _ => runs.push((stream, tree.block.content().to_string())),
+        }
+        if let Some((_, text)) = runs.last_mut() {
+            text.push('\n');
+        }
+        for child in &tree.children {
+            collect(child, runs);
+        }
+    }
+    for child in tree.children.iter().filter(|child| is_output(child)) {
+        collect(child, &mut runs);
+    }
+
+    runs.iter().flat_map(|(stream, text)| fence_code(stream, text).lines().map(str::to_string).collect::<Vec<_>>()).collect()
+}
+
+/// Lines that follow a block's content: the collapsed marker and its output
+fn trailing_lines(tree: &BlockTree, options: &ExportOptions) -> Vec<String> {
+    let mut lines = vec![];
+    let collapsed = tree.block.get_bool("collapsed").unwrap_or(false);
+    if options.mark_collapsed && collapsed && !tree.children.is_empty() {
+        lines.push(COLLAPSED_MARKER.to_string());
+    }
+    if options.include_output {
+        lines.extend(output_lines(tree));
+    }
+    lines
+}
+
+/// A block and its descendants as nested list items
+fn bullet_lines(tree: &BlockTree, depth: usize, options: &ExportOptions, out: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    let task = match tree.block.get_bool("checked") {
+        Some(true) => "[x] ",
+        Some(false) => "[ ] ",
+        None => "",
+    };
+
+    let mut lines = content_lines(tree).into_iter();
+    out.push(format!("{}- {}{}", indent, task, lines.next().unwrap_or_default()).trim_end().to_string());
+    for line in lines.chain(trailing_lines(tree, options)) {
+        // Blank lines stay blank, so code keeps its exact text
+        out.push(if line.is_empty() { line } else { format!("{}  {}", indent, line) });
+    }
+    for child in child_blocks(tree) {
+        bullet_lines(child, depth + 1, options, out);
+    }
+}
+
+/// A block as a heading over its children, or a paragraph if it has none
+/// Text following a heading is a heading too, so it doesn't read as part of
+/// that heading's section; returns whether the block was written as a heading
+fn heading_lines(tree: &BlockTree, level: usize, after_heading: bool, options: &ExportOptions, out: &mut Vec<String>) -> bool {
+    let children = child_blocks(tree);
+
+    // Too deep for a heading, or only a checkbox keeps its state as a task item
+    if ((!children.is_empty() || after_heading) && level > 6) || tree.block.get_bool("checked").is_some() {
+        bullet_lines(tree, 0, options, out);
+        out.push(String::new());
+        return false;
+    }
+
+    let content = tree.block.content();
+    let paragraph = (children.is_empty() && (!after_heading || tree.block.get_str("type") == Some("quote")))
+        || is_table(tree)
+        || is_fence(content)
+        || prefix_fence(content).is_some();
+    if paragraph {
+        out.extend(content_lines(tree));
+    } else {
+        let title = content.trim_start_matches('#').lines().collect::<Vec<_>>().join(" ");
+        out.push(format!("{} {}", "#".repeat(level), title.trim()));
+    }
+    out.extend(trailing_lines(tree, options));
+    out.push(String::new());
+
+    let mut after_heading = false;
+    for child in children {
+        after_heading |= heading_lines(child, level + 1, after_heading, options, out);
+    }
+    !paragraph
+}
+
+/// Render blocks as markdown
+fn render_markdown(trees: &[BlockTree], options: &ExportOptions) -> String {
+    let mut lines = vec![];
+    let mut after_heading = false;
+    for tree in trees {
+        match options.style {
+            ExportStyle::Bullets => bullet_lines(tree, 0, options, &mut lines),
+            ExportStyle::Headings => after_heading |= heading_lines(tree, 1, after_heading, options, &mut lines),
+        }
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Import a markdown file (`path`) or markdown text (`text`) under a block, or at the top level
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_markdown(
+    state: tauri::State<'_, AppState>,
+    parent_id: Option<String>,
+    path: Option<String>,
+    text: Option<String>,
+) -> Result<String, String> {
+    let parsed = match (path, text) {
+        (Some(path), None) => {
+            let path = PathBuf::from(path);
+            let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            document_blocks(&text, &file_title(&path), false)
+        }
+        (None, Some(text)) => document_blocks(&text, "Imported", false),
+        _ => return Err("Give either a path or text to import".to_string()),
+    };
+    import_blocks(&state, parsed, parent_id.as_deref())
+}
+
+/// Import every markdown file in a directory, one subtree per file
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_markdown_dir(state: tauri::State<'_, AppState>, parent_id: Option<String>, path: String) -> Result<String, String> {
+    let parsed = directory_blocks(Path::new(&path))?;
+    if parsed.is_empty() {
+        return Err(format!("No markdown files in {}", path));
+    }
+    import_blocks(&state, parsed, parent_id.as_deref())
+}
+
+/// Export a block's subtree, or the whole workspace, as markdown
+/// Writes it to `path` if given; returns the markdown either way
+#[tauri::command]
+pub fn export_markdown(
+    state: tauri::State<'_, AppState>,
+    block_id: Option<String>,
+    options: Option<ExportOptions>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let trees = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        read_trees(&blocks, &txn, block_id.as_deref())?
+    };
+
+    let markdown = render_markdown(&trees, &options.unwrap_or_default());
+    if let Some(path) = path {
+        fs::write(&path, &markdown).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(markdown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{doc_with, set_field};
+
+    /// Blocks as indented `content` lines, with the type of any block that isn't text
+    fn outline(blocks: &[ParsedBlock]) -> String {
+        fn walk(blocks: &[ParsedBlock], depth: usize, out: &mut String) {
+            for block in blocks {
+                out.push_str(&"  ".repeat(depth));
+                out.push_str(&block.content.replace('\n', "\\n"));
+                if block.block_type != "text" {
+                    out.push_str(&format!(" ({})", block.block_type));
+                }
+                out.push('\n');
+                walk(&block.children, depth + 1, out);
+            }
+        }
+        let mut out = String::new();
+        walk(blocks, 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn mixed_lists_nest_under_headings() {
+        let markdown = "\
+# Setup
+- tools
+  1. rustup
+  2. cargo
+     - clippy
+- editor
+## Build
+3. fetch
+4. compile
+   - debug
+   - release
+### Release
+- tag
+  1. push
+# Notes
+1. one
+";
+        assert_eq!(outline(&parse_markdown_tree(markdown, "b", "text")), "\
+# Setup
+  tools
+    1. rustup
+    2. cargo
+      clippy
+  editor
+  ## Build
+    3. fetch
+    4. compile
+      debug
+      release
+    ### Release
+      tag
+        1. push
+# Notes
+  1. one
+");
+    }
+
+    #[test]
+    fn list_numbers_restart_with_each_list() {
+        let markdown = "# A\n1. a\n2. b\n\n- x\n\n1. c\n";
+        assert_eq!(outline(&parse_markdown_tree(markdown, "b", "text")), "# A\n  1. a\n  2. b\n  x\n  1. c\n");
+    }
+
+    #[test]
+    fn quotes_alone_are_structure() {
+        assert_eq!(outline(&parse_markdown_tree("> a quote\n", "b", "text")), "a quote (quote)\n");
+        let tree = parse_markdown_tree("before\n\n> [!NOTE]\n> heads up\n", "b", "text");
+        assert_eq!(outline(&tree), "before\nheads up (quote)\n");
+        assert_eq!(tree[1].props.get("callout"), Some(&yrs::Any::String("note".into())));
+    }
+
+    #[test]
+    fn fences_and_footnotes_are_structure() {
+        let fence = parse_markdown_tree("```sh\necho one\necho two\n```\n", "b", "text");
+        assert_eq!(outline(&fence), "```sh\\necho one\\necho two\\n``` (code)\n");
+        assert_eq!(fence[0].props.get("language"), Some(&yrs::Any::String("sh".into())));
+
+        let prose = parse_markdown_tree("Run this:\n\n```\nmake\nmake install\n```\n", "b", "text");
+        assert_eq!(outline(&prose), "Run this:\n```\\nmake\\nmake install\\n``` (code)\n");
+
+        let footnote = parse_markdown_tree("See here[^1].\n\n[^1]: The source\n", "b", "text");
+        assert_eq!(outline(&footnote), "See here[^1].\n  [^1]: The source\n");
+    }
+
+    /// A list prop as strings
+    fn strings(block: &ParsedBlock, key: &str) -> Vec<String> {
+        match block.props.get(key) {
+            Some(yrs::Any::Array(items)) => items.iter().map(|item| item.to_string()).collect(),
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn tables_keep_their_cells() {
+        let markdown = "\
+| Command | Meaning |
+|---------|---------|
+| `a \\| b` | pipe |
+| x \\|\\| y | or |
+";
+        let tree = parse_markdown_tree(markdown, "b", "text");
+        assert_eq!(outline(&tree), "Command | Meaning (table)\n  `a | b` | pipe (row)\n  x || y | or (row)\n");
+        assert_eq!(strings(&tree[0], "columns"), ["Command", "Meaning"]);
+        assert_eq!(strings(&tree[0].children[0], "cells"), ["`a | b`", "pipe"]);
+        assert_eq!(strings(&tree[0].children[1], "cells"), ["x || y", "or"]);
+    }
+
+    #[test]
+    fn task_items_are_checked_or_not() {
+        let tree = parse_markdown_tree("- [x] done\n- [ ] todo\n  - [X] nested\n- plain\n", "b", "text");
+        assert_eq!(outline(&tree), "done\ntodo\n  nested\nplain\n");
+        let checked = |block: &ParsedBlock| block.props.get("checked").cloned();
+        assert_eq!(checked(&tree[0]), Some(yrs::Any::Bool(true)));
+        assert_eq!(checked(&tree[1]), Some(yrs::Any::Bool(false)));
+        assert_eq!(checked(&tree[1].children[0]), Some(yrs::Any::Bool(true)));
+        assert_eq!(checked(&tree[2]), None);
+    }
+
+    #[test]
+    fn footnotes_go_under_their_first_reference() {
+        let markdown = "\
+# Notes
+- first[^src]
+- second[^src] and[^other]
+
+[^other]: Another
+[^src]: The source
+[^unused]: Nobody
+";
+        assert_eq!(outline(&parse_markdown_tree(markdown, "b", "text")), "\
+# Notes
+  first[^src]
+    [^src]: The source
+  second[^src] and[^other]
+    [^other]: Another
+[^unused]: Nobody
+");
+    }
+
+    #[test]
+    fn inline_markup_is_kept() {
+        let markdown = r#"# Inline
+- *soft*, **strong**, ~~gone~~ and `a ``b`` c`
+- [site](https://example.com "The \"site\"") and [spaced](<my notes.md>)
+- ![logo](img/logo.png "Logo") and <https://example.com> and <me@example.com>
+
+*one
+two*
+"#;
+        assert_eq!(outline(&parse_markdown_tree(markdown, "b", "text")), r#"# Inline
+  *soft*, **strong**, ~~gone~~ and ```a ``b`` c```
+  [site](https://example.com "The \"site\"") and [spaced](<my notes.md>)
+  ![logo](img/logo.png "Logo") and https://example.com and me@example.com
+  *one*
+  *two*
+"#);
+    }
+
+    #[test]
+    fn reads_front_matter() {
+        let yaml = "\
+# a comment
+title: \"Deploy: prod\"
+tags: [ops, 'prod', ]
+owners:
+  - ana
+  - 7
+script: |
+  make
+  make install
+summary: >
+  one
+  two
+server:
+  host: example.com
+  port: 22
+draft: no
+empty:
+";
+        let props = parse_front_matter(yaml);
+        let string = |s: &str| yrs::Any::String(s.into());
+        let list = |items: Vec<yrs::Any>| yrs::Any::Array(Arc::from(items));
+        assert_eq!(props.get("title"), Some(&string("Deploy: prod")));
+        assert_eq!(props.get("tags"), Some(&list(vec![string("ops"), string("prod")])));
+        assert_eq!(props.get("owners"), Some(&list(vec![string("ana"), yrs::Any::Number(7.0)])));
+        assert_eq!(props.get("script"), Some(&string("make\nmake install")));
+        assert_eq!(props.get("summary"), Some(&string("one two")));
+        assert_eq!(props.get("server"), Some(&string("  host: example.com\n  port: 22")));
+        assert_eq!(props.get("draft"), Some(&yrs::Any::Bool(false)));
+        assert_eq!(props.get("empty"), Some(&yrs::Any::Null));
+        assert_eq!(props.len(), 8);
+    }
+
+    #[test]
+    fn front_matter_makes_a_document_block() {
+        let text = "---\ntitle: Runbook\nid: stolen\nowner: ana\nwatch: src/**\n---\n# Steps\n- check\n";
+        let blocks = document_blocks(text, "runbook", false);
+        assert_eq!(outline(&blocks), "Runbook\n  # Steps\n    check\n");
+        assert_eq!(blocks[0].props.get("owner"), Some(&yrs::Any::String("ana".into())));
+        // Block fields and properties the app acts on can't be set from front matter
+        assert!(!blocks[0].props.contains_key("id") && !blocks[0].props.contains_key("watch"));
+        assert_ne!(blocks[0].id, "stolen");
+        assert_eq!(blocks[0].props.get("markdownId"), Some(&yrs::Any::String("stolen".into())));
+        assert_eq!(blocks[0].props.get("markdownWatch"), Some(&yrs::Any::String("src/**".into())));
+
+        // Without front matter there's no wrapper, unless asked for
+        assert_eq!(outline(&document_blocks("# Steps\n", "runbook", false)), "# Steps\n");
+        assert_eq!(outline(&document_blocks("# Steps\n", "runbook", true)), "runbook\n  # Steps\n");
+        // An unclosed `---` isn't front matter, just a rule
+        assert_eq!(outline(&document_blocks("---\ntitle: x\n", "n", false)), "title: x\n");
+    }
+
+    #[test]
+    fn imports_a_directory_of_markdown() {
+        let dir = std::env::temp_dir().join(format!("float-markdown-{}", uuid::Uuid::new_v4()));
+        for (path, text) in [
+            ("b.md", "# B\n"),
+            ("a.markdown", "---\ntitle: Alpha\n---\ntext\n"),
+            ("notes.txt", "not markdown\n"),
+            (".hidden.md", "# hidden\n"),
+            ("sub/c.MD", "- c\n"),
+            ("empty/readme.txt", "nothing\n"),
+        ] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        let blocks = directory_blocks(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(outline(&blocks), "Alpha\n  text\nb\n  # B\nsub\n  c\n    c\n");
+        assert!(directory_blocks(&dir).unwrap_err().contains("Failed to read"));
+    }
+
+    #[test]
+    fn importing_a_file_twice_gives_new_ids() {
+        use yrs::{Map, WriteTxn};
+        let doc = doc_with(&[("root", None, "Root")]);
+        let text = "---\ntitle: Notes\n---\n# One\n- two\n";
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        for _ in 0..2 {
+            let mut parsed = document_blocks(text, "notes", false);
+            crate::claim_ids(&blocks, &txn, &mut parsed);
+            crate::insert_tree(&blocks, &mut txn, &parsed, Some("root"), 0).unwrap();
+        }
+        // Root plus two copies of three blocks, each under its own parent
+        assert_eq!(blocks.len(&txn), 7);
+        let trees = read_trees(&blocks, &txn, None).unwrap();
+        assert_eq!(trees.len(), 2);
+        assert_ne!(trees[0].block.id(), trees[1].block.id());
+        assert_eq!(trees[0].children[0].block.parent_id(), Some(trees[0].block.id()));
+        assert_eq!(trees[1].children[0].block.parent_id(), Some(trees[1].block.id()));
+    }
+
+    #[test]
+    fn imports_read_markdown_without_structure() {
+        let blocks = document_blocks("Fish &amp; chips\nsee <https://example.com>\n", "Notes", false);
+        assert_eq!(outline(&blocks), "Fish & chips\nsee https://example.com\n");
+
+        let blocks = document_blocks("Intro\n\n    cargo build\n", "Notes", false);
+        assert_eq!(outline(&blocks), "Intro\n```\\ncargo build\\n``` (code)\n");
+    }
+
+    #[test]
+    fn plain_text_stays_one_block_per_line() {
+        let output = "Compiling app\n\n    warning: unused\n    --> src/main.rs\nFinished\n";
+        assert_eq!(
+            outline(&parse_markdown_tree(output, "b", "output")),
+            "Compiling app (output)\n    warning: unused (output)\n    --> src/main.rs (output)\nFinished (output)\n",
+        );
+    }
+
+    /// A workspace with run output, code, a quote and text that looks like markdown
+    fn exported_doc() -> yrs::Doc {
+        let doc = doc_with(&[
+            ("root", None, "Root"),
+            ("app", Some("root"), "cwd:: ~/code/app"),
+            ("test", Some("app"), "sh:: cargo test"),
+            ("out-1", Some("test"), "running 2 tests"),
+            ("out-2", Some("test"), "test result: ok"),
+            ("err", Some("test"), "warning: unused import"),
+            ("code", Some("app"), "```py\nprint(\"hi\")\n```"),
+            ("quote", Some("app"), "careful\nreally"),
+            ("notes", Some("app"), "notes"),
+            ("bullet", Some("notes"), "- not a bullet"),
+            ("number", Some("notes"), "1. not a number"),
+            ("done", Some("root"), "done"),
+        ]);
+        for (id, block_type) in [("out-1", "output"), ("out-2", "output"), ("err", "error"), ("code", "code"), ("quote", "quote")] {
+            set_field(&doc, id, "type", block_type);
+        }
+        doc
+    }
+
+    /// Export the whole workspace, then read it back in
+    fn round_trip(doc: &yrs::Doc, style: ExportStyle) -> (Vec<BlockTree>, String, Vec<ParsedBlock>) {
+        let txn = doc.transact();
+        let trees = read_trees(&txn.get_map("blocks").unwrap(), &txn, None).unwrap();
+        let options = ExportOptions { style, ..ExportOptions::default() };
+        let markdown = render_markdown(&trees, &options);
+        let parsed = parse_markdown_tree(&markdown, "b", "text");
+        (trees, markdown, parsed)
+    }
+
+    fn as_parsed(trees: &[BlockTree]) -> Vec<ParsedBlock> {
+        trees.iter().map(|tree| {
+            let block_type = tree.block.get_str("type").unwrap_or("text");
+            let mut block = ParsedBlock::new(tree.block.id().to_string(), tree.block.content().to_string(), block_type);
+            block.children = as_parsed(&tree.children);
+            block
+        }).collect()
+    }
+
+    #[test]
+    fn bullets_round_trip() {
+        let (trees, markdown, parsed) = round_trip(&exported_doc(), ExportStyle::Bullets);
+        assert!(markdown.starts_with("- cwd:: ~/code/app\n"), "{}", markdown);
+        assert_eq!(outline(&parsed), outline(&as_parsed(&trees)), "{}", markdown);
+        assert_eq!(parsed[0].children[1].props.get("language"), Some(&yrs::Any::String("py".into())));
+    }
+
+    #[test]
+    fn headings_round_trip() {
+        let (trees, markdown, mut parsed) = round_trip(&exported_doc(), ExportStyle::Headings);
+        assert!(markdown.starts_with("# cwd:: ~/code/app\n"), "{}", markdown);
+
+        // Headings keep their `#`s when read back
+        fn strip_headings(blocks: &mut [ParsedBlock]) {
+            for block in blocks {
+                block.content = block.content.trim_start_matches('#').trim_start().to_string();
+                strip_headings(&mut block.children);
+            }
+        }
+        strip_headings(&mut parsed);
+        assert_eq!(outline(&parsed), outline(&as_parsed(&trees)), "{}", markdown);
+    }
+
+    #[test]
+    fn multi_line_content_round_trips() {
+        let doc = doc_with(&[
+            ("root", None, "Root"),
+            ("py", Some("root"), "py:: def f():\n    return 1"),
+            ("sh", Some("root"), "sh:: echo one \\\n  two"),
+            ("empty-first", Some("root"), "sh::\nls"),
+            ("text", Some("root"), "lines\n    indented\n\t- tabbed\nends in \\\nlast"),
+        ]);
+        let (trees, markdown, parsed) = round_trip(&doc, ExportStyle::Bullets);
+        // The workspace's root block isn't exported, just its children
+        assert_eq!(markdown, "\
+- ```py::
+  def f():
+      return 1
+  ```
+- ```sh::
+  echo one \\
+    two
+  ```
+- ```sh::
+
+  ls
+  ```
+- lines\\
+  &#32;   indented\\
+  &#9;\\- tabbed\\
+  ends in \\\\\\
+  last
+");
+        assert_eq!(outline(&parsed), outline(&as_parsed(&trees)), "{}", markdown);
+
+        // Headings export them the same way
+        let doc = doc_with(&[("py", None, "py:: def f():\n    return 1"), ("after", None, "sh:: a\n b")]);
+        let (trees, markdown, parsed) = round_trip(&doc, ExportStyle::Headings);
+        assert_eq!(outline(&parsed), outline(&as_parsed(&trees)), "{}", markdown);
+    }
+
+    #[test]
+    fn only_prefix_fences_become_prefix_blocks() {
+        assert_eq!(prefix_from_fence("py::", "x\n"), Some("py:: x".to_string()));
+        assert_eq!(prefix_from_fence("py", "x\n"), None);
+        assert_eq!(prefix_from_fence("py:: extra", "x\n"), None);
+        assert_eq!(prefix_from_fence("a b::", "x\n"), None);
+        let output = parse_markdown_tree("# Out\n```sh::\nls\n```\n", "b", "output");
+        assert_eq!(outline(&output), "# Out (output)\n  ```sh::\\nls\\n``` (output)\n");
+    }
+
+    #[test]
+    fn quote_callouts_round_trip() {
+        let doc = doc_with(&[("note", None, "heads up"), ("child", Some("note"), "details")]);
+        set_field(&doc, "note", "type", "quote");
+        set_field(&doc, "note", "callout", "warning");
+
+        let (trees, markdown, parsed) = round_trip(&doc, ExportStyle::Bullets);
+        assert_eq!(markdown, "- > [!WARNING]\n  > heads up\n  - details\n");
+        assert_eq!(outline(&parsed), outline(&as_parsed(&trees)));
+        assert_eq!(parsed[0].props.get("callout"), Some(&yrs::Any::String("warning".into())));
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use tauri_plugin_notification::NotificationExt;
+use crate::settings::load_settings;
+use crate::AppState;
+
+// ═══════════════════════════════════════════════════════════════
+// NOTIFICATIONS
+// ═══════════════════════════════════════════════════════════════
+//
+// Every run is counted in a summary the UI can poll (`get_run_summary`) or
+// follow (`run-summary` events), so a badge can show what's going on in
+// the background. A run that took longer than the threshold also raises a
+// desktop notification with its exit status and duration:
+//
+//   { "notifications": { "enabled": true, "afterSeconds": 10 } }
+
+/// Event emitted whenever the summary changes
+const SUMMARY_EVENT: &str = "run-summary";
+
+/// Longest command shown in a notification
+const MAX_COMMAND_CHARS: usize = 80;
+
+/// When to raise a notification for a finished run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotifySettings {
+    pub enabled: bool,
+    /// Runs shorter than this finish silently
+    pub after_seconds: u64,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self { enabled: true, after_seconds: 10 }
+    }
+}
+
+/// Counts of runs since the app started (or the summary was last cleared)
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub running: usize,
+    /// Exited zero
+    pub completed: usize,
+    /// Exited non-zero, were killed, or couldn't start
+    pub failed: usize,
+    /// Held by the workspace policy until approved
+    pub pending: usize,
+}
+
+/// The live summary
+#[derive(Default)]
+pub struct Activity {
+    summary: Mutex<RunSummary>,
+}
+
+/// How a run ended, as far as notifications care
+pub enum Finish<'a> {
+    Exited { exit_code: i32, signal: Option<i32> },
+    Error(&'a str),
+}
+
+impl Finish<'_> {
+    fn succeeded(&self) -> bool {
+        matches!(self, Finish::Exited { exit_code: 0, signal: None })
+    }
+}
+
+/// "850ms", "42s", "3m 05s", "1h 02m"
+fn format_duration(ms: i64) -> String {
+    let seconds = ms / 1000;
+    match seconds {
+        0 => format!("{}ms", ms.max(0)),
+        1..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+fn shorten(command: &str) -> String {
+    let line = command.lines().next().unwrap_or_default().trim();
+    if line.chars().count() > MAX_COMMAND_CHARS || command.trim().lines().count() > 1 {
+        let cut: String = line.chars().take(MAX_COMMAND_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Title and body of the notification for a finished run
+fn describe(command: &str, finish: &Finish, duration_ms: i64) -> (String, String) {
+    let duration = format_duration(duration_ms);
+    let title = match finish {
+        Finish::Exited { exit_code: 0, signal: None } => format!("Finished in {}", duration),
+        Finish::Exited { signal: Some(signal), .. } => format!("Killed by signal {} after {}", signal, duration),
+        Finish::Exited { exit_code, .. } => format!("Failed with exit {} after {}", exit_code, duration),
+        Finish::Error(e) => format!("Failed after {}: {}", duration, e),
+    };
+    (title, shorten(command))
+}
+
+impl Activity {
+    fn update(&self, app: &tauri::AppHandle, state: &AppState, change: impl FnOnce(&mut RunSummary)) {
+        let Ok(mut summary) = self.summary.lock() else { return };
+        change(&mut summary);
+        summary.pending = state.policy.count();
+        if let Err(e) = app.emit(SUMMARY_EVENT, *summary) {
+            log::warn!("Failed to emit {}: {}", SUMMARY_EVENT, e);
+        }
+    }
+
+    /// Count a run as started
+    pub fn started(&self, app: &tauri::AppHandle, state: &AppState) {
+        self.update(app, state, |summary| summary.running += 1);
+    }
+
+    /// Count a run as done, and notify if it ran long enough to have been forgotten
+    pub fn finished(&self, app: &tauri::AppHandle, state: &AppState, command: &str, finish: Finish, duration_ms: i64) {
+        self.update(app, state, |summary| {
+            summary.running = summary.running.saturating_sub(1);
+            if finish.succeeded() {
+                summary.completed += 1;
+            } else {
+                summary.failed += 1;
+            }
+        });
+
+        let settings = match load_settings() {
+            Ok(settings) => settings.notifications,
+            Err(e) => {
+                log::warn!("Notifications: {}", e);
+                return;
+            }
+        };
+        if !settings.enabled || duration_ms < (settings.after_seconds as i64).saturating_mul(1000) {
+            return;
+        }
+
+        let (title, body) = describe(command, &finish, duration_ms);
+        if let Err(e) = app.notification().builder().title(title).body(body).show() {
+            log::warn!("Failed to show notification: {}", e);
+        }
+    }
+
+    /// Report a change in runs waiting for confirmation
+    pub fn pending_changed(&self, app: &tauri::AppHandle, state: &AppState) {
+        self.update(app, state, |_| {});
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Get the current run counts
+#[tauri::command]
+pub fn get_run_summary(state: tauri::State<'_, AppState>) -> Result<RunSummary, String> {
+    let mut summary = *state.activity.summary.lock().map_err(|e| e.to_string())?;
+    summary.pending = state.policy.count();
+    Ok(summary)
+}
+
+/// Reset the completed and failed counts (running and pending runs stay)
+#[tauri::command]
+pub fn clear_run_summary(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<RunSummary, String> {
+    state.activity.update(&app, &state, |summary| {
+        summary.completed = 0;
+        summary.failed = 0;
+    });
+    get_run_summary(state)
+}
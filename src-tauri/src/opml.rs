@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use chrono::Utc;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+use yrs::{ReadTxn, Transact};
+use crate::{import_blocks, imported_prop, read_trees, AppState, BlockTree, ParsedBlock, APP_PROPS, BLOCK_FIELDS};
+
+// ═══════════════════════════════════════════════════════════════
+// OPML
+// ═══════════════════════════════════════════════════════════════
+//
+// OPML 2.0 outlines map onto blocks one to one:
+//
+//   <outline text="Deploy" _note="Friday only" owner="ops">
+//     <outline text="sh:: make release" _type="sh"/>
+//   </outline>
+//
+// `text` is the content and `_note` the `note` property. Every other
+// attribute becomes a property of the same name - `true`/`false` and
+// numbers as such - except ones a block owns itself or the app acts on,
+// which keep their value under an `opml` prefix (an RSS outline's `type`
+// is `opmlType`, a `status` is `opmlStatus`).
+// Exports write the block's own type and collapsed state back as `_type`
+// and `_collapsed`, so a workspace comes back as it went out.
+
+/// Properties written as attributes of their own, rather than by name
+const NOTE_ATTR: &str = "_note";
+const TYPE_ATTR: &str = "_type";
+const COLLAPSED_ATTR: &str = "_collapsed";
+
+/// Fields left out of exported attributes - the outline's nesting already says them
+const TREE_FIELDS: &[&str] = &["id", "parentId", "childIds", "content", "createdAt", "updatedAt"];
+
+/// Outline attribute name for a property (None if it can't be one)
+fn attr_name(prop: &str) -> Option<String> {
+    let unprefixed = prop.strip_prefix("opml").and_then(|rest| {
+        let mut chars = rest.chars();
+        let first = chars.next()?.to_ascii_lowercase();
+        let name = format!("{}{}", first, chars.as_str());
+        (BLOCK_FIELDS.contains(&name.as_str()) || APP_PROPS.contains(&name.as_str())).then_some(name)
+    });
+    let name = unprefixed.unwrap_or_else(|| prop.to_string());
+
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c));
+    valid.then_some(name)
+}
+
+/// An attribute's value as a property: booleans, numbers and JSON lists stay typed
+pub fn attr_value(value: &str) -> yrs::Any {
+    match value {
+        "true" => return yrs::Any::Bool(true),
+        "false" => return yrs::Any::Bool(false),
+        _ => {}
+    }
+    if let Ok(n) = value.parse::<i64>() {
+        // Only if nothing is lost - "007" stays a string
+        if n.to_string() == value {
+            return yrs::Any::BigInt(n);
+        }
+    }
+    if value.starts_with('[') || value.starts_with('{') {
+        if let Ok(any) = yrs::Any::from_json(value) {
+            return any;
+        }
+    }
+    yrs::Any::String(value.into())
+}
+
+/// A property's value as an attribute (None for empty values)
+pub fn prop_value(value: &yrs::Any) -> Option<String> {
+    match value {
+        yrs::Any::Null | yrs::Any::Undefined => None,
+        yrs::Any::String(s) => Some(s.to_string()),
+        yrs::Any::Bool(b) => Some(b.to_string()),
+        yrs::Any::Number(n) => Some(n.to_string()),
+        yrs::Any::BigInt(n) => Some(n.to_string()),
+        other => {
+            let mut json = String::new();
+            other.to_json(&mut json);
+            Some(json)
+        }
+    }
+}
+
+/// Escape an attribute value, keeping line breaks and tabs through attribute normalization
+fn escape_attr(value: &str) -> String {
+    quick_xml::escape::escape(value)
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+        .replace('\t', "&#9;")
+}
+
+// ═══════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════
+
+/// A block from an `<outline>` element's attributes
+fn outline_block(element: &BytesStart, id: String) -> Result<ParsedBlock, String> {
+    let mut text = None;
+    let mut title = None;
+    let mut block = ParsedBlock::new(id, String::new(), "text");
+
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| format!("Invalid OPML attribute: {}", e))?;
+        let key = attr.key.as_ref().to_string();
+        let value = attr
+            .normalized_value(XmlVersion::Implicit1_0)
+            .map_err(|e| format!("Invalid OPML attribute `{}`: {}", key, e))?
+            .into_owned();
+
+        match key.as_str() {
+            "text" => text = Some(value),
+            NOTE_ATTR => {
+                block.props.insert("note".to_string(), yrs::Any::String(value.into()));
+            }
+            TYPE_ATTR if !value.is_empty() => block.block_type = value,
+            COLLAPSED_ATTR => {
+                block.props.insert("collapsed".to_string(), yrs::Any::Bool(value == "true"));
+            }
+            _ => {
+                if key == "title" {
+                    title = Some(value.clone());
+                }
+                block.props.insert(imported_prop("opml", &key), attr_value(&value));
+            }
+        }
+    }
+
+    // `text` is required, but some outliners only write `title`
+    block.content = text.or(title).unwrap_or_default();
+    Ok(block)
+}
+
+/// Parse an OPML document's outlines into blocks
+fn parse_opml(xml: &str) -> Result<Vec<ParsedBlock>, String> {
+    let base_id = uuid::Uuid::new_v4().to_string();
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut roots = vec![];
+    let mut open: Vec<ParsedBlock> = vec![];
+    let mut next = 0;
+    let mut is_opml = false;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid OPML at byte {}: {}", reader.error_position(), e))?;
+        match event {
+            Event::Start(element) | Event::Empty(element) if element.name().as_ref() == "opml" => is_opml = true,
+            Event::Start(element) if element.name().as_ref() == "outline" => {
+                open.push(outline_block(&element, format!("{}-{}", base_id, next))?);
+                next += 1;
+            }
+            Event::Empty(element) if element.name().as_ref() == "outline" => {
+                let block = outline_block(&element, format!("{}-{}", base_id, next))?;
+                next += 1;
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(block),
+                    None => roots.push(block),
+                }
+            }
+            Event::End(element) if element.name().as_ref() == "outline" => {
+                let Some(block) = open.pop() else { continue };
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(block),
+                    None => roots.push(block),
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !is_opml {
+        return Err("Not an OPML document (no <opml> element)".to_string());
+    }
+    Ok(roots)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════
+
+/// An `<outline>` element's attributes for a block
+fn outline_attrs(tree: &BlockTree) -> Vec<(String, String)> {
+    let block = &tree.block;
+    let mut attrs = vec![("text".to_string(), block.content().to_string())];
+
+    let mut props: BTreeMap<&str, &yrs::Any> = block.0.iter().map(|(key, value)| (key.as_str(), value)).collect();
+    if let Some(note) = props.remove("note").and_then(prop_value) {
+        attrs.push((NOTE_ATTR.to_string(), note));
+    }
+    if let Some(block_type) = block.get_str("type").filter(|t| *t != "text") {
+        attrs.push((TYPE_ATTR.to_string(), block_type.to_string()));
+    }
+    if block.get_bool("collapsed").unwrap_or(false) {
+        attrs.push((COLLAPSED_ATTR.to_string(), "true".to_string()));
+    }
+
+    for (key, value) in props {
+        if TREE_FIELDS.contains(&key) || key == "type" || key == "collapsed" {
+            continue;
+        }
+        if let (Some(name), Some(value)) = (attr_name(key), prop_value(value)) {
+            attrs.push((name, value));
+        }
+    }
+    attrs
+}
+
+fn write_outline(tree: &BlockTree, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let attrs: String = outline_attrs(tree)
+        .iter()
+        .map(|(name, value)| format!(" {}=\"{}\"", name, escape_attr(value)))
+        .collect();
+
+    if tree.children.is_empty() {
+        out.push_str(&format!("{}<outline{}/>\n", indent, attrs));
+        return;
+    }
+    out.push_str(&format!("{}<outline{}>\n", indent, attrs));
+    for child in &tree.children {
+        write_outline(child, depth + 1, out);
+    }
+    out.push_str(&format!("{}</outline>\n", indent));
+}
+
+/// Render blocks as an OPML 2.0 document
+fn render_opml(trees: &[BlockTree], title: &str) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n");
+    out.push_str(&format!("    <title>{}</title>\n", quick_xml::escape::escape(title)));
+    out.push_str(&format!("    <dateModified>{}</dateModified>\n", Utc::now().to_rfc2822()));
+    out.push_str("  </head>\n  <body>\n");
+    for tree in trees {
+        write_outline(tree, 2, &mut out);
+    }
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Import an OPML file (`path`) or document (`text`) under a block, or at the top level
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_opml(
+    state: tauri::State<'_, AppState>,
+    parent_id: Option<String>,
+    path: Option<String>,
+    text: Option<String>,
+) -> Result<String, String> {
+    let xml = match (path, text) {
+        (Some(path), None) => {
+            let path = PathBuf::from(path);
+            fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        }
+        (None, Some(text)) => text,
+        _ => return Err("Give either a path or text to import".to_string()),
+    };
+    let parsed = parse_opml(&xml)?;
+    import_blocks(&state, parsed, parent_id.as_deref())
+}
+
+/// Export a block's subtree, or the whole workspace, as OPML
+/// Writes it to `path` if given; returns the document either way
+#[tauri::command]
+pub fn export_opml(state: tauri::State<'_, AppState>, block_id: Option<String>, path: Option<String>) -> Result<String, String> {
+    let trees = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        read_trees(&blocks, &txn, block_id.as_deref())?
+    };
+
+    // A subtree is titled by its block, the workspace by its name
+    let title = match (&block_id, trees.first()) {
+        (Some(_), Some(tree)) => tree.block.content().lines().next().unwrap_or_default().to_string(),
+        _ => state.workspace_name.lock().map_err(|e| e.to_string())?.clone(),
+    };
+
+    let opml = render_opml(&trees, &title);
+    if let Some(path) = path {
+        fs::write(&path, &opml).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(opml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, WriteTxn};
+    use crate::insert_tree;
+
+    const OUTLINE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head><title>Ops</title></head>
+  <body>
+    <outline text="Deploy &amp; release" _note="Friday only&#10;after &quot;standup&quot;&#9;&lt;ok&gt;" owner="ops" type="rss">
+      <outline text="sh:: make release &lt;prod&gt;" _type="sh" priority="2"/>
+      <outline text="Checklist" _collapsed="true" paused="true">
+        <outline text="tag 'v1'" done="false" tags='["a","b"]'/>
+      </outline>
+    </outline>
+    <outline title="Only a title"/>
+  </body>
+</opml>
+"#;
+
+    /// Blocks with their IDs cleared, for comparing trees
+    fn without_ids(blocks: &[ParsedBlock]) -> Vec<ParsedBlock> {
+        blocks.iter().map(|block| ParsedBlock {
+            id: String::new(),
+            children: without_ids(&block.children),
+            ..block.clone()
+        }).collect()
+    }
+
+    /// Import parsed blocks into a fresh doc and export them again
+    fn export(parsed: &[ParsedBlock]) -> String {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        insert_tree(&blocks, &mut txn, parsed, None, 0).unwrap();
+        let trees = read_trees(&blocks, &txn, None).unwrap();
+        render_opml(&trees, "Ops")
+    }
+
+    #[test]
+    fn import_reads_attributes() {
+        let parsed = parse_opml(OUTLINE).unwrap();
+        let deploy = &parsed[0];
+        assert_eq!(deploy.content, "Deploy & release");
+        assert_eq!(deploy.props.get("note"), Some(&yrs::Any::String("Friday only\nafter \"standup\"\t<ok>".into())));
+        assert_eq!(deploy.props.get("opmlType"), Some(&yrs::Any::String("rss".into())));
+        assert_eq!(deploy.children[0].block_type, "sh");
+        assert_eq!(deploy.children[0].props.get("priority"), Some(&yrs::Any::BigInt(2)));
+        assert_eq!(deploy.children[1].props.get("collapsed"), Some(&yrs::Any::Bool(true)));
+        // Not a paused schedule here - just what the outline said
+        assert_eq!(deploy.children[1].props.get("opmlPaused"), Some(&yrs::Any::Bool(true)));
+        assert!(!deploy.children[1].props.contains_key("paused"));
+        assert_eq!(deploy.children[1].children[0].content, "tag 'v1'");
+        assert_eq!(parsed[1].content, "Only a title");
+    }
+
+    #[test]
+    fn import_export_import_round_trips() {
+        let parsed = parse_opml(OUTLINE).unwrap();
+        let opml = export(&parsed);
+        assert!(opml.contains(r#"text="Deploy &amp; release" _note="Friday only&#10;after &quot;standup&quot;&#9;&lt;ok&gt;""#), "{}", opml);
+        assert!(opml.contains(r#"text="sh:: make release &lt;prod&gt;" _type="sh""#), "{}", opml);
+
+        let reparsed = parse_opml(&opml).unwrap();
+        assert_eq!(format!("{:?}", without_ids(&reparsed)), format!("{:?}", without_ids(&parsed)));
+        // And it stays put on a second trip (past the head's timestamp)
+        let body = |opml: &str| opml.split_once("<body>").map(|(_, body)| body.to_string());
+        assert_eq!(body(&export(&reparsed)), body(&opml));
+    }
+}
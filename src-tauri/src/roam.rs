@@ -0,0 +1,241 @@
+use std::fs;
+use serde::Deserialize;
+use crate::doors::RESERVED_PREFIXES;
+use crate::{import_blocks, imported_prop, split_prefix, AppState, ParsedBlock, BLOCK_FIELDS};
+
+// ═══════════════════════════════════════════════════════════════
+// ROAM
+// ═══════════════════════════════════════════════════════════════
+//
+// Roam exports a graph as a JSON list of pages, each with nested
+// `children`. Pages become top-level blocks titled by their page title,
+// and every block keeps its `uid` as its ID, so `((uid))` references
+// still land:
+//
+//   { "title": "Deploy", "uid": "a1b2c3d4e", "children": [
+//     { "string": "Owner:: ops" },                   → owner: "ops" on "Deploy"
+//     { "string": "{{[[TODO]]}} ship", "uid": ... },  → "ship" (checked: false)
+//     { "string": "Plan", "heading": 2, "open": false } → "## Plan" (collapsed)
+//   ] }
+//
+// A childless `Key:: value` block is a Roam attribute, and becomes a
+// property of its parent - unless the key means something here
+// (`sh::`, `cwd::`, ...), then it stays a block. An attribute named like a
+// property the app acts on (`Status::`, `watch::`, ...) is kept under a
+// `roam` prefix instead, e.g. `roamStatus`.
+
+/// Roam's task markers, and whether each means done
+const MARKERS: &[(&str, bool)] = &[("{{[[TODO]]}} ", false), ("{{[[DONE]]}} ", true)];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RoamPage {
+    title: String,
+    uid: Option<String>,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+    create_time: Option<i64>,
+    edit_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RoamBlock {
+    #[serde(default)]
+    string: String,
+    uid: Option<String>,
+    #[serde(default)]
+    children: Vec<RoamBlock>,
+    heading: Option<usize>,
+    /// False when collapsed
+    open: Option<bool>,
+    create_time: Option<i64>,
+    edit_time: Option<i64>,
+}
+
+/// A Roam attribute's property name and value
+fn attribute(block: &RoamBlock) -> Option<(String, String)> {
+    if !block.children.is_empty() {
+        return None;
+    }
+    let (key, value) = split_prefix(&block.string)?;
+    if RESERVED_PREFIXES.contains(&key.as_str()) || BLOCK_FIELDS.contains(&key.as_str()) {
+        return None;
+    }
+    Some((key, value.to_string()))
+}
+
+/// Keep Roam's own timestamps
+fn set_times(block: &mut ParsedBlock, created: Option<i64>, edited: Option<i64>) {
+    if let Some(created) = created {
+        block.props.insert("createdAt".to_string(), yrs::Any::BigInt(created));
+    }
+    if let Some(edited) = edited.or(created) {
+        block.props.insert("updatedAt".to_string(), yrs::Any::BigInt(edited));
+    }
+}
+
+/// Children as blocks, with attribute blocks moved onto the parent as properties
+fn add_children(parent: &mut ParsedBlock, children: &[RoamBlock]) {
+    for child in children {
+        match attribute(child) {
+            Some((key, value)) => {
+                parent.props.insert(imported_prop("roam", &key), yrs::Any::String(value.into()));
+            }
+            None => parent.children.push(roam_block(child)),
+        }
+    }
+}
+
+fn roam_block(roam: &RoamBlock) -> ParsedBlock {
+    let mut content = roam.string.clone();
+    let mut checked = None;
+    for (marker, done) in MARKERS {
+        if let Some(rest) = content.strip_prefix(marker) {
+            content = rest.to_string();
+            checked = Some(*done);
+            break;
+        }
+    }
+    if let Some(level) = roam.heading.filter(|level| (1..=6).contains(level)) {
+        content = format!("{} {}", "#".repeat(level), content);
+    }
+
+    // Blocks without a uid get one when they're inserted
+    let mut block = ParsedBlock::new(roam.uid.clone().unwrap_or_default(), content, "text");
+    if let Some(checked) = checked {
+        block.props.insert("checked".to_string(), yrs::Any::Bool(checked));
+    }
+    if roam.open == Some(false) {
+        block.props.insert("collapsed".to_string(), yrs::Any::Bool(true));
+    }
+    set_times(&mut block, roam.create_time, roam.edit_time);
+    add_children(&mut block, &roam.children);
+    block
+}
+
+/// Parse a Roam JSON export into one block per page
+fn parse_roam(json: &str) -> Result<Vec<ParsedBlock>, String> {
+    let pages: Vec<RoamPage> = serde_json::from_str(json).map_err(|e| format!("Invalid Roam export: {}", e))?;
+    Ok(pages
+        .iter()
+        .map(|page| {
+            let mut block = ParsedBlock::new(page.uid.clone().unwrap_or_default(), page.title.clone(), "text");
+            set_times(&mut block, page.create_time, page.edit_time);
+            add_children(&mut block, &page.children);
+            block
+        })
+        .collect())
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Import a Roam JSON export (`path`, or its `text`) under a block or at the top level
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn import_roam(
+    state: tauri::State<'_, AppState>,
+    parent_id: Option<String>,
+    path: Option<String>,
+    text: Option<String>,
+) -> Result<String, String> {
+    let json = match (path, text) {
+        (Some(path), None) => fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?,
+        (None, Some(text)) => text,
+        _ => return Err("Give either a path or text to import".to_string()),
+    };
+    let pages = parse_roam(&json)?;
+    if pages.is_empty() {
+        return Err("No pages in the Roam export".to_string());
+    }
+    import_blocks(&state, pages, parent_id.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yrs::{ReadTxn, Transact};
+    use crate::testing::doc_with;
+
+    const EXPORT: &str = r#"[
+        { "title": "Deploy", "uid": "page-1", "create-time": 100, "edit-time": 200, "children": [
+            { "string": "Status:: blocked" },
+            { "string": "Owner:: ops" },
+            { "string": "sh:: make release", "uid": "uid-sh" },
+            { "string": "{{[[TODO]]}} ship", "uid": "uid-ship", "children": [
+                { "string": "see ((uid-plan))", "uid": "uid-see" }
+            ] },
+            { "string": "Plan", "uid": "uid-plan", "heading": 2, "open": false, "create-time": 300, "children": [
+                { "string": "{{[[DONE]]}} tag" }
+            ] },
+            { "string": "watch:: src/**", "children": [{ "string": "has children" }] },
+            { "string": "content:: x" }
+        ] },
+        { "title": "Empty" }
+    ]"#;
+
+    fn string(value: &str) -> yrs::Any {
+        yrs::Any::String(value.into())
+    }
+
+    #[test]
+    fn pages_and_blocks_keep_their_uids() {
+        let pages = parse_roam(EXPORT).unwrap();
+        let [deploy, empty] = &pages[..] else { panic!("{:?}", pages) };
+        assert_eq!((deploy.id.as_str(), deploy.content.as_str()), ("page-1", "Deploy"));
+        assert_eq!(deploy.props.get("createdAt"), Some(&yrs::Any::BigInt(100)));
+        assert_eq!(deploy.props.get("updatedAt"), Some(&yrs::Any::BigInt(200)));
+        assert_eq!((empty.id.as_str(), empty.children.len()), ("", 0));
+
+        let contents: Vec<&str> = deploy.children.iter().map(|block| block.content.as_str()).collect();
+        assert_eq!(contents, ["sh:: make release", "ship", "## Plan", "watch:: src/**", "content:: x"]);
+
+        let [_, ship, plan, ..] = &deploy.children[..] else { panic!() };
+        assert_eq!(ship.id, "uid-ship");
+        assert_eq!(ship.props.get("checked"), Some(&yrs::Any::Bool(false)));
+        assert_eq!((ship.children[0].id.as_str(), ship.children[0].content.as_str()), ("uid-see", "see ((uid-plan))"));
+
+        assert_eq!(plan.props.get("collapsed"), Some(&yrs::Any::Bool(true)));
+        // Without an edit time, created is also when it was last edited
+        assert_eq!(plan.props.get("updatedAt"), Some(&yrs::Any::BigInt(300)));
+        assert_eq!(plan.children[0].content, "tag");
+        assert_eq!(plan.children[0].props.get("checked"), Some(&yrs::Any::Bool(true)));
+        // No uid: one is given when it's inserted
+        assert_eq!(plan.children[0].id, "");
+    }
+
+    #[test]
+    fn attributes_fold_into_their_parent() {
+        let pages = parse_roam(EXPORT).unwrap();
+        let deploy = &pages[0];
+        assert_eq!(deploy.props.get("owner"), Some(&string("ops")));
+        // Named like a property the app acts on: kept, but not as that property
+        assert_eq!(deploy.props.get("roamStatus"), Some(&string("blocked")));
+        assert!(!deploy.props.contains_key("status"));
+        // A block with children, a prefix of the app's own or a block field isn't an attribute
+        assert!(!deploy.props.contains_key("watch") && !deploy.props.contains_key("roamWatch"));
+        assert!(!deploy.props.contains_key("sh") && !deploy.props.contains_key("content"));
+    }
+
+    #[test]
+    fn references_follow_a_reimported_block() {
+        let doc = doc_with(&[("uid-plan", None, "already here")]);
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").unwrap();
+        let mut pages = parse_roam(EXPORT).unwrap();
+        crate::claim_ids(&blocks, &txn, &mut pages);
+
+        let plan = &pages[0].children[2];
+        assert_ne!(plan.id, "uid-plan");
+        assert_eq!(pages[0].children[1].children[0].content, format!("see (({}))", plan.id));
+        assert_eq!(pages[0].id, "page-1");
+    }
+
+    #[test]
+    fn rejects_what_isnt_an_export() {
+        assert!(parse_roam("{}").unwrap_err().starts_with("Invalid Roam export"));
+        assert!(parse_roam(r#"[{ "uid": "no-title" }]"#).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::ansi::AnsiMode;
+use crate::structured::split_format;
+use crate::{get_data_dir, run_process, split_prefix, RunOutput};
+
+// ═══════════════════════════════════════════════════════════════
+// RUNNER TABLE
+// ═══════════════════════════════════════════════════════════════
+//
+// Interpreter-backed blocks. A runner is selected either by prefix
+//
+//   py:: print("hello")
+//
+// or by the language tag of a fenced code block:
+//
+//   ```python
+//   print("hello")
+//   ```
+//
+// The code is written to a temp file and the file path is appended to the
+// runner's command. Defaults can be overridden or extended per runner name
+// in ~/.float-liner/runners.json. The `shell` entry also carries the output
+// options for sh::, term:: and door blocks, e.g. `"ansi": "marks"`.
+
+/// An interpreter that runs code written in a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Runner {
+    /// Block prefixes that select this runner, e.g. `py` for `py:: print(1)`
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Fenced code block languages that select this runner
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// Program and leading arguments - the code file path is appended
+    pub command: Vec<String>,
+    /// Extension for the temp file (some interpreters care)
+    #[serde(default)]
+    pub extension: String,
+    /// Strip escape sequences from output, or keep colors as block marks
+    #[serde(default)]
+    pub ansi: AnsiMode,
+}
+
+/// Runners by name
+pub type RunnerTable = BTreeMap<String, Runner>;
+
+fn runner(prefixes: &[&str], languages: &[&str], command: &[&str], extension: &str) -> Runner {
+    Runner {
+        prefixes: prefixes.iter().map(|s| s.to_string()).collect(),
+        languages: languages.iter().map(|s| s.to_string()).collect(),
+        command: command.iter().map(|s| s.to_string()).collect(),
+        extension: extension.to_string(),
+        ansi: AnsiMode::default(),
+    }
+}
+
+/// Built-in runners (sh:: itself stays on the `sh -c` path)
+fn default_runners() -> RunnerTable {
+    BTreeMap::from([
+        ("python".to_string(), runner(&["py", "python"], &["python", "py", "python3"], &["python3", "-u"], "py")),
+        ("node".to_string(), runner(&["node", "js"], &["javascript", "js", "node"], &["node"], "js")),
+        ("ruby".to_string(), runner(&["ruby", "rb"], &["ruby", "rb"], &["ruby"], "rb")),
+        ("shell".to_string(), runner(&[], &["sh", "bash", "shell"], &["sh"], "sh")),
+    ])
+}
+
+/// Get the path to the runner config file
+fn get_runners_path() -> PathBuf {
+    get_data_dir().join("runners.json")
+}
+
+/// Load the runner table: defaults, overridden by runners.json entries of the same name
+pub fn load_runners() -> Result<RunnerTable, String> {
+    let mut table = default_runners();
+
+    let path = get_runners_path();
+    if path.exists() {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let overrides: RunnerTable = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid runner config {:?}: {}", path, e))?;
+        table.extend(overrides);
+    }
+
+    for (name, runner) in &table {
+        if runner.command.is_empty() {
+            return Err(format!("Runner '{}' has an empty command", name));
+        }
+    }
+
+    Ok(table)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// CODE EXTRACTION
+// ═══════════════════════════════════════════════════════════════
+
+/// Code pulled out of a block, ready to run
+#[derive(Debug, Clone)]
+pub struct CodeRun {
+    /// Runner name, empty for shebang code
+    pub runner: String,
+    /// Program and arguments; empty means execute the file itself (shebang)
+    pub command: Vec<String>,
+    pub extension: String,
+    pub code: String,
+}
+
+impl CodeRun {
+    fn with_runner(name: &str, runner: &Runner, code: String) -> Self {
+        Self {
+            runner: name.to_string(),
+            command: runner.command.clone(),
+            extension: runner.extension.clone(),
+            code,
+        }
+    }
+}
+
+/// Split a fenced code block into its language tag and body
+/// Returns None if the content isn't a fence
+fn split_fence(content: &str) -> Option<(String, String)> {
+    let trimmed = content.trim();
+    // A fence is three or more backticks, closed by the same number
+    let ticks = trimmed.len() - trimmed.trim_start_matches('`').len();
+    if ticks < 3 {
+        return None;
+    }
+    let (fence, rest) = trimmed.split_at(ticks);
+    let (info, body) = rest.split_once('\n').unwrap_or((rest, ""));
+    let language = info.split_whitespace().next().unwrap_or("").to_lowercase();
+    let body = body.trim_end().strip_suffix(fence).unwrap_or(body);
+    Some((language, body.trim_end().to_string()))
+}
+
+/// Work out which runner (if any) executes a block's content
+pub fn resolve_code(table: &RunnerTable, content: &str) -> Option<CodeRun> {
+    if let Some((language, body)) = split_fence(content) {
+        if body.trim().is_empty() {
+            return None;
+        }
+        if let Some((name, runner)) = table.iter().find(|(_, r)| r.languages.contains(&language)) {
+            return Some(CodeRun::with_runner(name, runner, body));
+        }
+        // No runner for the tag - a shebang line still says how to run it
+        if body.starts_with("#!") {
+            return Some(CodeRun { runner: String::new(), command: vec![], extension: String::new(), code: body });
+        }
+        return None;
+    }
+
+    // A format word (`py::json ...`) is about the output, not the code
+    let (_, content) = split_format(content);
+    let (prefix, _) = split_prefix(&content)?;
+    let (name, runner) = table.iter().find(|(_, r)| r.prefixes.contains(&prefix))?;
+
+    // Keep everything after the prefix, including newlines and indentation of later lines
+    let (_, rest) = content.trim_start().split_once("::")?;
+    let code = rest.trim_start_matches([' ', '\t']).trim_start_matches('\n').trim_end();
+    if code.is_empty() {
+        return None;
+    }
+    Some(CodeRun::with_runner(name, runner, code.to_string()))
+}
+
+/// Write the code to a temp file, run it, and clean up
+pub async fn run_code(run: &CodeRun, stdin_text: Option<String>, cwd: Option<PathBuf>) -> Result<RunOutput, String> {
+    let mut file_name = format!("float-liner-{}", uuid::Uuid::new_v4());
+    if !run.extension.is_empty() {
+        file_name.push('.');
+        file_name.push_str(&run.extension);
+    }
+    let path = std::env::temp_dir().join(file_name);
+
+    let mut code = run.code.clone();
+    code.push('\n');
+    fs::write(&path, code).map_err(|e| format!("Failed to write code file: {}", e))?;
+
+    let mut command = match run.command.split_first() {
+        Some((program, args)) => {
+            let mut command = Command::new(program);
+            command.args(args).arg(&path);
+            command
+        }
+        None => {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o700))
+                    .map_err(|e| format!("Failed to make code file executable: {}", e))?;
+            }
+            Command::new(&path)
+        }
+    };
+
+    if let Some(dir) = cwd {
+        command.current_dir(dir);
+    }
+
+    let result = run_process(command, stdin_text).await;
+    fs::remove_file(&path).ok();
+    result
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Get the runner table (defaults merged with runners.json)
+#[tauri::command]
+pub fn list_runners() -> Result<RunnerTable, String> {
+    load_runners()
+}
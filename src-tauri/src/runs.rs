@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::{append_jsonl, get_workspace_dir, read_jsonl, AppState};
+
+// ═══════════════════════════════════════════════════════════════
+// RUN HISTORY
+// ═══════════════════════════════════════════════════════════════
+//
+// Each run is recorded on its block (`runCount`, `lastRun`) and appended to
+// ~/.float-liner/<workspace>/runs.jsonl, so the doc only carries the latest
+// run while the full history stays available.
+
+/// What happened when a block ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub block_id: String,
+    /// 1 for the block's first run, counting up
+    pub run: i64,
+    /// Runner name (`shell` for sh::, term:: and doors)
+    pub runner: String,
+    /// The command as run, after door expansion (the code for runner blocks)
+    pub command: String,
+    pub cwd: Option<String>,
+    /// Session the run went to, if any
+    pub session: Option<String>,
+    /// Unix millis
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub duration_ms: i64,
+    pub exit_code: i32,
+    /// Signal that killed the process (unix)
+    pub signal: Option<i32>,
+    pub stdout_bytes: usize,
+    pub stderr_bytes: usize,
+}
+
+impl RunRecord {
+    /// The record as a block field value
+    pub fn to_any(&self) -> Result<yrs::Any, String> {
+        yrs::encoding::serde::to_any(self).map_err(|e| e.to_string())
+    }
+}
+
+/// Get the path to a workspace's run history
+fn get_history_path(workspace: &str) -> PathBuf {
+    get_workspace_dir(workspace).join("runs.jsonl")
+}
+
+/// Append a run to the workspace's history
+pub fn append_history(workspace: &str, record: &RunRecord) -> Result<(), String> {
+    append_jsonl(&get_history_path(workspace), record)
+}
+
+/// Read a workspace's run history, oldest first, optionally for one block
+pub fn read_history(workspace: &str, block_id: Option<&str>) -> Result<Vec<RunRecord>, String> {
+    let mut records: Vec<RunRecord> = read_jsonl(&get_history_path(workspace))?;
+    if let Some(id) = block_id {
+        records.retain(|record| record.block_id == id);
+    }
+    Ok(records)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Get every recorded run of a block in the current workspace, oldest first
+#[tauri::command]
+pub fn get_run_history(state: tauri::State<'_, AppState>, block_id: String) -> Result<Vec<RunRecord>, String> {
+    let workspace = state.workspace_name.lock().map_err(|e| e.to_string())?.clone();
+    read_history(&workspace, Some(&block_id))
+}
@@ -0,0 +1,361 @@
+#[cfg(target_os = "linux")]
+use std::ffi::CStr;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use crate::{ancestors, split_prefix, BlockData};
+
+// ═══════════════════════════════════════════════════════════════
+// SANDBOX
+// ═══════════════════════════════════════════════════════════════
+//
+// Shell commands can run restricted, set for a whole workspace in its
+// policy.json (`"sandbox": { ... }`) or for a subtree by a `sandbox::`
+// ancestor:
+//
+//   sandbox:: fs=readonly net=off cpu=30s mem=512M procs=64
+//   └── sh:: make test
+//
+// - fs=readonly  every mount is read-only
+// - fs=tmp       read-only too, but the command starts in a fresh, throwaway /tmp
+// - fs=full      the filesystem is left alone
+// - net=off      a new network namespace with only a (down) loopback
+// - cpu / mem / procs set RLIMIT_CPU, RLIMIT_AS and RLIMIT_NPROC
+//
+// procs is not a count of the command's own processes. The kernel checks
+// RLIMIT_NPROC against every process of the user, system-wide (the user
+// namespace maps the user to itself), so it has to sit above what the
+// desktop session already runs: with 300 processes open, procs=64 makes
+// every fork in the command fail. Use it as a fork-bomb ceiling (e.g. the
+// current count plus a few hundred), not as a per-command quota.
+//
+// A bare `sandbox::` is fs=readonly net=off. When several apply (workspace,
+// nested ancestors) the strictest value of each setting wins, so a block
+// can't loosen the sandbox it sits in. Namespaces need Linux with
+// unprivileged user namespaces enabled.
+
+/// What a sandboxed command can see of the filesystem, least restricted first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsMode {
+    Full,
+    /// Read-only, plus a private empty /tmp the command starts in
+    Tmp,
+    #[serde(rename = "readonly")]
+    ReadOnly,
+}
+
+/// Restrictions for a sandboxed command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SandboxConfig {
+    pub filesystem: FsMode,
+    pub network: bool,
+    pub cpu_seconds: Option<u64>,
+    /// Address space limit in MiB
+    pub memory_mb: Option<u64>,
+    /// Processes the command's user may have in total - the user's other
+    /// processes (the whole desktop session) count against it too
+    pub max_processes: Option<u64>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            filesystem: FsMode::ReadOnly,
+            network: false,
+            cpu_seconds: None,
+            memory_mb: None,
+            max_processes: None,
+        }
+    }
+}
+
+fn stricter_limit(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl SandboxConfig {
+    /// Combine two sandboxes, keeping the stricter value of each setting
+    pub fn merge(self, other: SandboxConfig) -> SandboxConfig {
+        SandboxConfig {
+            filesystem: self.filesystem.max(other.filesystem),
+            network: self.network && other.network,
+            cpu_seconds: stricter_limit(self.cpu_seconds, other.cpu_seconds),
+            memory_mb: stricter_limit(self.memory_mb, other.memory_mb),
+            max_processes: stricter_limit(self.max_processes, other.max_processes),
+        }
+    }
+
+    /// Parse a `sandbox::` value, e.g. "fs=tmp net=on cpu=2m mem=1G procs=32"
+    pub fn parse(value: &str) -> Result<SandboxConfig, String> {
+        let mut config = SandboxConfig::default();
+
+        for setting in value.split_whitespace() {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("sandbox:: settings look like key=value, got `{}`", setting))?;
+            let value = value.to_lowercase();
+
+            match key.to_lowercase().as_str() {
+                "fs" | "filesystem" => {
+                    config.filesystem = match value.as_str() {
+                        "full" | "rw" => FsMode::Full,
+                        "tmp" | "temp" => FsMode::Tmp,
+                        "readonly" | "ro" => FsMode::ReadOnly,
+                        _ => return Err(format!("Unknown sandbox fs `{}` (full, tmp or readonly)", value)),
+                    }
+                }
+                "net" | "network" => {
+                    config.network = match value.as_str() {
+                        "on" | "true" | "yes" => true,
+                        "off" | "false" | "no" => false,
+                        _ => return Err(format!("Unknown sandbox net `{}` (on or off)", value)),
+                    }
+                }
+                "cpu" => config.cpu_seconds = Some(parse_seconds(&value)?),
+                "mem" | "memory" => config.memory_mb = Some(parse_megabytes(&value)?),
+                "procs" | "processes" => {
+                    config.max_processes = Some(value.parse().map_err(|_| format!("Invalid sandbox procs `{}`", value))?)
+                }
+                other => return Err(format!("Unknown sandbox setting `{}`", other)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// "90", "90s", "2m", "1h" → seconds
+fn parse_seconds(value: &str) -> Result<u64, String> {
+    let (number, unit) = split_unit(value);
+    let multiplier = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        _ => return Err(format!("Invalid sandbox cpu `{}` (e.g. 30s, 2m)", value)),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid sandbox cpu `{}`", value))
+}
+
+/// "512", "512m", "2g", "65536k" → MiB (rounded up)
+fn parse_megabytes(value: &str) -> Result<u64, String> {
+    let (number, unit) = split_unit(value);
+    let number: u64 = number.parse().map_err(|_| format!("Invalid sandbox mem `{}`", value))?;
+    match unit.trim_end_matches('b') {
+        "k" => Ok(number.div_ceil(1024)),
+        "" | "m" => Ok(number),
+        "g" => number.checked_mul(1024).ok_or_else(|| format!("Invalid sandbox mem `{}`", value)),
+        _ => Err(format!("Invalid sandbox mem `{}` (e.g. 512M, 2G)", value)),
+    }
+}
+
+fn split_unit(value: &str) -> (&str, &str) {
+    let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    value.split_at(end)
+}
+
+fn sandbox_value(block: &BlockData) -> Option<String> {
+    match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == "sandbox" => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// The sandbox a block runs in: the workspace's merged with every `sandbox::` ancestor
+pub fn find_sandbox<T: yrs::ReadTxn>(
+    blocks: &yrs::MapRef,
+    txn: &T,
+    block_id: &str,
+    workspace: Option<SandboxConfig>,
+) -> Result<Option<SandboxConfig>, String> {
+    let mut sandbox = workspace;
+    for value in ancestors(blocks, txn, block_id).iter().filter_map(sandbox_value) {
+        let config = SandboxConfig::parse(&value)?;
+        sandbox = Some(match sandbox {
+            Some(outer) => outer.merge(config),
+            None => config,
+        });
+    }
+    Ok(sandbox)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// LINUX
+// ═══════════════════════════════════════════════════════════════
+
+/// `struct mount_attr` for mount_setattr(2)
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+#[cfg(target_os = "linux")]
+const MOUNT_ATTR_RDONLY: u64 = 0x1;
+
+/// Write a whole buffer to a file with raw syscalls (safe between fork and exec)
+#[cfg(target_os = "linux")]
+fn write_proc_file(path: &CStr, contents: &[u8]) -> std::io::Result<()> {
+    // SAFETY: contents outlive the calls
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+        let result = if written == contents.len() as isize { Ok(()) } else { Err(std::io::Error::last_os_error()) };
+        libc::close(fd);
+        result
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check(rc: libc::c_int) -> std::io::Result<()> {
+    if rc == -1 { Err(std::io::Error::last_os_error()) } else { Ok(()) }
+}
+
+/// Set up the sandbox in the child, between fork and exec
+#[cfg(target_os = "linux")]
+fn enter(config: &SandboxConfig, uid_map: &[u8], gid_map: &[u8]) -> std::io::Result<()> {
+    let limits = [
+        (libc::RLIMIT_CPU, config.cpu_seconds),
+        (libc::RLIMIT_AS, config.memory_mb.map(|mb| mb.saturating_mul(1024 * 1024))),
+        (libc::RLIMIT_NPROC, config.max_processes),
+    ];
+
+    // SAFETY (whole function): only async-signal-safe syscalls on data prepared before the fork
+    unsafe {
+        for (resource, limit) in limits {
+            let Some(limit) = limit else { continue };
+            let rlimit = libc::rlimit { rlim_cur: limit as libc::rlim_t, rlim_max: limit as libc::rlim_t };
+            check(libc::setrlimit(resource, &rlimit))?;
+        }
+
+        if config.filesystem == FsMode::Full && config.network {
+            return Ok(());
+        }
+
+        // A user namespace lets an unprivileged process own the mount and network namespaces
+        let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS;
+        if !config.network {
+            flags |= libc::CLONE_NEWNET;
+        }
+        check(libc::unshare(flags))?;
+        write_proc_file(c"/proc/self/setgroups", b"deny")?;
+        write_proc_file(c"/proc/self/uid_map", uid_map)?;
+        write_proc_file(c"/proc/self/gid_map", gid_map)?;
+
+        if config.filesystem == FsMode::Full {
+            return Ok(());
+        }
+
+        let root = c"/".as_ptr();
+        check(libc::mount(std::ptr::null(), root, std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null()))?;
+
+        let attr = MountAttr { attr_set: MOUNT_ATTR_RDONLY, attr_clr: 0, propagation: 0, userns_fd: 0 };
+        let rc = libc::syscall(
+            libc::SYS_mount_setattr,
+            libc::AT_FDCWD,
+            root,
+            libc::AT_RECURSIVE as libc::c_uint,
+            &attr as *const MountAttr,
+            std::mem::size_of::<MountAttr>(),
+        );
+        check(rc as libc::c_int)?;
+
+        if config.filesystem == FsMode::Tmp {
+            let tmp = c"/tmp".as_ptr();
+            check(libc::mount(
+                c"tmpfs".as_ptr(),
+                tmp,
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                c"mode=1777".as_ptr().cast(),
+            ))?;
+            check(libc::chdir(tmp))?;
+        }
+    }
+    Ok(())
+}
+
+/// Make a command run inside the sandbox
+#[cfg(target_os = "linux")]
+pub fn apply(command: &mut Command, config: &SandboxConfig) -> Result<(), String> {
+    // Everything the child needs is built here - it mustn't allocate after the fork
+    // SAFETY: getuid/getgid can't fail
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let uid_map = format!("{} {} 1", uid, uid).into_bytes();
+    let gid_map = format!("{} {} 1", gid, gid).into_bytes();
+
+    if config.filesystem == FsMode::Tmp {
+        command.current_dir("/tmp");
+    }
+
+    let config = config.clone();
+    // SAFETY: `enter` only makes async-signal-safe syscalls
+    unsafe {
+        command.pre_exec(move || enter(&config, &uid_map, &gid_map));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_command: &mut Command, _config: &SandboxConfig) -> Result<(), String> {
+    Err("sandbox:: needs Linux namespaces - refusing to run the command unsandboxed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(filesystem: FsMode, network: bool, cpu: Option<u64>, mem: Option<u64>, procs: Option<u64>) -> SandboxConfig {
+        SandboxConfig { filesystem, network, cpu_seconds: cpu, memory_mb: mem, max_processes: procs }
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert_eq!(SandboxConfig::parse("").unwrap(), SandboxConfig::default());
+        assert_eq!(
+            SandboxConfig::parse("fs=tmp net=on cpu=2m mem=1G procs=32").unwrap(),
+            config(FsMode::Tmp, true, Some(120), Some(1024), Some(32)),
+        );
+        assert_eq!(
+            SandboxConfig::parse("FS=RW network=yes cpu=90 memory=65537k").unwrap(),
+            config(FsMode::Full, true, Some(90), Some(65), None),
+        );
+        assert_eq!(SandboxConfig::parse("cpu=1h mem=512mb").unwrap(), config(FsMode::ReadOnly, false, Some(3600), Some(512), None));
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        for value in ["fs", "fs=rw2", "net=maybe", "cpu=5d", "mem=2t", "procs=many", "color=on", "cpu=-1"] {
+            assert!(SandboxConfig::parse(value).is_err(), "{}", value);
+        }
+        // Too big to count in seconds or MiB
+        assert!(SandboxConfig::parse(&format!("cpu={}h", u64::MAX / 2)).is_err());
+        assert!(SandboxConfig::parse(&format!("mem={}g", u64::MAX / 2)).is_err());
+    }
+
+    #[test]
+    fn strictest_wins() {
+        let workspace = config(FsMode::Tmp, true, Some(60), None, Some(500));
+        let block = config(FsMode::Full, false, Some(300), Some(256), None);
+        let merged = config(FsMode::Tmp, false, Some(60), Some(256), Some(500));
+        assert_eq!(workspace.clone().merge(block.clone()), merged);
+        assert_eq!(block.merge(workspace), merged);
+        assert_eq!(
+            config(FsMode::ReadOnly, false, None, None, None).merge(config(FsMode::Tmp, true, None, None, None)).filesystem,
+            FsMode::ReadOnly,
+        );
+    }
+}
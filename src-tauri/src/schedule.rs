@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone, Timelike};
+use serde::Serialize;
+use tauri::Manager;
+use yrs::{ReadTxn, Transact};
+use crate::index::BlockIndex;
+use crate::jobs::{start_queue, QueueOptions};
+use crate::output::OUTPUT_TYPES;
+use crate::{read_block, split_prefix, update_block, AppState, BlockData, Placement};
+
+// ═══════════════════════════════════════════════════════════════
+// SCHEDULES
+// ═══════════════════════════════════════════════════════════════
+//
+// A schedule block re-runs the executable blocks beneath it while the app
+// is open:
+//
+//   every:: 15m                 every 15 minutes (s, m, h, d; `1h30m` works; at most 366d)
+//   cron:: 0 9 * * 1-5          09:00 on weekdays (minute hour day month weekday, local time)
+//   every:: 1h replace          keep only the latest run's output
+//
+// Each run's output goes under a timestamped `run` block; with `replace`
+// the previous runs' output is dropped first. Runs go through the job queue
+// (and the workspace policy), one block at a time. A schedule whose last
+// run is still going skips its turn, and `paused` on the block holds it.
+
+/// How often the scheduler looks for due schedules
+const TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// A parsed cron expression, one bit per allowed value
+#[derive(Debug, Clone, PartialEq)]
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and weekday both given: either may match (as in cron)
+    either_day: bool,
+}
+
+/// Parse one cron field: `*`, `5`, `1-5`, `*/15`, `10-40/10`, or a comma list of those
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("Invalid cron step in `{}`", part))?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (parse_cron_value(a)?, parse_cron_value(b)?),
+                // `5/10` means from 5 to the end in steps of 10
+                None if part.contains('/') => (parse_cron_value(range)?, max),
+                None => {
+                    let value = parse_cron_value(range)?;
+                    (value, value)
+                }
+            },
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(format!("Cron field `{}` is out of range ({}-{})", part, min, max));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_cron_value(value: &str) -> Result<u32, String> {
+    value.parse().map_err(|_| format!("Invalid cron value `{}`", value))
+}
+
+impl Cron {
+    fn parse(fields: &[&str]) -> Result<Cron, String> {
+        let [minute, hour, day, month, weekday] = fields else {
+            return Err("cron:: needs 5 fields: minute hour day month weekday".to_string());
+        };
+        let mut weekdays = parse_cron_field(weekday, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Cron {
+            minutes: parse_cron_field(minute, 0, 59)?,
+            hours: parse_cron_field(hour, 0, 23)?,
+            days: parse_cron_field(day, 1, 31)?,
+            months: parse_cron_field(month, 1, 12)?,
+            weekdays,
+            either_day: *day != "*" && *weekday != "*",
+        })
+    }
+
+    fn matches_day(&self, time: &NaiveDateTime) -> bool {
+        let day = self.days & (1 << time.day()) != 0;
+        let weekday = self.weekdays & (1 << time.weekday().num_days_from_sunday()) != 0;
+        if self.either_day { day || weekday } else { day && weekday }
+    }
+
+    /// The first matching minute after `after`
+    fn next_after<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut time = start;
+
+        // Skips whole months, days and hours, so even `0 0 29 2 *` is found quickly
+        while time < start + Duration::days(366 * 8) {
+            if self.months & (1 << time.month()) == 0 {
+                let (year, month) = if time.month() == 12 { (time.year() + 1, 1) } else { (time.year(), time.month() + 1) };
+                time = chrono::NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.matches_day(&time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if self.hours & (1 << time.hour()) == 0 {
+                time = time.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << time.minute()) == 0 {
+                time += Duration::minutes(1);
+            } else {
+                // A time skipped by a DST change doesn't exist - try the next match
+                match after.timezone().from_local_datetime(&time).earliest() {
+                    Some(local) if local > after => return Some(local),
+                    _ => time += Duration::minutes(1),
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Longest `every::` interval - anything longer is most likely a typo
+const MAX_INTERVAL_DAYS: i64 = 366;
+
+/// Parse an interval like "15m", "1h30m" or "90s"
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid interval `{}` (e.g. 30s, 15m, 1h30m, 1d)", value);
+    let mut total = Duration::zero();
+    let mut number = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| invalid())?;
+        let part = match c {
+            's' => Duration::try_seconds(n),
+            'm' => Duration::try_minutes(n),
+            'h' => Duration::try_hours(n),
+            'd' => Duration::try_days(n),
+            _ => return Err(invalid()),
+        };
+        total = part.and_then(|part| total.checked_add(&part)).ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || total <= Duration::zero() {
+        return Err(invalid());
+    }
+    if total > Duration::days(MAX_INTERVAL_DAYS) {
+        return Err(format!("Interval `{}` is longer than {} days", value, MAX_INTERVAL_DAYS));
+    }
+    Ok(total)
+}
+
+/// When a schedule fires
+#[derive(Debug, Clone, PartialEq)]
+enum Timing {
+    Every(Duration),
+    Cron(Cron),
+}
+
+/// A parsed `every::` / `cron::` block
+#[derive(Debug, Clone, PartialEq)]
+struct Schedule {
+    timing: Timing,
+    /// Keep only the latest run's output
+    replace: bool,
+}
+
+impl Schedule {
+    fn parse(kind: &str, value: &str) -> Result<Schedule, String> {
+        let mut words: Vec<&str> = value.split_whitespace().collect();
+        let replace = match words.last() {
+            Some(&"replace") => true,
+            Some(&"append") => false,
+            _ => false,
+        };
+        if matches!(words.last(), Some(&"replace" | &"append")) {
+            words.pop();
+        }
+
+        let timing = match kind {
+            "every" => match words.as_slice() {
+                [interval] => Timing::Every(parse_interval(interval)?),
+                _ => return Err("every:: needs one interval, e.g. `every:: 15m`".to_string()),
+            },
+            _ => Timing::Cron(Cron::parse(&words)?),
+        };
+        Ok(Schedule { timing, replace })
+    }
+
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        match &self.timing {
+            Timing::Every(interval) => after.checked_add_signed(*interval),
+            Timing::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+/// The scheduler's view of one schedule block
+struct Entry {
+    /// Block content the entry was built from - an edit starts it over
+    content: String,
+    schedule: Result<Schedule, String>,
+    next_at: Option<DateTime<Local>>,
+    last_at: Option<DateTime<Local>>,
+    /// Queue of the latest run
+    queue_id: Option<String>,
+}
+
+impl Entry {
+    fn new(content: &str, schedule: Result<Schedule, String>, now: DateTime<Local>) -> Self {
+        let next_at = schedule.as_ref().ok().and_then(|s| s.next_after(now));
+        Self { content: content.to_string(), schedule, next_at, last_at: None, queue_id: None }
+    }
+}
+
+/// Schedule entries by block ID
+pub struct Schedules {
+    entries: Mutex<HashMap<String, Entry>>,
+    /// The doc's every:: / cron:: blocks, so a tick doesn't read every block
+    index: BlockIndex,
+}
+
+impl Default for Schedules {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::default(),
+            index: BlockIndex::new(|block| schedule_kind(block).is_some()),
+        }
+    }
+}
+
+/// A schedule as reported to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleInfo {
+    pub block_id: String,
+    pub content: String,
+    /// Unix millis
+    pub next_run: Option<i64>,
+    pub last_run: Option<i64>,
+    pub paused: bool,
+    /// The latest run is still going
+    pub running: bool,
+    pub error: Option<String>,
+}
+
+/// A block's schedule kind and value - never for output, which only looks like one
+fn schedule_kind(block: &BlockData) -> Option<(String, String)> {
+    if OUTPUT_TYPES.contains(&block.get_str("type").unwrap_or_default()) {
+        return None;
+    }
+    match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == "every" || prefix == "cron" => Some((prefix, value.to_string())),
+        _ => None,
+    }
+}
+
+/// A schedule whose time has come
+struct Due {
+    block_id: String,
+    replace: bool,
+}
+
+/// Bring the entries in line with the doc's schedule blocks
+/// Returns the schedules and, when firing, which of them are due (moving those on to their next time)
+fn reconcile(state: &AppState, now: DateTime<Local>, fire: bool) -> Result<(Vec<ScheduleInfo>, Vec<Due>), String> {
+    let found: Vec<(BlockData, String, String)> = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let ids = state.schedules.index.ids(&doc)?;
+        let txn = doc.transact();
+        let Some(blocks) = txn.get_map("blocks") else { return Ok((vec![], vec![])) };
+        ids.iter()
+            .filter_map(|id| read_block(&blocks, &txn, id))
+            .filter_map(|block| schedule_kind(&block).map(|(kind, value)| (block, kind, value)))
+            .collect()
+    };
+
+    let mut entries = state.schedules.entries.lock().map_err(|e| e.to_string())?;
+    entries.retain(|id, _| found.iter().any(|(block, _, _)| block.id() == id));
+
+    let mut infos = vec![];
+    let mut due = vec![];
+    for (block, kind, value) in found {
+        let entry = entries
+            .entry(block.id().to_string())
+            .or_insert_with(|| Entry::new(block.content(), Schedule::parse(&kind, &value), now));
+        if entry.content != block.content() {
+            *entry = Entry::new(block.content(), Schedule::parse(&kind, &value), now);
+        }
+
+        let running = entry.queue_id.as_ref().is_some_and(|id| state.jobs.is_running(id));
+        let paused = block.get_bool("paused").unwrap_or(false);
+        if let (Ok(schedule), Some(next_at)) = (&entry.schedule, entry.next_at) {
+            if fire && next_at <= now {
+                // A paused or still-running schedule just skips its turn
+                if !paused && !running {
+                    due.push(Due { block_id: block.id().to_string(), replace: schedule.replace });
+                    entry.last_at = Some(now);
+                }
+                entry.next_at = schedule.next_after(now);
+            }
+        }
+
+        infos.push(ScheduleInfo {
+            block_id: block.id().to_string(),
+            content: block.content().to_string(),
+            next_run: entry.next_at.map(|at| at.timestamp_millis()),
+            last_run: entry.last_at.map(|at| at.timestamp_millis()),
+            paused,
+            running,
+            error: entry.schedule.as_ref().err().cloned(),
+        });
+    }
+
+    infos.sort_by_key(|info| (info.next_run.is_none(), info.next_run, info.block_id.clone()));
+    Ok((infos, due))
+}
+
+/// Start a run of every schedule that is due
+fn tick(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let (_, due) = reconcile(&state, Local::now(), true)?;
+
+    for Due { block_id, replace } in due {
+        let options = QueueOptions {
+            concurrency: 1,
+            stop_on_failure: false,
+            placement: Placement { replace, grouped: true },
+        };
+        match start_queue(app.clone(), &state, &block_id, options) {
+            Ok(queue) => {
+                let mut entries = state.schedules.entries.lock().map_err(|e| e.to_string())?;
+                if let Some(entry) = entries.get_mut(&block_id) {
+                    entry.queue_id = Some(queue.queue_id);
+                }
+            }
+            Err(e) => log::warn!("Scheduled run of {} failed to start: {}", block_id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Run due schedules for as long as the app is open
+pub async fn run_scheduler(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(TICK).await;
+        if let Err(e) = tick(&app) {
+            log::warn!("Scheduler: {}", e);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// List the current workspace's schedules, soonest first
+#[tauri::command]
+pub fn list_schedules(state: tauri::State<'_, AppState>) -> Result<Vec<ScheduleInfo>, String> {
+    let (infos, _) = reconcile(&state, Local::now(), false)?;
+    Ok(infos)
+}
+
+/// Pause or resume a schedule block
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn set_schedule_paused(state: tauri::State<'_, AppState>, block_id: String, paused: bool) -> Result<String, String> {
+    {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        let block = read_block(&blocks, &txn, &block_id)
+            .ok_or_else(|| format!("Block {} not found", block_id))?;
+        if schedule_kind(&block).is_none() {
+            return Err(format!("Block {} isn't an every:: or cron:: schedule", block_id));
+        }
+    }
+    update_block(&state, &block_id, |block| block.set("paused", paused))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, LocalResult, NaiveDate, Utc};
+
+    fn bits(values: &[u32]) -> u64 {
+        values.iter().fold(0, |bits, value| bits | 1 << value)
+    }
+
+    fn cron(expr: &str) -> Cron {
+        Cron::parse(&expr.split_whitespace().collect::<Vec<_>>()).unwrap()
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    /// Central European time in 2026: clocks go forward at 02:00 on
+    /// March 29 and back at 03:00 on October 25
+    #[derive(Debug, Clone, Copy)]
+    struct Berlin;
+
+    const CET: i32 = 3600;
+    const CEST: i32 = 7200;
+
+    impl TimeZone for Berlin {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Berlin
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // An offset fits when the UTC time it gives maps back to it
+            let fits: Vec<FixedOffset> = [CEST, CET]
+                .into_iter()
+                .map(|secs| FixedOffset::east_opt(secs).unwrap())
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - Duration::seconds(offset.local_minus_utc().into()))) == *offset)
+                .collect();
+            match fits[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest, ..] => LocalResult::Ambiguous(earliest, latest),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, time: &NaiveDateTime) -> FixedOffset {
+            let summer = utc(2026, 3, 29, 1, 0).naive_utc()..utc(2026, 10, 25, 1, 0).naive_utc();
+            FixedOffset::east_opt(if summer.contains(time) { CEST } else { CET }).unwrap()
+        }
+    }
+
+    fn berlin(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Berlin> {
+        Berlin.with_ymd_and_hms(2026, month, day, hour, minute, 0).earliest().unwrap()
+    }
+
+    #[test]
+    fn parses_cron_fields() {
+        assert_eq!(parse_cron_field("*", 1, 12), Ok(bits(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12])));
+        assert_eq!(parse_cron_field("5", 0, 59), Ok(bits(&[5])));
+        assert_eq!(parse_cron_field("1-5", 0, 7), Ok(bits(&[1, 2, 3, 4, 5])));
+        assert_eq!(parse_cron_field("*/15", 0, 59), Ok(bits(&[0, 15, 30, 45])));
+        assert_eq!(parse_cron_field("10-40/10", 0, 59), Ok(bits(&[10, 20, 30, 40])));
+        assert_eq!(parse_cron_field("5/20", 0, 59), Ok(bits(&[5, 25, 45])));
+        assert_eq!(parse_cron_field("1,3,20-22", 1, 31), Ok(bits(&[1, 3, 20, 21, 22])));
+
+        for field in ["60", "0", "5-1", "*/0", "a", "1-x", "*/x", "", "1,"] {
+            assert!(parse_cron_field(field, 1, 59).is_err(), "{}", field);
+        }
+    }
+
+    #[test]
+    fn parses_cron_expressions() {
+        assert_eq!(cron("0 9 * * 7").weekdays, bits(&[0]));
+        assert_eq!(cron("0 9 * * 0,7").weekdays, bits(&[0]));
+        assert!(!cron("0 9 * * 1-5").either_day);
+        assert!(cron("0 9 13 * 5").either_day);
+        assert!(Cron::parse(&["0", "9", "*", "*"]).unwrap_err().contains("5 fields"));
+        assert!(Cron::parse(&["0", "24", "*", "*", "*"]).is_err());
+    }
+
+    #[test]
+    fn finds_next_cron_time() {
+        // Friday evening to Monday morning
+        assert_eq!(cron("0 9 * * 1-5").next_after(utc(2026, 10, 16, 17, 0)), Some(utc(2026, 10, 19, 9, 0)));
+        // Always strictly after
+        assert_eq!(cron("*/15 * * * *").next_after(utc(2026, 10, 16, 10, 7)), Some(utc(2026, 10, 16, 10, 15)));
+        assert_eq!(cron("*/15 * * * *").next_after(utc(2026, 10, 16, 10, 15)), Some(utc(2026, 10, 16, 10, 30)));
+        // Only leap years have the day
+        assert_eq!(cron("0 0 29 2 *").next_after(utc(2026, 3, 1, 0, 0)), Some(utc(2028, 2, 29, 0, 0)));
+        assert_eq!(cron("0 0 31 2 *").next_after(utc(2026, 3, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn day_of_month_or_weekday() {
+        // Both given: the 13th or any Friday
+        let either = cron("0 12 13 * 5");
+        assert_eq!(either.next_after(utc(2026, 10, 1, 0, 0)), Some(utc(2026, 10, 2, 12, 0)));
+        assert_eq!(either.next_after(utc(2026, 10, 9, 12, 0)), Some(utc(2026, 10, 13, 12, 0)));
+        assert_eq!(either.next_after(utc(2026, 10, 13, 12, 0)), Some(utc(2026, 10, 16, 12, 0)));
+        // Only one given: just that one
+        assert_eq!(cron("0 12 13 * *").next_after(utc(2026, 10, 1, 0, 0)), Some(utc(2026, 10, 13, 12, 0)));
+        assert_eq!(cron("0 12 * * 5").next_after(utc(2026, 10, 2, 12, 0)), Some(utc(2026, 10, 9, 12, 0)));
+    }
+
+    #[test]
+    fn skips_times_lost_to_dst() {
+        // 02:30 doesn't exist on March 29
+        assert_eq!(cron("30 2 * * *").next_after(berlin(3, 28, 12, 0)), Some(berlin(3, 30, 2, 30)));
+        assert_eq!(cron("0 * * * *").next_after(berlin(3, 29, 1, 30)), Some(berlin(3, 29, 3, 0)));
+        // 02:30 happens twice on October 25: run at the first one only
+        let first = cron("30 2 * * *").next_after(berlin(10, 25, 0, 0)).unwrap();
+        assert_eq!(first.with_timezone(&Utc), utc(2026, 10, 25, 0, 30));
+        assert_eq!(cron("30 2 * * *").next_after(first), Some(berlin(10, 26, 2, 30)));
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!(parse_interval("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_interval("15m"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_interval("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_interval("1d"), Ok(Duration::days(1)));
+        assert_eq!(parse_interval("365d24h"), Ok(Duration::days(366)));
+
+        for value in ["", "15", "m", "0m", "15x", "-5m", "1.5h", "1h 30m"] {
+            assert!(parse_interval(value).unwrap_err().contains("Invalid interval"), "{}", value);
+        }
+        assert!(parse_interval("367d").unwrap_err().contains("longer than 366 days"));
+    }
+
+    #[test]
+    fn out_of_range_intervals_are_errors() {
+        for value in ["99999999999999999999s", "200000000000d", "9000000000000000s9000000000000000s", "100000000000d100000000000d"] {
+            assert!(parse_interval(value).is_err(), "{}", value);
+        }
+        let schedule = Schedule { timing: Timing::Every(Duration::days(366)), replace: false };
+        assert_eq!(schedule.next_after(DateTime::<Local>::MAX_UTC.into()), None);
+    }
+
+    #[test]
+    fn parses_schedules() {
+        let every = Schedule::parse("every", "1h replace").unwrap();
+        assert_eq!(every, Schedule { timing: Timing::Every(Duration::hours(1)), replace: true });
+        assert!(!Schedule::parse("cron", "0 9 * * * append").unwrap().replace);
+        assert!(Schedule::parse("every", "1h 2h").unwrap_err().contains("one interval"));
+    }
+
+    #[test]
+    fn output_is_never_a_schedule() {
+        let mut block = BlockData::default();
+        block.set("content", "every:: 5m");
+        block.set("type", "text");
+        assert_eq!(schedule_kind(&block), Some(("every".to_string(), "5m".to_string())));
+        for kind in OUTPUT_TYPES {
+            block.set("type", *kind);
+            assert_eq!(schedule_kind(&block), None, "{}", kind);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::mpsc;
+use std::process::Stdio;
+use crate::runners::RunnerTable;
+use crate::{AppState, BlockData, RunOutput, ancestors, split_prefix};
+
+// ═══════════════════════════════════════════════════════════════
+// SESSIONS
+// ═══════════════════════════════════════════════════════════════
+//
+// A `session:: name [kind]` ancestor keeps one interpreter alive for the
+// blocks beneath it, so `cd`, variables and Python state carry over:
+//
+//   session:: analysis python
+//     py:: import json; data = [1, 2, 3]
+//     py:: print(sum(data))
+//
+// The kind is a runner name ("shell" by default, or "python") and the
+// interpreter command comes from the runner table. A block only runs in
+// the session when its own runner matches the session kind. Each block's
+// code is followed by an end marker so its output can be split off.
+
+/// Kinds we know how to drive as a REPL
+const SHELL_KIND: &str = "shell";
+const PYTHON_KIND: &str = "python";
+
+/// Reads framed blocks of code from stdin and execs them in one namespace
+const PYTHON_DRIVER: &str = r#"
+import os, sys, traceback
+source = sys.stdin
+sys.stdin = open(os.devnull)
+namespace = {"__name__": "__main__"}
+while True:
+    token = source.readline()
+    if not token:
+        break
+    token = token.strip()
+    lines = []
+    while True:
+        line = source.readline()
+        if not line or line.rstrip("\n") == "__FLOAT_END_" + token:
+            break
+        lines.append(line)
+    status = 0
+    try:
+        exec(compile("".join(lines), "<block>", "exec"), namespace)
+    except SystemExit as e:
+        if e.code is None or isinstance(e.code, int):
+            status = e.code or 0
+        else:
+            print(e.code, file=sys.stderr)
+            status = 1
+    except BaseException:
+        kind, error, tb = sys.exc_info()
+        traceback.print_exception(kind, error, tb.tb_next)
+        status = 1
+    sys.stdout.flush()
+    print("\n__FLOAT_DONE_%s_%d" % (token, status), flush=True)
+    print("\n__FLOAT_DONE_%s" % token, file=sys.stderr, flush=True)
+"#;
+
+/// The `session::` ancestor a block runs under
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionSpec {
+    pub name: String,
+    pub kind: String,
+}
+
+/// Find the nearest `session::` ancestor of a block
+pub fn find_session<T: yrs::ReadTxn>(blocks: &yrs::MapRef, txn: &T, block_id: &str) -> Result<Option<SessionSpec>, String> {
+    let Some(value) = ancestors(blocks, txn, block_id).iter().find_map(session_value) else {
+        return Ok(None);
+    };
+
+    let mut words = value.split_whitespace();
+    let name = words.next().ok_or("session:: needs a name, e.g. `session:: analysis python`")?;
+    let kind = words.next().unwrap_or(SHELL_KIND).to_lowercase();
+    Ok(Some(SessionSpec { name: name.to_string(), kind }))
+}
+
+fn session_value(block: &BlockData) -> Option<String> {
+    match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == "session" => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Which stream a line of session output came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The interpreter's pipes, held for the duration of one block's run
+struct SessionIo {
+    stdin: ChildStdin,
+    lines: mpsc::UnboundedReceiver<(Stream, Vec<u8>)>,
+    /// Token of a run that was dropped (cancelled) before its end markers were read
+    unfinished: Option<String>,
+}
+
+/// A long-lived interpreter process
+pub struct Session {
+    name: String,
+    kind: String,
+    pid: Option<u32>,
+    started_at: i64,
+    runs: AtomicU64,
+    /// Kept outside `io` so a hung run can still be killed
+    child: Mutex<Child>,
+    io: tokio::sync::Mutex<SessionIo>,
+}
+
+/// Session state as reported to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub name: String,
+    pub kind: String,
+    pub pid: Option<u32>,
+    pub started_at: i64,
+    pub runs: u64,
+    pub alive: bool,
+}
+
+impl Session {
+    fn info(&self) -> SessionInfo {
+        let alive = self.child.lock()
+            .map(|mut child| matches!(child.try_wait(), Ok(None)))
+            .unwrap_or(false);
+        SessionInfo {
+            name: self.name.clone(),
+            kind: self.kind.clone(),
+            pid: self.pid,
+            started_at: self.started_at,
+            runs: self.runs.load(Ordering::Relaxed),
+            alive,
+        }
+    }
+
+    fn kill(&self) {
+        if let Ok(mut child) = self.child.lock() {
+            child.start_kill().ok();
+        }
+    }
+}
+
+/// Live sessions by name
+#[derive(Default)]
+pub struct SessionRegistry {
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+}
+
+/// Forward lines from one of the interpreter's pipes into the session channel
+fn forward_lines<R>(reader: R, stream: Stream, tx: mpsc::UnboundedSender<(Stream, Vec<u8>)>)
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = vec![];
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if tx.send((stream, line)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Spawn the interpreter for a session
+fn start_session(spec: &SessionSpec, table: &RunnerTable) -> Result<Session, String> {
+    let runner = table.get(&spec.kind)
+        .ok_or_else(|| format!("Unknown session kind '{}'", spec.kind))?;
+    let (program, args) = runner.command.split_first()
+        .ok_or_else(|| format!("Runner '{}' has an empty command", spec.kind))?;
+
+    let mut command = Command::new(program);
+    command.args(args);
+    match spec.kind.as_str() {
+        SHELL_KIND => {}
+        PYTHON_KIND => {
+            command.arg("-c").arg(PYTHON_DRIVER);
+        }
+        other => return Err(format!("'{}' sessions aren't supported (use shell or python)", other)),
+    }
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start session '{}': {}", spec.name, e))?;
+
+    let stdin = child.stdin.take().ok_or("Session has no stdin")?;
+    let stdout = child.stdout.take().ok_or("Session has no stdout")?;
+    let stderr = child.stderr.take().ok_or("Session has no stderr")?;
+
+    let (tx, lines) = mpsc::unbounded_channel();
+    forward_lines(stdout, Stream::Stdout, tx.clone());
+    forward_lines(stderr, Stream::Stderr, tx);
+
+    Ok(Session {
+        name: spec.name.clone(),
+        kind: spec.kind.clone(),
+        pid: child.id(),
+        started_at: Utc::now().timestamp_millis(),
+        runs: AtomicU64::new(0),
+        child: Mutex::new(child),
+        io: tokio::sync::Mutex::new(SessionIo { stdin, lines, unfinished: None }),
+    })
+}
+
+/// Wrap a block's code in the session protocol for its kind
+fn frame_code(kind: &str, token: &str, code: &str) -> String {
+    if kind == PYTHON_KIND {
+        return format!("{}\n{}\n__FLOAT_END_{}\n", token, code, token);
+    }
+
+    // `command eval` keeps a syntax error from killing the shell, and
+    // /dev/null keeps the code from reading our protocol lines as input
+    format!(
+        "command eval '{}' </dev/null\nprintf '\\n__FLOAT_DONE_{}_%s\\n' \"$?\"\nprintf '\\n__FLOAT_DONE_{}\\n' >&2\n",
+        code.replace('\'', "'\\''"),
+        token,
+        token
+    )
+}
+
+/// Drop the newline we put in front of the end marker
+fn strip_marker_newline(mut text: String) -> String {
+    if text.ends_with('\n') {
+        text.pop();
+    }
+    text
+}
+
+/// Output of one framed run
+#[derive(Default)]
+struct Collected {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+    /// Both end markers arrived (false if the interpreter went away first)
+    complete: bool,
+}
+
+/// Read session output up to a run's end markers
+async fn collect_run(lines: &mut mpsc::UnboundedReceiver<(Stream, Vec<u8>)>, token: &str) -> Collected {
+    let stdout_marker = format!("__FLOAT_DONE_{}_", token);
+    let stderr_marker = format!("__FLOAT_DONE_{}", token);
+
+    let mut run = Collected { stdout: String::new(), stderr: String::new(), exit_code: None, complete: false };
+    let mut stderr_done = false;
+
+    while run.exit_code.is_none() || !stderr_done {
+        // Both pipes closed - the interpreter exited or was killed mid-run
+        let Some((stream, line)) = lines.recv().await else { return run };
+        let line = String::from_utf8_lossy(&line).into_owned();
+
+        match stream {
+            Stream::Stdout => match line.trim_end().strip_prefix(&stdout_marker) {
+                Some(status) => run.exit_code = Some(status.parse().unwrap_or(-1)),
+                None => run.stdout.push_str(&line),
+            },
+            Stream::Stderr => {
+                if line.trim_end() == stderr_marker {
+                    stderr_done = true;
+                } else {
+                    run.stderr.push_str(&line);
+                }
+            }
+        }
+    }
+
+    run.complete = true;
+    run
+}
+
+/// What a run got before its interpreter exited or was killed
+fn session_ended(name: &str, run: Collected) -> RunOutput {
+    let stderr = format!("{}\nsession '{}' ended\n", run.stderr, name);
+    RunOutput { stdout: run.stdout, stderr, exit_code: -1, signal: None, cwd: None }
+}
+
+/// Run one block's code in its session, starting the session if needed
+pub async fn run_in_session(
+    registry: &SessionRegistry,
+    spec: &SessionSpec,
+    table: &RunnerTable,
+    code: &str,
+) -> Result<RunOutput, String> {
+    let session = {
+        let mut sessions = registry.sessions.lock().map_err(|e| e.to_string())?;
+
+        // A session whose interpreter died is replaced transparently
+        if sessions.get(&spec.name).is_some_and(|s| !s.info().alive) {
+            sessions.remove(&spec.name);
+        }
+
+        match sessions.get(&spec.name) {
+            Some(session) if session.kind != spec.kind => {
+                return Err(format!(
+                    "Session '{}' is already running {} - kill it to start a {} session",
+                    spec.name, session.kind, spec.kind
+                ));
+            }
+            Some(session) => session.clone(),
+            None => {
+                let session = Arc::new(start_session(spec, table)?);
+                sessions.insert(spec.name.clone(), session.clone());
+                session
+            }
+        }
+    };
+
+    let mut io = session.io.lock().await;
+    session.runs.fetch_add(1, Ordering::Relaxed);
+
+    // A cancelled run's output (and possibly the run itself) is still ahead of ours
+    // in the pipes - read past it so it doesn't end up in this block
+    if let Some(stale) = io.unfinished.clone() {
+        if !collect_run(&mut io.lines, &stale).await.complete {
+            return Ok(session_ended(&session.name, Collected::default()));
+        }
+        io.unfinished = None;
+    }
+
+    // Cleared once the end markers are read, so it stays set if this future is dropped
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    io.unfinished = Some(token.clone());
+
+    io.stdin.write_all(frame_code(&session.kind, &token, code).as_bytes()).await
+        .map_err(|e| format!("Session '{}' isn't accepting input: {}", session.name, e))?;
+    io.stdin.flush().await.map_err(|e| e.to_string())?;
+
+    let run = collect_run(&mut io.lines, &token).await;
+    if !run.complete {
+        return Ok(session_ended(&session.name, run));
+    }
+    io.unfinished = None;
+
+    Ok(RunOutput {
+        stdout: strip_marker_newline(run.stdout),
+        stderr: strip_marker_newline(run.stderr),
+        exit_code: run.exit_code.unwrap_or(-1),
+        signal: None,
+        // The session's own cwd can drift with `cd`
+        cwd: None,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// List live sessions
+#[tauri::command]
+pub fn list_sessions(state: tauri::State<'_, AppState>) -> Result<Vec<SessionInfo>, String> {
+    let sessions = state.sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let mut infos: Vec<SessionInfo> = sessions.values().map(|s| s.info()).collect();
+    infos.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(infos)
+}
+
+/// Kill a session's interpreter (the next block under it starts a fresh one)
+#[tauri::command]
+pub fn kill_session(state: tauri::State<'_, AppState>, name: String) -> Result<(), String> {
+    let mut sessions = state.sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let session = sessions.remove(&name)
+        .ok_or_else(|| format!("No session named '{}'", name))?;
+    session.kill();
+    Ok(())
+}
+
+/// Restart a session with a fresh interpreter of the same kind
+#[tauri::command]
+pub async fn restart_session(state: tauri::State<'_, AppState>, name: String) -> Result<SessionInfo, String> {
+    let table = crate::runners::load_runners()?;
+    let mut sessions = state.sessions.sessions.lock().map_err(|e| e.to_string())?;
+    let old = sessions.remove(&name)
+        .ok_or_else(|| format!("No session named '{}'", name))?;
+    old.kill();
+
+    let spec = SessionSpec { name: old.name.clone(), kind: old.kind.clone() };
+    let session = Arc::new(start_session(&spec, &table)?);
+    let info = session.info();
+    sessions.insert(name, session);
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::runners::Runner;
+
+    /// A python session set up without the user's runners.json - None (and the
+    /// test skipped) when python3 isn't installed
+    fn python() -> Option<(SessionRegistry, SessionSpec, RunnerTable)> {
+        if std::process::Command::new("python3").arg("--version").output().is_err() {
+            eprintln!("python3 not found, skipping");
+            return None;
+        }
+        let runner = Runner {
+            prefixes: vec![],
+            languages: vec![],
+            command: vec!["python3".to_string(), "-u".to_string()],
+            extension: "py".to_string(),
+            ansi: Default::default(),
+        };
+        let table = RunnerTable::from([(PYTHON_KIND.to_string(), runner)]);
+        let spec = SessionSpec { name: "test".to_string(), kind: PYTHON_KIND.to_string() };
+        Some((SessionRegistry::default(), spec, table))
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Runtime::new().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn python_exit_codes() {
+        let Some((registry, spec, table)) = python() else { return };
+        block_on(async {
+            let run = |code: &'static str| run_in_session(&registry, &spec, &table, code);
+            assert_eq!(run("raise SystemExit").await.unwrap().exit_code, 0);
+            assert_eq!(run("import sys; sys.exit(3)").await.unwrap().exit_code, 3);
+            let failed = run("import sys; sys.exit('bad input')").await.unwrap();
+            assert_eq!((failed.exit_code, failed.stderr.as_str()), (1, "bad input\n"));
+            assert_eq!(run("print('still here')").await.unwrap().stdout, "still here\n");
+        });
+    }
+
+    #[test]
+    fn cancelled_run_does_not_leak_into_the_next() {
+        let Some((registry, spec, table)) = python() else { return };
+        block_on(async {
+            let slow = run_in_session(&registry, &spec, &table, "import time; time.sleep(0.3); print('old')");
+            assert!(tokio::time::timeout(Duration::from_millis(50), slow).await.is_err());
+
+            let next = run_in_session(&registry, &spec, &table, "print('new')").await.unwrap();
+            assert_eq!((next.stdout.as_str(), next.exit_code), ("new\n", 0));
+        });
+    }
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use crate::get_data_dir;
+use crate::notify::NotifySettings;
+use crate::output::OutputLimits;
+
+// ═══════════════════════════════════════════════════════════════
+// SETTINGS
+// ═══════════════════════════════════════════════════════════════
+//
+// App-wide settings from ~/.float-liner/settings.json. Every section and
+// field is optional, missing ones fall back to defaults:
+//
+//   { "output": { "maxLines": 2000 }, "notifications": { "afterSeconds": 30 } }
+
+/// Settings loaded from settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Caps on how much command output becomes blocks
+    pub output: OutputLimits,
+    /// When finished runs raise a desktop notification
+    pub notifications: NotifySettings,
+}
+
+/// Get the path to the settings file
+fn get_settings_path() -> PathBuf {
+    get_data_dir().join("settings.json")
+}
+
+/// Load settings, falling back to defaults when the file doesn't exist
+pub fn load_settings() -> Result<Settings, String> {
+    let path = get_settings_path();
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid settings {:?}: {}", path, e))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Get the effective settings (settings.json merged over defaults)
+#[tauri::command]
+pub fn get_settings() -> Result<Settings, String> {
+    load_settings()
+}
@@ -0,0 +1,338 @@
+use std::sync::Arc;
+use serde_json::Value as JsonValue;
+use crate::{split_prefix, ParsedBlock};
+
+// ═══════════════════════════════════════════════════════════════
+// STRUCTURED OUTPUT
+// ═══════════════════════════════════════════════════════════════
+//
+// A format word attached to the prefix says how to read stdout:
+//
+//   sh::json gh api repos/float/liner     → nested key:: value blocks
+//   sh::csv  cat report.csv               → table block with row children
+//
+// `sh:: json ...` (with a space) is still a command called `json`. Without
+// a format, output that parses as a JSON object or array is read as JSON.
+
+/// How to read a run's stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Plain lines / markdown, never structured
+    Text,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    fn from_word(word: &str) -> Option<Self> {
+        match word.to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "json" => Some(OutputFormat::Json),
+            "csv" => Some(OutputFormat::Csv),
+            "tsv" => Some(OutputFormat::Tsv),
+            _ => None,
+        }
+    }
+}
+
+/// Split a format word off the prefix: "sh::json gh api" → (Json, "sh:: gh api")
+/// Content without one comes back unchanged
+pub fn split_format(content: &str) -> (Option<OutputFormat>, String) {
+    let trimmed = content.trim();
+    let Some((prefix, rest)) = trimmed.split_once("::") else { return (None, content.to_string()) };
+
+    if split_prefix(trimmed).is_none() {
+        return (None, content.to_string());
+    }
+
+    let word_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    match OutputFormat::from_word(&rest[..word_end]) {
+        Some(format) => (Some(format), format!("{}:: {}", prefix, rest[word_end..].trim_start())),
+        None => (None, content.to_string()),
+    }
+}
+
+/// Hands out `{base_id}-{n}` IDs that are unique across a whole tree
+struct IdGen<'a> {
+    base_id: &'a str,
+    next: usize,
+}
+
+impl IdGen<'_> {
+    fn block(&mut self, content: String, block_type: &str) -> ParsedBlock {
+        let id = format!("{}-{}", self.base_id, self.next);
+        self.next += 1;
+        ParsedBlock::new(id, content, block_type)
+    }
+}
+
+/// Total number of blocks in a parsed tree
+pub fn count_blocks(parsed: &[ParsedBlock]) -> usize {
+    parsed.iter().map(|b| 1 + count_blocks(&b.children)).sum()
+}
+
+/// Parse stdout as structured data
+/// Ok(None) means "not structured, use the text parser"; Err means the requested format didn't parse
+pub fn parse_structured(
+    text: &str,
+    format: Option<OutputFormat>,
+    base_id: &str,
+    block_type: &str,
+) -> Result<Option<Vec<ParsedBlock>>, String> {
+    let mut ids = IdGen { base_id, next: 0 };
+
+    match format {
+        Some(OutputFormat::Text) => Ok(None),
+        Some(OutputFormat::Json) => {
+            let value: JsonValue = serde_json::from_str(text.trim())
+                .map_err(|e| format!("Output isn't valid JSON: {}", e))?;
+            Ok(Some(json_blocks(&mut ids, &value, block_type)))
+        }
+        Some(OutputFormat::Csv) => Ok(Some(table_blocks(&mut ids, parse_csv(text)?))),
+        Some(OutputFormat::Tsv) => Ok(Some(table_blocks(&mut ids, parse_tsv(text)))),
+        None => {
+            // Only objects and arrays - a bare `42` or `"ok"` reads fine as text
+            let trimmed = text.trim();
+            if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+                return Ok(None);
+            }
+            match serde_json::from_str::<JsonValue>(trimmed) {
+                Ok(value) => Ok(Some(json_blocks(&mut ids, &value, block_type))),
+                Err(_) => Ok(None),
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// JSON
+// ═══════════════════════════════════════════════════════════════
+
+/// Text for a scalar JSON value
+fn scalar_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        JsonValue::Null => Some("null".to_string()),
+        JsonValue::Array(_) | JsonValue::Object(_) => None,
+    }
+}
+
+/// Label for an array element holding an object, e.g. "[0] float-liner" from its name
+fn element_label(index: usize, value: &JsonValue) -> String {
+    let title = ["name", "title", "id", "key"].iter().find_map(|field| match value.get(field) {
+        Some(JsonValue::String(s)) => Some(s.clone()),
+        Some(JsonValue::Number(n)) => Some(n.to_string()),
+        _ => None,
+    });
+    match title {
+        Some(title) => format!("[{}] {}", index, title),
+        None => format!("[{}]", index),
+    }
+}
+
+/// Blocks for the members of an object or the elements of an array
+fn json_blocks(ids: &mut IdGen, value: &JsonValue, block_type: &str) -> Vec<ParsedBlock> {
+    match value {
+        JsonValue::Object(map) => map
+            .iter()
+            .map(|(key, value)| match scalar_text(value) {
+                Some(text) => ids.block(format!("{}:: {}", key, text), block_type),
+                None => {
+                    let mut block = ids.block(format!("{}::", key), block_type);
+                    block.children = json_blocks(ids, value, block_type);
+                    block
+                }
+            })
+            .collect(),
+        JsonValue::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| match scalar_text(item) {
+                Some(text) => ids.block(text, block_type),
+                None => {
+                    let mut block = ids.block(element_label(i, item), block_type);
+                    block.children = json_blocks(ids, item, block_type);
+                    block
+                }
+            })
+            .collect(),
+        scalar => vec![ids.block(scalar_text(scalar).unwrap_or_default(), block_type)],
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TABLES
+// ═══════════════════════════════════════════════════════════════
+
+/// Parse CSV (RFC 4180: quoted fields, "" escapes, newlines inside quotes)
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = vec![];
+    let mut row: Vec<String> = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("Output isn't valid CSV: unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    rows.retain(|r| !(r.len() == 1 && r[0].trim().is_empty()));
+    Ok(rows)
+}
+
+/// Parse tab-separated values (no quoting)
+fn parse_tsv(text: &str) -> Vec<Vec<String>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split('\t').map(str::to_string).collect())
+        .collect()
+}
+
+fn strings_to_any(values: &[String]) -> yrs::Any {
+    yrs::Any::Array(Arc::from(
+        values.iter().map(|s| yrs::Any::String(s.clone().into())).collect::<Vec<_>>()
+    ))
+}
+
+/// A table block (header row as content and `columns`) with one row block per record
+fn table_blocks(ids: &mut IdGen, rows: Vec<Vec<String>>) -> Vec<ParsedBlock> {
+    let mut rows = rows.into_iter();
+    let Some(columns) = rows.next() else { return vec![] };
+
+    let mut table = ids.block(columns.join(" | "), "table");
+    table.props.insert("columns".to_string(), strings_to_any(&columns));
+
+    table.children = rows
+        .map(|cells| {
+            let mut row = ids.block(cells.join(" | "), "row");
+            row.props.insert("cells".to_string(), strings_to_any(&cells));
+            row
+        })
+        .collect();
+
+    vec![table]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Blocks as indented `content` lines
+    fn outline(blocks: &[ParsedBlock]) -> String {
+        fn walk(blocks: &[ParsedBlock], depth: usize, out: &mut String) {
+            for block in blocks {
+                out.push_str(&format!("{}{}\n", "  ".repeat(depth), block.content));
+                walk(&block.children, depth + 1, out);
+            }
+        }
+        let mut out = String::new();
+        walk(blocks, 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn format_words() {
+        assert_eq!(split_format("sh::json gh api user"), (Some(OutputFormat::Json), "sh:: gh api user".to_string()));
+        assert_eq!(split_format("sh::CSV cat a.csv"), (Some(OutputFormat::Csv), "sh:: cat a.csv".to_string()));
+        assert_eq!(split_format("sh::tsv"), (Some(OutputFormat::Tsv), "sh:: ".to_string()));
+        // With a space it's the command's name, not a format
+        assert_eq!(split_format("sh:: json x"), (None, "sh:: json x".to_string()));
+        assert_eq!(split_format("sh::yaml x"), (None, "sh::yaml x".to_string()));
+        assert_eq!(split_format("plain text"), (None, "plain text".to_string()));
+    }
+
+    #[test]
+    fn csv_quoting() {
+        let rows = parse_csv("name,note\r\n\"Smith, J\",\"said \"\"hi\"\"\"\nb,\"two\nlines\"\n\n").unwrap();
+        assert_eq!(rows, vec![
+            strings(&["name", "note"]),
+            strings(&["Smith, J", "said \"hi\""]),
+            strings(&["b", "two\nlines"]),
+        ]);
+        // No trailing newline, and an empty last field
+        assert_eq!(parse_csv("a,b\n1,").unwrap(), vec![strings(&["a", "b"]), strings(&["1", ""])]);
+        assert!(parse_csv("a,\"open\n").is_err());
+    }
+
+    #[test]
+    fn tsv_rows() {
+        assert_eq!(parse_tsv("a\tb\n\n1\t\"2\"\n"), vec![strings(&["a", "b"]), strings(&["1", "\"2\""])]);
+    }
+
+    #[test]
+    fn tables_from_csv() {
+        let parsed = parse_structured("name,size\na.txt,12\n", Some(OutputFormat::Csv), "b", "output").unwrap().unwrap();
+        assert_eq!(outline(&parsed), "name | size\n  a.txt | 12\n");
+        assert_eq!((parsed[0].block_type.as_str(), parsed[0].children[0].block_type.as_str()), ("table", "row"));
+        assert_eq!(parsed[0].children[0].props.get("cells"), Some(&strings_to_any(&strings(&["a.txt", "12"]))));
+    }
+
+    #[test]
+    fn json_labels() {
+        let json = r#"{"repo": {"name": "liner", "stars": 3, "tags": ["a", {"id": 7}, {"x": null}]}, "ok": true}"#;
+        let parsed = parse_structured(json, Some(OutputFormat::Json), "b", "output").unwrap().unwrap();
+        // Keys stay in document order; IDs count through the whole tree
+        assert_eq!(outline(&parsed), "\
+repo::
+  name:: liner
+  stars:: 3
+  tags::
+    a
+    [1] 7
+      id:: 7
+    [2]
+      x:: null
+ok:: true
+");
+        let ids: Vec<&str> = parsed.iter().map(|block| block.id.as_str()).collect();
+        assert_eq!(ids, ["b-0", "b-9"]);
+        assert!(parse_structured("{oops", Some(OutputFormat::Json), "b", "output").is_err());
+    }
+
+    #[test]
+    fn auto_detects_only_json_objects_and_arrays() {
+        let detect = |text: &str| parse_structured(text, None, "b", "output").unwrap().map(|parsed| outline(&parsed));
+        assert_eq!(detect("[1, 2]\n"), Some("1\n2\n".to_string()));
+        assert_eq!(detect("42"), None);
+        assert_eq!(detect("\"ok\""), None);
+        assert_eq!(detect("{not json"), None);
+        assert_eq!(detect("name,size\na,1"), None);
+        assert_eq!(parse_structured("[1]", Some(OutputFormat::Text), "b", "output").unwrap().map(|_| ()), None);
+    }
+}
@@ -0,0 +1,674 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use serde::Serialize;
+use tauri::Emitter;
+use tokio::sync::mpsc;
+use crate::{current_dir, exit_details, AppState, RunOutput};
+
+// ═══════════════════════════════════════════════════════════════
+// PTY
+// ═══════════════════════════════════════════════════════════════
+//
+// term:: blocks run under a pseudo-terminal so programs that want a TTY
+// (progress bars, pagers, colors, `top`) behave. Raw output is streamed
+// to the UI as `term-output` events, and a small terminal emulator
+// resolves carriage returns and cursor moves into the final lines that
+// are appended as output blocks.
+
+/// Default terminal size until the UI sends a resize
+const DEFAULT_ROWS: u16 = 40;
+const DEFAULT_COLS: u16 = 120;
+
+/// Raw output chunk streamed to the UI while a term:: block runs
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermOutput {
+    pub block_id: String,
+    /// Raw bytes from the terminal (lossy UTF-8), for a terminal widget
+    pub data: String,
+    /// The emulated visible screen after this chunk, for plain renderers
+    pub screen: Vec<String>,
+}
+
+/// A running terminal the UI can type into and resize
+struct TermHandle {
+    master: File,
+    /// Shared with the output loop, so a resize reaches the emulator too
+    screen: Arc<Mutex<Screen>>,
+}
+
+/// Running terminals by block ID
+#[derive(Default)]
+pub struct TermRegistry {
+    terms: Mutex<HashMap<String, Arc<Mutex<TermHandle>>>>,
+}
+
+/// What the PTY reader thread reports back
+enum PtyEvent {
+    Data(Vec<u8>),
+    /// Exit code and killing signal
+    Exit(i32, Option<i32>),
+}
+
+/// Open a PTY pair with the given window size
+#[cfg(unix)]
+fn open_pty(rows: u16, cols: u16) -> Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd), String> {
+    use std::os::fd::{FromRawFd, OwnedFd};
+
+    let mut master: libc::c_int = -1;
+    let mut slave: libc::c_int = -1;
+    let mut size = libc::winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+
+    // SAFETY: openpty writes two fds into the out-params on success; the
+    // name/termios pointers may be null and the winsize outlives the call
+    let rc = unsafe {
+        libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::addr_of_mut!(size))
+    };
+    if rc != 0 {
+        return Err(format!("Failed to open a terminal: {}", std::io::Error::last_os_error()));
+    }
+
+    // SAFETY: both fds were just returned by openpty and nothing else owns them
+    Ok(unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) })
+}
+
+/// Set the window size of a running terminal
+#[cfg(unix)]
+fn set_window_size(master: &File, rows: u16, cols: u16) -> Result<(), String> {
+    use std::os::fd::AsRawFd;
+    let size = libc::winsize { ws_row: rows, ws_col: cols, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: TIOCSWINSZ reads a winsize from the pointer, which is valid for the call
+    let rc = unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ as _, &size) };
+    if rc != 0 {
+        return Err(format!("Failed to resize terminal: {}", std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Spawn `sh -c command` attached to a fresh PTY
+/// Returns the master side and the child
+#[cfg(unix)]
+fn spawn_in_pty(command: &str, cwd: Option<&Path>) -> Result<(File, std::process::Child), String> {
+    use std::os::fd::OwnedFd;
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    let (master, slave) = open_pty(DEFAULT_ROWS, DEFAULT_COLS)?;
+    let slave_io = |fd: &OwnedFd| -> Result<Stdio, String> {
+        fd.try_clone().map(Stdio::from).map_err(|e| e.to_string())
+    };
+
+    let mut shell = std::process::Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .env("TERM", "xterm-256color")
+        .stdin(slave_io(&slave)?)
+        .stdout(slave_io(&slave)?)
+        .stderr(slave_io(&slave)?);
+    if let Some(dir) = cwd {
+        shell.current_dir(dir);
+    }
+
+    // SAFETY: only async-signal-safe calls between fork and exec - start a new
+    // session and make the PTY (already on fd 0) its controlling terminal
+    unsafe {
+        shell.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let child = shell.spawn().map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    // Close every parent copy of the slave, or reads on the master never see EOF
+    drop(shell);
+    drop(slave);
+
+    Ok((File::from(master), child))
+}
+
+#[cfg(not(unix))]
+fn spawn_in_pty(_command: &str, _cwd: Option<&Path>) -> Result<(File, std::process::Child), String> {
+    Err("term:: blocks need a Unix pseudo-terminal".to_string())
+}
+
+#[cfg(not(unix))]
+fn set_window_size(_master: &File, _rows: u16, _cols: u16) -> Result<(), String> {
+    Err("term:: blocks need a Unix pseudo-terminal".to_string())
+}
+
+/// Read the master side on a plain thread until the child is gone
+fn read_pty(mut master: File, mut child: std::process::Child, tx: mpsc::UnboundedSender<PtyEvent>) {
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match master.read(&mut buf) {
+                // Linux reports EIO once the last slave fd closes
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if tx.send(PtyEvent::Data(buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let (code, signal) = child.wait().map(exit_details).unwrap_or((-1, None));
+        tx.send(PtyEvent::Exit(code, signal)).ok();
+    });
+}
+
+/// Run a term:: command under a PTY, streaming output events, and return the
+/// emulated final screen as stdout
+pub async fn run_in_terminal(
+    app: &tauri::AppHandle,
+    registry: &TermRegistry,
+    block_id: &str,
+    command: &str,
+    input: Option<String>,
+    cwd: Option<PathBuf>,
+) -> Result<RunOutput, String> {
+    let (master, child) = spawn_in_pty(command, cwd.as_deref())?;
+    let writer = master.try_clone().map_err(|e| e.to_string())?;
+    let screen = Arc::new(Mutex::new(Screen::new(DEFAULT_ROWS as usize, DEFAULT_COLS as usize)));
+
+    {
+        let mut terms = registry.terms.lock().map_err(|e| e.to_string())?;
+        let handle = TermHandle { master: writer, screen: screen.clone() };
+        terms.insert(block_id.to_string(), Arc::new(Mutex::new(handle)));
+    }
+
+    // stdin:: text is typed into the terminal up front
+    if let Some(text) = input {
+        write_input(registry, block_id, text.as_bytes()).ok();
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    read_pty(master, child, tx);
+
+    let mut exit_code = -1;
+    let mut signal = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            PtyEvent::Data(bytes) => {
+                let visible = {
+                    let mut screen = screen.lock().map_err(|e| e.to_string())?;
+                    screen.feed(&bytes);
+                    screen.visible_lines()
+                };
+                app.emit("term-output", TermOutput {
+                    block_id: block_id.to_string(),
+                    data: String::from_utf8_lossy(&bytes).into_owned(),
+                    screen: visible,
+                }).ok();
+            }
+            PtyEvent::Exit(code, exit_signal) => {
+                exit_code = code;
+                signal = exit_signal;
+            }
+        }
+    }
+
+    if let Ok(mut terms) = registry.terms.lock() {
+        terms.remove(block_id);
+    }
+    let lines = screen.lock().map_err(|e| e.to_string())?.lines();
+
+    Ok(RunOutput {
+        stdout: lines.join("\n"),
+        stderr: String::new(),
+        exit_code,
+        signal,
+        cwd: match cwd {
+            Some(dir) => Some(dir.to_string_lossy().into_owned()),
+            None => current_dir(),
+        },
+    })
+}
+
+fn write_input(registry: &TermRegistry, block_id: &str, data: &[u8]) -> Result<(), String> {
+    let handle = {
+        let terms = registry.terms.lock().map_err(|e| e.to_string())?;
+        terms.get(block_id).cloned()
+            .ok_or_else(|| format!("No terminal running for block {}", block_id))?
+    };
+    let mut handle = handle.lock().map_err(|e| e.to_string())?;
+    handle.master.write_all(data).map_err(|e| format!("Failed to write to terminal: {}", e))
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TERMINAL EMULATOR
+// ═══════════════════════════════════════════════════════════════
+
+/// Lines kept above the visible screen - the oldest go first
+const MAX_SCROLLBACK: usize = 10_000;
+
+/// Escape sequence parser state
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseState {
+    Ground,
+    Escape,
+    /// ESC ( or ESC ) - one charset byte follows
+    Charset,
+    Csi,
+    Osc,
+    /// ESC inside an OSC, expecting `\` to terminate it
+    OscEscape,
+}
+
+/// Just enough of a VT100 to turn terminal output into final lines:
+/// carriage returns, cursor movement, erasing and the alternate screen.
+/// Colors are dropped; scrollback is kept up to `MAX_SCROLLBACK` lines.
+pub struct Screen {
+    rows: usize,
+    cols: usize,
+    lines: Vec<Vec<char>>,
+    row: usize,
+    col: usize,
+    saved_cursor: (usize, usize),
+    /// Main buffer and cursor while the alternate screen is active
+    main_screen: Option<(Vec<Vec<char>>, usize, usize)>,
+    state: ParseState,
+    params: String,
+    /// Trailing bytes of an incomplete UTF-8 character
+    pending: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows: rows.max(1),
+            cols: cols.max(1),
+            lines: vec![vec![]],
+            row: 0,
+            col: 0,
+            saved_cursor: (0, 0),
+            main_screen: None,
+            state: ParseState::Ground,
+            params: String::new(),
+            pending: vec![],
+        }
+    }
+
+    /// Feed raw terminal output
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let mut data = std::mem::take(&mut self.pending);
+        data.extend_from_slice(bytes);
+
+        let mut rest = data.as_slice();
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.put(c));
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    // SAFETY: from_utf8 just validated this prefix
+                    unsafe { std::str::from_utf8_unchecked(valid) }.chars().for_each(|c| self.put(c));
+                    match e.error_len() {
+                        Some(len) => {
+                            self.put(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            // Incomplete character at the end of the chunk
+                            self.pending = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// All lines, trailing whitespace and trailing blank lines removed
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.lines.iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+
+    /// The lines on the visible screen, trailing blank lines removed
+    /// Only looks at the last screenful, so it stays cheap however long the scrollback gets
+    pub fn visible_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.lines[self.top()..].iter()
+            .map(|line| line.iter().collect::<String>().trim_end().to_string())
+            .collect();
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+
+    /// Change the window size, as the PTY was just told
+    /// Lines already written keep their width; wrapping uses the new width from here on
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.rows = rows.max(1);
+        self.cols = cols.max(1);
+        self.col = self.col.min(self.cols);
+    }
+
+    /// Index of the first line on the visible screen
+    fn top(&self) -> usize {
+        self.lines.len().saturating_sub(self.rows)
+    }
+
+    fn ensure_row(&mut self, row: usize) {
+        while self.lines.len() <= row {
+            self.lines.push(vec![]);
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.row += 1;
+        self.ensure_row(self.row);
+
+        let excess = self.lines.len().saturating_sub(self.rows + MAX_SCROLLBACK);
+        if excess > 0 {
+            self.lines.drain(..excess);
+            self.row -= excess;
+        }
+    }
+
+    /// Move the cursor down, no further than the bottom of the screen
+    fn cursor_down(&mut self, n: usize) {
+        let bottom = self.top() + self.rows - 1;
+        self.row = self.row.saturating_add(n).min(bottom.max(self.row));
+        self.ensure_row(self.row);
+    }
+
+    fn print(&mut self, c: char) {
+        if self.col >= self.cols {
+            // Auto-wrap
+            self.col = 0;
+            self.line_feed();
+        }
+        let col = self.col;
+        let line = &mut self.lines[self.row];
+        if line.len() <= col {
+            line.resize(col + 1, ' ');
+        }
+        line[col] = c;
+        self.col += 1;
+    }
+
+    fn put(&mut self, c: char) {
+        match self.state {
+            ParseState::Ground => match c {
+                '\x1b' => self.state = ParseState::Escape,
+                '\r' => self.col = 0,
+                '\n' | '\x0b' | '\x0c' => self.line_feed(),
+                '\x08' => self.col = self.col.saturating_sub(1),
+                '\t' => self.col = ((self.col / 8) + 1) * 8,
+                c if c.is_control() => {}
+                c => self.print(c),
+            },
+            ParseState::Escape => {
+                self.state = ParseState::Ground;
+                match c {
+                    '[' => {
+                        self.params.clear();
+                        self.state = ParseState::Csi;
+                    }
+                    ']' => self.state = ParseState::Osc,
+                    '(' | ')' => self.state = ParseState::Charset,
+                    '7' => self.saved_cursor = (self.row - self.top(), self.col),
+                    '8' => self.restore_cursor(),
+                    'M' => self.row = self.row.saturating_sub(1).max(self.top()),
+                    'D' => self.line_feed(),
+                    'E' => {
+                        self.col = 0;
+                        self.line_feed();
+                    }
+                    _ => {}
+                }
+            }
+            ParseState::Charset => self.state = ParseState::Ground,
+            ParseState::Osc => match c {
+                '\x07' => self.state = ParseState::Ground,
+                '\x1b' => self.state = ParseState::OscEscape,
+                _ => {}
+            },
+            ParseState::OscEscape => self.state = ParseState::Ground,
+            ParseState::Csi => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.state = ParseState::Ground;
+                    let params = std::mem::take(&mut self.params);
+                    self.csi(&params, c);
+                } else {
+                    self.params.push(c);
+                }
+            }
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        let (row, col) = self.saved_cursor;
+        self.row = self.top() + row;
+        self.ensure_row(self.row);
+        self.col = col;
+    }
+
+    /// Handle a CSI sequence, `ESC [ params final`
+    fn csi(&mut self, params: &str, action: char) {
+        let private = params.starts_with('?');
+        let args: Vec<usize> = params
+            .trim_start_matches(['?', '>', '='])
+            .split(';')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        let arg = |i: usize, default: usize| args.get(i).copied().filter(|&n| n != 0).unwrap_or(default);
+
+        match action {
+            'A' => self.row = self.row.saturating_sub(arg(0, 1)).max(self.top()),
+            'B' | 'e' => self.cursor_down(arg(0, 1)),
+            'C' | 'a' => self.col = self.col.saturating_add(arg(0, 1)).min(self.cols - 1),
+            'D' => self.col = self.col.saturating_sub(arg(0, 1)),
+            'E' => {
+                self.cursor_down(arg(0, 1));
+                self.col = 0;
+            }
+            'F' => {
+                self.row = self.row.saturating_sub(arg(0, 1)).max(self.top());
+                self.col = 0;
+            }
+            'G' | '`' => self.col = (arg(0, 1) - 1).min(self.cols - 1),
+            'H' | 'f' => {
+                let top = self.top();
+                self.row = top + (arg(0, 1) - 1).min(self.rows - 1);
+                self.ensure_row(self.row);
+                self.col = (arg(1, 1) - 1).min(self.cols - 1);
+            }
+            'd' => {
+                self.row = self.top() + (arg(0, 1) - 1).min(self.rows - 1);
+                self.ensure_row(self.row);
+            }
+            'J' => self.erase_display(args.first().copied().unwrap_or(0)),
+            'K' => self.erase_line(args.first().copied().unwrap_or(0)),
+            'P' => {
+                let line = &mut self.lines[self.row];
+                if self.col < line.len() {
+                    let end = self.col.saturating_add(arg(0, 1)).min(line.len());
+                    line.drain(self.col..end);
+                }
+            }
+            '@' => {
+                let col = self.col;
+                let line = &mut self.lines[self.row];
+                if col <= line.len() {
+                    line.splice(col..col, std::iter::repeat(' ').take(arg(0, 1).min(self.cols)));
+                    line.truncate(self.cols);
+                }
+            }
+            'X' => {
+                let col = self.col;
+                let line = &mut self.lines[self.row];
+                let end = col.saturating_add(arg(0, 1)).min(line.len());
+                if col < end {
+                    line[col..end].fill(' ');
+                }
+            }
+            's' if !private => self.saved_cursor = (self.row - self.top(), self.col),
+            'u' if !private => self.restore_cursor(),
+            'h' if private && args.iter().any(|&n| n == 47 || n == 1047 || n == 1049) => {
+                self.enter_alternate_screen()
+            }
+            'l' if private && args.iter().any(|&n| n == 47 || n == 1047 || n == 1049) => {
+                self.leave_alternate_screen()
+            }
+            // SGR (colors) and modes we don't model
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let col = self.col;
+        let line = &mut self.lines[self.row];
+        match mode {
+            0 => line.truncate(col),
+            1 => {
+                let end = (col + 1).min(line.len());
+                line[..end].fill(' ');
+            }
+            _ => line.clear(),
+        }
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let top = self.top();
+        match mode {
+            0 => {
+                self.erase_line(0);
+                self.lines.truncate(self.row + 1);
+            }
+            1 => {
+                for line in &mut self.lines[top..self.row] {
+                    line.clear();
+                }
+                self.erase_line(1);
+            }
+            _ => {
+                // Clear the visible screen; scrollback above it stays
+                for line in &mut self.lines[top..] {
+                    line.clear();
+                }
+            }
+        }
+    }
+
+    fn enter_alternate_screen(&mut self) {
+        if self.main_screen.is_none() {
+            let main = std::mem::replace(&mut self.lines, vec![vec![]; self.rows]);
+            self.main_screen = Some((main, self.row, self.col));
+            self.row = 0;
+            self.col = 0;
+        }
+    }
+
+    fn leave_alternate_screen(&mut self) {
+        if let Some((main, row, col)) = self.main_screen.take() {
+            self.lines = main;
+            self.row = row;
+            self.col = col;
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// Send keystrokes (raw bytes, e.g. "q" or "\u0003" for Ctrl-C) to a running term:: block
+#[tauri::command]
+pub fn send_term_input(state: tauri::State<'_, AppState>, block_id: String, data: String) -> Result<(), String> {
+    write_input(&state.terminals, &block_id, data.as_bytes())
+}
+
+/// Resize a running term:: block's terminal
+#[tauri::command]
+pub fn resize_term(state: tauri::State<'_, AppState>, block_id: String, rows: u16, cols: u16) -> Result<(), String> {
+    let handle = {
+        let terms = state.terminals.terms.lock().map_err(|e| e.to_string())?;
+        terms.get(&block_id).cloned()
+            .ok_or_else(|| format!("No terminal running for block {}", block_id))?
+    };
+    let handle = handle.lock().map_err(|e| e.to_string())?;
+    set_window_size(&handle.master, rows, cols)?;
+    handle.screen.lock().map_err(|e| e.to_string())?.resize(rows as usize, cols as usize);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_cursor_moves_stay_on_the_screen() {
+        let mut screen = Screen::new(24, 80);
+        screen.feed(b"top\x1b[2000000000Bdown\x1b[18446744073709551615Cx\x1b[4000000000@\x1b[4000000000X");
+        assert_eq!(screen.lines.len(), 24);
+        assert_eq!(screen.lines().last(), Some(&format!("   down{}x", " ".repeat(72))));
+        assert!(screen.lines.iter().all(|line| line.len() <= 80));
+
+        screen.feed(b"\x1b[2000000000E!");
+        assert_eq!(screen.lines.len(), 24);
+    }
+
+    #[test]
+    fn scrollback_is_capped() {
+        let mut screen = Screen::new(24, 80);
+        for i in 0..MAX_SCROLLBACK + 100 {
+            screen.feed(format!("{}\r\n", i).as_bytes());
+        }
+        assert_eq!(screen.lines.len(), MAX_SCROLLBACK + 24);
+        assert_eq!(screen.visible_lines().last().map(String::as_str), Some("10099"));
+        assert_eq!(screen.lines().first().map(String::as_str), Some("77"));
+    }
+
+    #[test]
+    fn visible_lines_are_the_last_screenful() {
+        let mut screen = Screen::new(3, 80);
+        screen.feed(b"one\r\ntwo\r\nthree\r\nfour\r\n");
+        assert_eq!(screen.visible_lines(), vec!["three", "four"]);
+        assert_eq!(screen.lines(), vec!["one", "two", "three", "four"]);
+    }
+
+    #[test]
+    fn cursor_up_and_erase_redraw_a_line() {
+        // A progress loop recorded under script(1): ESC[2K + \r redraws, then
+        // ESC[1A moves back up to replace the last step with "done"
+        let mut screen = Screen::new(24, 80);
+        screen.feed(include_bytes!("../tests/fixtures/ansi/spinner-pty.txt"));
+        assert_eq!(screen.lines(), vec!["done"]);
+
+        let mut screen = Screen::new(24, 80);
+        screen.feed(include_bytes!("../tests/fixtures/ansi/cargo-build-pty.txt"));
+        let lines = screen.lines();
+        assert!(lines.iter().all(|l| !l.contains("Building")), "{:#?}", lines);
+        assert_eq!(lines.last().map(String::as_str), Some("error: could not compile `bad` (bin \"bad\") due to 1 previous error"));
+    }
+
+    #[test]
+    fn resize_changes_wrapping_and_the_visible_screen() {
+        let mut screen = Screen::new(2, 4);
+        screen.feed(b"abcdef");
+        assert_eq!(screen.lines(), vec!["abcd", "ef"]);
+
+        screen.resize(4, 8);
+        screen.feed(b"\r\nabcdefgh\r\nxy");
+        assert_eq!(screen.visible_lines(), vec!["abcd", "ef", "abcdefgh", "xy"]);
+    }
+}
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use yrs::{Array, Doc, Transact, WriteTxn};
+use crate::{ids_to_any, read_block, write_block, BlockData};
+
+// ═══════════════════════════════════════════════════════════════
+// TEST FIXTURES
+// ═══════════════════════════════════════════════════════════════
+
+/// A doc holding `(id, parent, content)` blocks, each child listed after its
+/// parent in order; blocks without a parent go in `rootIds`
+pub fn doc_with(items: &[(&str, Option<&str>, &str)]) -> Doc {
+    let mut children: HashMap<&str, Vec<String>> = HashMap::new();
+    for (id, parent, _) in items {
+        if let Some(parent) = parent {
+            children.entry(parent).or_default().push(id.to_string());
+        }
+    }
+
+    let doc = Doc::new();
+    {
+        let mut txn = doc.transact_mut();
+        let blocks = txn.get_or_insert_map("blocks");
+        let root_ids = txn.get_or_insert_array("rootIds");
+        for (id, parent, content) in items {
+            let mut block = BlockData::default();
+            block.set("id", *id);
+            block.set("parentId", parent.map(yrs::Any::from).unwrap_or(yrs::Any::Null));
+            block.set("childIds", ids_to_any(children.get(id).map(Vec::as_slice).unwrap_or_default()));
+            block.set("content", *content);
+            block.set("type", "text");
+            block.set("collapsed", false);
+            block.set("createdAt", yrs::Any::BigInt(0));
+            block.set("updatedAt", yrs::Any::BigInt(0));
+            write_block(&blocks, &mut txn, block);
+            if parent.is_none() {
+                root_ids.push_back(&mut txn, yrs::Any::String((*id).into()));
+            }
+        }
+    }
+    doc
+}
+
+/// Set a field on a block in a test doc
+pub fn set_field(doc: &Doc, id: &str, key: &str, value: impl Into<yrs::Any>) {
+    let mut txn = doc.transact_mut();
+    let blocks = txn.get_or_insert_map("blocks");
+    let mut block = read_block(&blocks, &txn, id).unwrap_or_else(|| panic!("no block {}", id));
+    block.set(key, value);
+    write_block(&blocks, &mut txn, block);
+}
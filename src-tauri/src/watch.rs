@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tauri::Manager;
+use yrs::{ReadTxn, Transact};
+use crate::index::BlockIndex;
+use crate::jobs::{start_queue, QueueOptions};
+use crate::output::OUTPUT_TYPES;
+use crate::{read_block, resolve_cwd, split_prefix, update_block, AppState, BlockData, Placement};
+
+// ═══════════════════════════════════════════════════════════════
+// WATCH MODE
+// ═══════════════════════════════════════════════════════════════
+//
+// A watch re-runs blocks when files change, while the app is open:
+//
+//   cwd:: ~/code/app
+//   └── watch:: src/**/*.rs Cargo.toml
+//       └── sh:: cargo check
+//
+// Patterns are globs relative to the inherited working directory (the
+// nearest `cwd::` ancestors, or the app's own). A single block can watch
+// on its own with a `watch` prop holding the patterns instead. Changes are
+// debounced - nothing runs until the files have been quiet for a moment
+// (`debounce=2s` to change it) - and each run replaces the previous
+// output. A change during a run queues one more run after it; `paused`
+// holds the watch. Files are polled, and a watch matching thousands of
+// files is polled less often so each poll stays cheap.
+
+/// How often watched files are checked
+const POLL: Duration = Duration::from_millis(500);
+
+/// Quiet time after a change before re-running
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Files matched by a watch are capped, so a runaway `**` can't stall the app
+const MAX_FILES: usize = 10_000;
+
+/// Files a watch may stat per poll - bigger watches are scanned less often
+const FILES_PER_POLL: usize = 1_000;
+
+/// How long to wait between scans of a watch that matched `files` last time
+fn scan_interval(files: usize) -> Duration {
+    POLL * (1 + files / FILES_PER_POLL) as u32
+}
+
+/// Parsed watch patterns and options
+#[derive(Debug, Clone, PartialEq)]
+struct WatchSpec {
+    patterns: Vec<String>,
+    debounce: Duration,
+}
+
+impl WatchSpec {
+    /// Parse "src/**/*.rs Cargo.toml debounce=2s"
+    fn parse(value: &str) -> Result<WatchSpec, String> {
+        let mut spec = WatchSpec { patterns: vec![], debounce: DEFAULT_DEBOUNCE };
+
+        for word in value.split_whitespace() {
+            match word.strip_prefix("debounce=") {
+                Some(time) => spec.debounce = parse_duration(time)?,
+                None => {
+                    glob::Pattern::new(word).map_err(|e| format!("Invalid watch pattern `{}`: {}", word, e))?;
+                    spec.patterns.push(word.to_string());
+                }
+            }
+        }
+
+        if spec.patterns.is_empty() {
+            return Err("watch:: needs at least one path or pattern, e.g. `watch:: src/**/*.rs`".to_string());
+        }
+        Ok(spec)
+    }
+}
+
+/// "300ms", "2s", "1m" → duration
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let end = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(end);
+    let number: u64 = number.parse().map_err(|_| format!("Invalid debounce `{}`", value))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "" | "s" => Ok(Duration::from_secs(number)),
+        "m" => number.checked_mul(60).map(Duration::from_secs).ok_or_else(|| format!("Invalid debounce `{}`", value)),
+        _ => Err(format!("Invalid debounce `{}` (e.g. 300ms, 2s)", value)),
+    }
+}
+
+/// Modification times of every file the patterns match, keyed by path
+type Snapshot = HashMap<PathBuf, Option<SystemTime>>;
+
+/// Match the patterns under a directory
+fn scan(spec: &WatchSpec, base: &std::path::Path) -> Result<Snapshot, String> {
+    let escaped = glob::Pattern::escape(&base.to_string_lossy());
+    let mut snapshot = Snapshot::new();
+
+    for pattern in &spec.patterns {
+        let full = if pattern.starts_with('/') { pattern.clone() } else { format!("{}/{}", escaped, pattern) };
+        let paths = glob::glob(&full).map_err(|e| format!("Invalid watch pattern `{}`: {}", pattern, e))?;
+        // Unreadable entries are skipped rather than failing the whole watch
+        for path in paths.flatten() {
+            if snapshot.len() >= MAX_FILES {
+                return Err(format!("Watch matches more than {} files - narrow the patterns", MAX_FILES));
+            }
+            let modified = path.metadata().and_then(|m| m.modified()).ok();
+            snapshot.insert(path, modified);
+        }
+    }
+    Ok(snapshot)
+}
+
+/// The watcher's view of one watching block
+struct Entry {
+    /// Patterns the entry was built from - an edit starts it over
+    source: String,
+    base: PathBuf,
+    spec: Result<WatchSpec, String>,
+    /// Files as last seen (None until the first scan)
+    snapshot: Option<Snapshot>,
+    scanned_at: Option<Instant>,
+    /// When files last changed, while a re-run is waiting
+    changed_at: Option<Instant>,
+    last_at: Option<DateTime<Local>>,
+    /// Queue of the latest run
+    queue_id: Option<String>,
+    /// Latest scan failure
+    error: Option<String>,
+}
+
+impl Entry {
+    fn new(source: &str, base: PathBuf) -> Self {
+        Self {
+            source: source.to_string(),
+            base,
+            spec: WatchSpec::parse(source),
+            snapshot: None,
+            scanned_at: None,
+            changed_at: None,
+            last_at: None,
+            queue_id: None,
+            error: None,
+        }
+    }
+}
+
+/// Watch entries by block ID
+pub struct Watches {
+    entries: Mutex<HashMap<String, Entry>>,
+    /// The doc's watching blocks, so a poll doesn't read every block
+    index: BlockIndex,
+}
+
+impl Default for Watches {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::default(),
+            index: BlockIndex::new(|block| watch_patterns(block).is_some()),
+        }
+    }
+}
+
+/// A watch as reported to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchInfo {
+    pub block_id: String,
+    pub patterns: String,
+    /// Directory the patterns are relative to
+    pub cwd: String,
+    /// Files currently matched
+    pub files: usize,
+    /// Unix millis
+    pub last_run: Option<i64>,
+    pub paused: bool,
+    /// Files changed and a re-run is waiting
+    pub pending: bool,
+    /// The latest run is still going
+    pub running: bool,
+    pub error: Option<String>,
+}
+
+/// Patterns a block watches: a `watch::` block's value, or a block's own `watch` prop
+/// Output never watches anything, whatever it prints
+fn watch_patterns(block: &BlockData) -> Option<String> {
+    if OUTPUT_TYPES.contains(&block.get_str("type").unwrap_or_default()) {
+        return None;
+    }
+    match split_prefix(block.content()) {
+        Some((prefix, value)) if prefix == "watch" => Some(value.to_string()),
+        _ => block.get_str("watch").filter(|p| !p.trim().is_empty()).map(str::to_string),
+    }
+}
+
+/// A watching block as found in the doc
+struct Found {
+    block_id: String,
+    patterns: String,
+    base: PathBuf,
+    paused: bool,
+}
+
+/// Bring the entries in line with the doc's watching blocks
+/// When polling, scan their files and return the watches that are due to re-run
+fn reconcile(state: &AppState, poll: bool) -> Result<(Vec<WatchInfo>, Vec<String>), String> {
+    let found: Vec<Found> = {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let ids = state.watches.index.ids(&doc)?;
+        let txn = doc.transact();
+        let Some(blocks) = txn.get_map("blocks") else { return Ok((vec![], vec![])) };
+        let app_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        ids.iter()
+            .filter_map(|id| read_block(&blocks, &txn, id))
+            .filter_map(|block| {
+                let patterns = watch_patterns(&block)?;
+                let base = match resolve_cwd(&blocks, &txn, block.id()) {
+                    Some(dir) => app_dir.join(dir),
+                    None => app_dir.clone(),
+                };
+                let paused = block.get_bool("paused").unwrap_or(false);
+                Some(Found { block_id: block.id().to_string(), patterns, base, paused })
+            })
+            .collect()
+    };
+
+    // New or edited watches are scanned right away, others once their interval is up
+    let now = Instant::now();
+    let scan_due: Vec<bool> = {
+        let entries = state.watches.entries.lock().map_err(|e| e.to_string())?;
+        found
+            .iter()
+            .map(|watch| match entries.get(&watch.block_id) {
+                Some(entry) if entry.source == watch.patterns && entry.base == watch.base => {
+                    let files = entry.snapshot.as_ref().map_or(0, HashMap::len);
+                    entry.scanned_at.map_or(true, |at| now.duration_since(at) >= scan_interval(files))
+                }
+                _ => true,
+            })
+            .collect()
+    };
+
+    // Scan without holding the entries lock - it can take a while on a big tree
+    let scans: Vec<Option<Result<Snapshot, String>>> = found
+        .iter()
+        .zip(scan_due)
+        .map(|(watch, due)| {
+            let spec = WatchSpec::parse(&watch.patterns).ok()?;
+            (poll && due && !watch.paused).then(|| scan(&spec, &watch.base))
+        })
+        .collect();
+
+    let mut entries = state.watches.entries.lock().map_err(|e| e.to_string())?;
+    entries.retain(|id, _| found.iter().any(|watch| &watch.block_id == id));
+
+    let mut infos = vec![];
+    let mut due = vec![];
+    for (watch, scanned) in found.into_iter().zip(scans) {
+        let entry = entries
+            .entry(watch.block_id.clone())
+            .or_insert_with(|| Entry::new(&watch.patterns, watch.base.clone()));
+        if entry.source != watch.patterns || entry.base != watch.base {
+            *entry = Entry::new(&watch.patterns, watch.base.clone());
+        }
+
+        if scanned.is_some() {
+            entry.scanned_at = Some(now);
+        }
+        match scanned {
+            Some(Ok(snapshot)) => {
+                // The first scan is only a baseline
+                if entry.snapshot.as_ref().is_some_and(|previous| *previous != snapshot) {
+                    entry.changed_at = Some(now);
+                }
+                entry.snapshot = Some(snapshot);
+                entry.error = None;
+            }
+            Some(Err(e)) => entry.error = Some(e),
+            None => {}
+        }
+
+        let running = entry.queue_id.as_ref().is_some_and(|id| state.jobs.is_running(id));
+        if let (Ok(spec), Some(changed_at)) = (&entry.spec, entry.changed_at) {
+            // A change during a run waits for it to finish, then runs once more
+            if poll && !watch.paused && !running && now.duration_since(changed_at) >= spec.debounce {
+                due.push(watch.block_id.clone());
+                entry.changed_at = None;
+                entry.last_at = Some(Local::now());
+            }
+        }
+
+        infos.push(WatchInfo {
+            block_id: watch.block_id,
+            patterns: watch.patterns,
+            cwd: watch.base.to_string_lossy().into_owned(),
+            files: entry.snapshot.as_ref().map_or(0, HashMap::len),
+            last_run: entry.last_at.map(|at| at.timestamp_millis()),
+            paused: watch.paused,
+            pending: entry.changed_at.is_some(),
+            running,
+            error: entry.spec.as_ref().err().or(entry.error.as_ref()).cloned(),
+        });
+    }
+
+    infos.sort_by(|a, b| a.block_id.cmp(&b.block_id));
+    Ok((infos, due))
+}
+
+/// Re-run every watch whose files changed and settled
+fn poll(app: &tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let (_, due) = reconcile(&state, true)?;
+
+    for block_id in due {
+        let options = QueueOptions {
+            concurrency: 1,
+            stop_on_failure: false,
+            placement: Placement { replace: true, grouped: false },
+        };
+        match start_queue(app.clone(), &state, &block_id, options) {
+            Ok(queue) => {
+                let mut entries = state.watches.entries.lock().map_err(|e| e.to_string())?;
+                if let Some(entry) = entries.get_mut(&block_id) {
+                    entry.queue_id = Some(queue.queue_id);
+                }
+            }
+            Err(e) => log::warn!("Watch re-run of {} failed to start: {}", block_id, e),
+        }
+    }
+    Ok(())
+}
+
+/// Watch files for as long as the app is open
+pub async fn run_watcher(app: tauri::AppHandle) {
+    loop {
+        tokio::time::sleep(POLL).await;
+        if let Err(e) = poll(&app) {
+            log::warn!("Watcher: {}", e);
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAURI COMMANDS
+// ═══════════════════════════════════════════════════════════════
+
+/// List the current workspace's watches
+#[tauri::command]
+pub fn list_watches(state: tauri::State<'_, AppState>) -> Result<Vec<WatchInfo>, String> {
+    let (infos, _) = reconcile(&state, false)?;
+    Ok(infos)
+}
+
+/// Set the patterns a block watches on its own (empty to stop watching)
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn set_block_watch(state: tauri::State<'_, AppState>, block_id: String, patterns: String) -> Result<String, String> {
+    let patterns = patterns.trim().to_string();
+    if !patterns.is_empty() {
+        WatchSpec::parse(&patterns)?;
+    }
+    update_block(&state, &block_id, |block| block.set("watch", patterns))
+}
+
+/// Pause or resume a watch
+/// Returns the updated Y.Doc state as base64
+#[tauri::command]
+pub fn set_watch_paused(state: tauri::State<'_, AppState>, block_id: String, paused: bool) -> Result<String, String> {
+    {
+        let doc = state.doc.lock().map_err(|e| e.to_string())?;
+        let txn = doc.transact();
+        let blocks = txn.get_map("blocks").ok_or("No blocks map")?;
+        let block = read_block(&blocks, &txn, &block_id)
+            .ok_or_else(|| format!("Block {} not found", block_id))?;
+        if watch_patterns(&block).is_none() {
+            return Err(format!("Block {} isn't watching anything", block_id));
+        }
+    }
+    update_block(&state, &block_id, |block| block.set("paused", paused))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_patterns_and_debounce() {
+        assert_eq!(
+            WatchSpec::parse("src/**/*.rs Cargo.toml"),
+            Ok(WatchSpec { patterns: vec!["src/**/*.rs".into(), "Cargo.toml".into()], debounce: DEFAULT_DEBOUNCE })
+        );
+        assert_eq!(
+            WatchSpec::parse("debounce=2s *.md"),
+            Ok(WatchSpec { patterns: vec!["*.md".into()], debounce: Duration::from_secs(2) })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        assert!(WatchSpec::parse("").unwrap_err().contains("at least one path"));
+        assert!(WatchSpec::parse("debounce=1s").unwrap_err().contains("at least one path"));
+        assert!(WatchSpec::parse("src/[a.rs").unwrap_err().contains("Invalid watch pattern"));
+        assert!(WatchSpec::parse("*.rs debounce=soon").unwrap_err().contains("Invalid debounce"));
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("300ms"), Ok(Duration::from_millis(300)));
+        assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_duration("1m"), Ok(Duration::from_secs(60)));
+        assert!(parse_duration("1h").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration(&format!("{}m", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn output_never_watches() {
+        let mut block = BlockData::default();
+        block.set("content", "watch:: src/**");
+        block.set("type", "text");
+        assert_eq!(watch_patterns(&block), Some("src/**".to_string()));
+        block.set("type", "output");
+        assert_eq!(watch_patterns(&block), None);
+
+        block.set("content", "cargo test");
+        block.set("watch", "*.rs");
+        assert_eq!(watch_patterns(&block), None);
+        block.set("type", "text");
+        assert_eq!(watch_patterns(&block), Some("*.rs".to_string()));
+    }
+
+    #[test]
+    fn big_watches_are_scanned_less_often() {
+        assert_eq!(scan_interval(0), POLL);
+        assert_eq!(scan_interval(999), POLL);
+        assert_eq!(scan_interval(MAX_FILES), POLL * 11);
+    }
+}
@@ -4,17 +4,19 @@
  * Handles:
  * - Rendering content via PlateBlock
  * - Keyboard navigation (cursor-aware - delegated to PlateBlock)
- * - sh:: execution triggering
+ * - sh:: / runner / door execution triggering
+ * - Live terminal for running term:: blocks
  * - Block operations (indent, outdent, delete, new block)
  */
 
-import { useCallback, useState, memo } from 'react';
+import { useCallback, useEffect, useState, memo } from 'react';
 import { PlateBlock } from './PlateBlock';
 import { StaticBlockRenderer } from './StaticBlockRenderer';
+import { TermView } from './TermView';
 import { useShallow } from 'zustand/shallow';
 import { useBlockStore } from '../hooks/useBlockStore';
-import { extractShellCommand, executeShellBlock, isExecutableShellBlock } from '../lib/executor';
-import type { Block } from '../lib/types';
+import { extractShellCommand, executeShellBlock, executeBlock, isExecutableShellBlock, isBackendExecutable, expandOutput, openOutput, listPendingRuns, approveExecution, rejectExecution, isScheduleBlock, setSchedulePaused, isWatchBlock, setWatchPaused, isTermBlock } from '../lib/executor';
+import type { Block, PendingRun } from '../lib/types';
 import * as Y from 'yjs';
 
 interface BlockItemProps {
@@ -45,7 +47,7 @@ export const BlockItem = memo(function BlockItem({
   doc,
 }: BlockItemProps) {
   // Use shallow selectors to prevent unnecessary rerenders
-  const { updateBlockContent, createBlockAfter, deleteBlock, indentBlock, outdentBlock, moveBlockUp, moveBlockDown } = useBlockStore(
+  const { updateBlockContent, createBlockAfter, deleteBlock, indentBlock, outdentBlock, moveBlockUp, moveBlockDown, setChecked } = useBlockStore(
     useShallow(state => ({
       updateBlockContent: state.updateBlockContent,
       createBlockAfter: state.createBlockAfter,
@@ -54,11 +56,24 @@ export const BlockItem = memo(function BlockItem({
       outdentBlock: state.outdentBlock,
       moveBlockUp: state.moveBlockUp,
       moveBlockDown: state.moveBlockDown,
+      setChecked: state.setChecked,
     }))
   );
 
   const [isExecuting, setIsExecuting] = useState(false);
 
+  // Run waiting for approval - the command shown comes from the backend, not the doc
+  const [pendingRun, setPendingRun] = useState<PendingRun | null>(null);
+  useEffect(() => {
+    if (block.status !== 'confirm') {
+      setPendingRun(null);
+      return;
+    }
+    listPendingRuns()
+      .then(runs => setPendingRun(runs.find(run => run.blockId === block.id) ?? null))
+      .catch(err => console.error('Failed to load pending runs:', err));
+  }, [block.id, block.status, block.updatedAt]);
+
   const handleChange = useCallback(
     (content: string) => {
       updateBlockContent(block.id, content);
@@ -106,21 +121,26 @@ export const BlockItem = memo(function BlockItem({
     [block.id, block.content, block.childIds.length, indentBlock, outdentBlock, createBlockAfter, deleteBlock, onNavigateUp, onRequestFocus, onZoomIntoBlock, moveBlockUp, moveBlockDown]
   );
 
-  // Execute sh:: command
-  const handleExecute = useCallback(async () => {
-    if (isExecuting) return;
-
-    const command = extractShellCommand(block.content);
-    if (!command) return;
+  // Execute sh:: command, code runner block or door alias
+  // Resolves false when the block turned out not to be executable
+  const handleExecute = useCallback(async (): Promise<boolean> => {
+    if (isExecuting) return true;
 
     setIsExecuting(true);
     try {
-      await executeShellBlock(block.id, command, doc);
+      // sh:: is resolved locally, anything else is up to the backend
+      const command = extractShellCommand(block.content);
+      if (command) {
+        await executeShellBlock(block.id, command, doc);
+      } else if (!await executeBlock(block.id, doc)) {
+        return false;
+      }
     } catch (err) {
       console.error('Execution failed:', err);
     } finally {
       setIsExecuting(false);
     }
+    return true;
   }, [block.id, block.content, doc, isExecuting]);
 
   const handleFocus = useCallback(() => {
@@ -142,6 +162,8 @@ export const BlockItem = memo(function BlockItem({
       ? 'text-cyan-600'
       : block.type === 'error'
       ? 'text-red-500'
+      : block.type === 'truncated'
+      ? 'text-neutral-600'
       : block.type === 'ctx'
       ? 'text-amber-500'
       : block.type === 'ai'
@@ -152,59 +174,140 @@ export const BlockItem = memo(function BlockItem({
   const indicatorSymbol = hasChildren ? (isCollapsed ? '▶' : '▼') : '•';
 
   return (
-    <div
-      className={`flex items-start gap-1 py-0.5 ${isFocused ? 'block-focused rounded' : ''}`}
-      style={{ paddingLeft: `${depth * 16}px` }}
-      onClick={handleFocus}
-    >
-      {/* Combined block indicator and collapse toggle */}
-      <button
-        className={`w-5 h-5 flex items-center justify-center flex-shrink-0 text-base leading-none mt-0.5 ${indicatorColor} ${hasChildren ? 'cursor-pointer' : 'cursor-default'}`}
-        onClick={(e) => {
-          if (hasChildren) {
-            e.stopPropagation();
-            onToggleCollapsed();
-          }
-        }}
-        tabIndex={hasChildren ? 0 : -1}
+    <>
+      <div
+        className={`flex items-start gap-1 py-0.5 ${isFocused ? 'block-focused rounded' : ''}`}
+        style={{ paddingLeft: `${depth * 16}px` }}
+        onClick={handleFocus}
       >
-        {indicatorSymbol}
-      </button>
-
-      {/* Content - Floating editor pattern: only render PlateBlock when focused */}
-      {isFocused ? (
-        <PlateBlock
-          content={block.content}
-          blockType={block.type}
-          isFocused={isFocused}
-          hasChildren={hasChildren}
-          onChange={handleChange}
-          onNavigateUp={onNavigateUp}
-          onNavigateDown={onNavigateDown}
-          onTreeAction={handleTreeAction}
-          onFocus={handleFocus}
-          onExecute={isExecutableShellBlock(block.content) ? handleExecute : undefined}
-          onToggleCollapsed={onToggleCollapsed}
-        />
-      ) : (
-        <StaticBlockRenderer
-          content={block.content}
-          blockType={block.type}
-          onFocus={handleFocus}
-        />
-      )}
-
-      {/* Exit code badge for sh:: blocks */}
-      {block.type === 'sh' && block.exitCode !== undefined && (
-        <span
-          className={`text-xs px-1 rounded ${
-            block.exitCode === 0 ? 'bg-emerald-900 text-emerald-300' : 'bg-red-900 text-red-300'
-          }`}
+        {/* Combined block indicator and collapse toggle */}
+        <button
+          className={`w-5 h-5 flex items-center justify-center flex-shrink-0 text-base leading-none mt-0.5 ${indicatorColor} ${hasChildren ? 'cursor-pointer' : 'cursor-default'}`}
+          onClick={(e) => {
+            if (hasChildren) {
+              e.stopPropagation();
+              onToggleCollapsed();
+            }
+          }}
+          tabIndex={hasChildren ? 0 : -1}
         >
-          {block.exitCode}
-        </span>
+          {indicatorSymbol}
+        </button>
+
+        {/* Task list checkbox */}
+        {block.checked !== undefined && (
+          <input
+            type="checkbox"
+            className="mt-1.5 flex-shrink-0 accent-emerald-500"
+            checked={block.checked}
+            onClick={(e) => e.stopPropagation()}
+            onChange={(e) => setChecked(block.id, e.target.checked)}
+          />
+        )}
+
+        {/* Content - Floating editor pattern: only render PlateBlock when focused */}
+        {isFocused ? (
+          <PlateBlock
+            content={block.content}
+            blockType={block.type}
+            isFocused={isFocused}
+            hasChildren={hasChildren}
+            onChange={handleChange}
+            onNavigateUp={onNavigateUp}
+            onNavigateDown={onNavigateDown}
+            onTreeAction={handleTreeAction}
+            onFocus={handleFocus}
+            onExecute={isExecutableShellBlock(block.content) || isBackendExecutable(block.content) ? handleExecute : undefined}
+            onToggleCollapsed={onToggleCollapsed}
+          />
+        ) : (
+          <StaticBlockRenderer
+            content={block.content}
+            blockType={block.type}
+            onFocus={handleFocus}
+          />
+        )}
+
+        {/* Truncated output: page in more, or open the full file */}
+        {block.type === 'truncated' && (
+          <span className="flex gap-1 text-xs">
+            <button
+              className="px-1 rounded bg-neutral-800 text-cyan-400 hover:text-cyan-300"
+              onClick={(e) => {
+                e.stopPropagation();
+                expandOutput(block.id, doc).catch(err => console.error('Expand failed:', err));
+              }}
+            >
+              more
+            </button>
+            <button
+              className="px-1 rounded bg-neutral-800 text-neutral-400 hover:text-neutral-300"
+              onClick={(e) => {
+                e.stopPropagation();
+                openOutput(block.id).catch(err => console.error('Open failed:', err));
+              }}
+            >
+              open
+            </button>
+          </span>
+        )}
+
+        {/* Run waiting for approval under the workspace's execution policy */}
+        {pendingRun && (
+          <span className="flex gap-1 text-xs items-center" title={pendingRun.reason}>
+            <code className="px-1 rounded bg-neutral-900 text-amber-300">{pendingRun.command}</code>
+            <button
+              className="px-1 rounded bg-neutral-800 text-emerald-400 hover:text-emerald-300"
+              onClick={(e) => {
+                e.stopPropagation();
+                approveExecution(block.id, pendingRun.token, doc).catch(err => console.error('Approve failed:', err));
+              }}
+            >
+              run
+            </button>
+            <button
+              className="px-1 rounded bg-neutral-800 text-red-400 hover:text-red-300"
+              onClick={(e) => {
+                e.stopPropagation();
+                rejectExecution(block.id, pendingRun.token, doc).catch(err => console.error('Reject failed:', err));
+              }}
+            >
+              skip
+            </button>
+          </span>
+        )}
+
+        {/* Pause / resume an every:: or cron:: schedule, or a watch */}
+        {(isScheduleBlock(block.content) || isWatchBlock(block.content, block.watch)) && (
+          <button
+            className={`text-xs px-1 rounded bg-neutral-800 ${block.paused ? 'text-amber-400 hover:text-amber-300' : 'text-neutral-400 hover:text-neutral-300'}`}
+            onClick={(e) => {
+              e.stopPropagation();
+              const setPaused = isScheduleBlock(block.content) ? setSchedulePaused : setWatchPaused;
+              setPaused(block.id, !block.paused, doc).catch(err => console.error('Pause failed:', err));
+            }}
+          >
+            {block.paused ? 'resume' : 'pause'}
+          </button>
+        )}
+
+        {/* Exit code badge for sh:: blocks */}
+        {block.type === 'sh' && block.exitCode !== undefined && (
+          <span
+            className={`text-xs px-1 rounded ${
+              block.exitCode === 0 ? 'bg-emerald-900 text-emerald-300' : 'bg-red-900 text-red-300'
+            }`}
+          >
+            {block.exitCode}
+          </span>
+        )}
+      </div>
+
+      {/* Running term:: block - live screen that takes keyboard input */}
+      {isExecuting && isTermBlock(block.content) && (
+        <TermView blockId={block.id} indent={depth * 16 + 24} />
       )}
-    </div>
+    </>
   );
 });
 
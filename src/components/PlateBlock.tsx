@@ -287,7 +287,8 @@ interface PlateBlockProps {
   onNavigateDown: () => void;
   onTreeAction: (action: 'indent' | 'outdent' | 'newBlockAfter' | 'deleteIfEmpty' | 'zoomIntoBlock' | 'moveUp' | 'moveDown') => void;
   onFocus?: () => void;
-  onExecute?: () => void;
+  /** Resolves false if the block wasn't executable after all (e.g. unknown door) */
+  onExecute?: () => Promise<boolean>;
   onToggleCollapsed?: () => void;
 }
 
@@ -480,13 +481,15 @@ export function PlateBlock({
       return;
     }
 
-    // Enter - execute sh:: block OR new block after
+    // Enter - execute sh:: / code / door block OR new block after
     if (e.key === 'Enter' && !e.shiftKey) {
       e.preventDefault();
       const currentText = valueToPlainText(editor.children);
-      // If it's an sh:: block and we have an execute handler, run it
-      if (currentText.trim().startsWith('sh::') && onExecute) {
-        onExecute();
+      // If it's an sh:: block, code block or door alias and we have an execute handler, run it
+      if (/^([\w-]+::|```)/.test(currentText.trim()) && onExecute) {
+        onExecute().then(ran => {
+          if (!ran) onTreeAction('newBlockAfter');
+        });
       } else {
         onTreeAction('newBlockAfter');
       }
@@ -6,7 +6,7 @@
  * This is the performance-critical path - keep it minimal!
  */
 
-import { memo, useMemo } from 'react';
+import { Fragment, memo, useMemo } from 'react';
 import type { BlockType } from '../lib/types';
 
 interface StaticBlockRendererProps {
@@ -15,14 +15,44 @@ interface StaticBlockRendererProps {
   onFocus: () => void;
 }
 
+/** Link and image schemes that may be rendered - anything else (javascript:, file:, data:...) stays text */
+const SAFE_URL = /^(https?|mailto):/i;
+
 /**
  * Parse simple markdown inline formatting to React elements
- * Handles: **bold**, ~~strikethrough~~, *italic*, `code`
+ * Handles: **bold**, ~~strikethrough~~, *italic*, `code`, [links](url), ![images](url)
  */
 function parseInlineMarkdown(text: string): React.ReactNode[] {
   const result: React.ReactNode[] = [];
   let key = 0;
 
+  // Links first - their labels can hold any of the rest
+  const linkMatch = /(!?)\[([^\]]*)\]\((<[^>]*>|[^)\s]+)(?:\s+"(?:[^"\\]|\\.)*")?\)/.exec(text);
+  if (linkMatch) {
+    const before = text.slice(0, linkMatch.index);
+    const after = text.slice(linkMatch.index + linkMatch[0].length);
+    const [, bang, label, rawUrl] = linkMatch;
+    const url = rawUrl.replace(/^<(.*)>$/, '$1').trim();
+    let link: React.ReactNode;
+    if (!SAFE_URL.test(url)) {
+      link = <Fragment key="link">{linkMatch[0]}</Fragment>;
+    } else if (bang) {
+      link = <img key="link" src={url} alt={label} title={label} className="inline max-h-48" loading="lazy" />;
+    } else {
+      link = (
+        <a key="link" href={url} title={url} target="_blank" rel="noopener noreferrer"
+          className="text-sky-400 underline" onClick={(e) => e.stopPropagation()}>
+          {parseInlineMarkdown(label)}
+        </a>
+      );
+    }
+    return [
+      <Fragment key="before">{parseInlineMarkdown(before)}</Fragment>,
+      link,
+      <Fragment key="after">{parseInlineMarkdown(after)}</Fragment>,
+    ];
+  }
+
   // Combined regex for inline markdown
   // Order matters: bold (**) and strikethrough (~~) before italic (*) to avoid conflicts
   const inlineRegex = /(\*\*(.+?)\*\*)|(~~(.+?)~~)|(\*(.+?)\*)|(`(.+?)`)/g;
@@ -131,6 +161,8 @@ export const StaticBlockRenderer = memo(function StaticBlockRenderer({
       ? 'block-ctx'
       : blockType === 'ai'
       ? 'block-ai'
+      : blockType === 'quote'
+      ? 'block-quote'
       : '';
 
   // Parse content into rendered lines
@@ -139,6 +171,17 @@ export const StaticBlockRenderer = memo(function StaticBlockRenderer({
       return <div className="text-neutral-500">Type here...</div>;
     }
 
+    // A fenced code block shows as-is (the fence can be longer than three backticks)
+    const fence = /^(`{3,})([^\n]*)\n([\s\S]*?)\n?\1\s*$/.exec(content.trim());
+    if (fence) {
+      const language = fence[2].trim().split(/\s+/)[0];
+      return (
+        <pre className="plate-code-block" title={language || undefined}>
+          <code>{fence[3]}</code>
+        </pre>
+      );
+    }
+
     const lines = content.split('\n');
     return lines.map((line, i) => renderLine(line, i));
   }, [content]);
@@ -0,0 +1,127 @@
+/**
+ * TermView - Live screen of a running term:: block
+ *
+ * Shows the emulated screen from term-output events and sends keystrokes to
+ * the block's PTY while it has focus. Once the command exits, its final
+ * screen lands as output blocks and this view goes away.
+ */
+
+import { memo, useCallback, useEffect, useRef, useState } from 'react';
+import { onTermOutput, sendTermInput, resizeTerm } from '../lib/executor';
+
+interface TermViewProps {
+  blockId: string;
+  /** Left padding to line up with the block's content */
+  indent: number;
+}
+
+/** Rows shown (and given to the PTY) */
+const TERM_ROWS = 24;
+
+/** Bytes a terminal sends for keys that aren't plain text */
+const KEY_SEQUENCES: Record<string, string> = {
+  Enter: '\r',
+  Backspace: '\x7f',
+  Tab: '\t',
+  Escape: '\x1b',
+  ArrowUp: '\x1b[A',
+  ArrowDown: '\x1b[B',
+  ArrowRight: '\x1b[C',
+  ArrowLeft: '\x1b[D',
+  Home: '\x1b[H',
+  End: '\x1b[F',
+  PageUp: '\x1b[5~',
+  PageDown: '\x1b[6~',
+  Delete: '\x1b[3~',
+};
+
+/**
+ * Translate a key press into the bytes a terminal would send
+ * Returns null for keys the terminal doesn't get (e.g. Cmd shortcuts)
+ */
+function keyToInput(e: React.KeyboardEvent): string | null {
+  if (e.metaKey) return null;
+  if (e.ctrlKey && e.key.length === 1) {
+    // Ctrl-A..Ctrl-Z and Ctrl-[ \ ] ^ _ map onto the C0 control codes
+    const code = e.key.toUpperCase().charCodeAt(0);
+    return code >= 64 && code <= 95 ? String.fromCharCode(code - 64) : null;
+  }
+  if (KEY_SEQUENCES[e.key]) return KEY_SEQUENCES[e.key];
+  if (e.key.length === 1) return e.altKey ? `\x1b${e.key}` : e.key;
+  return null;
+}
+
+export const TermView = memo(function TermView({ blockId, indent }: TermViewProps) {
+  const [screen, setScreen] = useState<string[]>([]);
+  const containerRef = useRef<HTMLPreElement>(null);
+  const probeRef = useRef<HTMLSpanElement>(null);
+  const sizedRef = useRef(false);
+
+  // Tell the PTY how many columns fit; the terminal only exists once output starts
+  const fitToWidth = useCallback(() => {
+    const container = containerRef.current;
+    const charWidth = probeRef.current?.getBoundingClientRect().width;
+    if (!container || !charWidth) return;
+    const cols = Math.max(20, Math.floor(container.clientWidth / charWidth));
+    resizeTerm(blockId, TERM_ROWS, cols).catch(err => console.error('Terminal resize failed:', err));
+  }, [blockId]);
+
+  useEffect(() => {
+    const unlisten = onTermOutput(output => {
+      if (output.blockId !== blockId) return;
+      setScreen(output.screen);
+      if (!sizedRef.current) {
+        sizedRef.current = true;
+        fitToWidth();
+      }
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [blockId, fitToWidth]);
+
+  useEffect(() => {
+    const container = containerRef.current;
+    if (!container) return;
+    const observer = new ResizeObserver(() => {
+      if (sizedRef.current) fitToWidth();
+    });
+    observer.observe(container);
+    return () => observer.disconnect();
+  }, [fitToWidth]);
+
+  const handleKeyDown = useCallback((e: React.KeyboardEvent) => {
+    const data = keyToInput(e);
+    if (data === null) return;
+    e.preventDefault();
+    e.stopPropagation();
+    sendTermInput(blockId, data).catch(err => console.error('Terminal input failed:', err));
+  }, [blockId]);
+
+  const handlePaste = useCallback((e: React.ClipboardEvent) => {
+    e.preventDefault();
+    sendTermInput(blockId, e.clipboardData.getData('text')).catch(err => console.error('Terminal input failed:', err));
+  }, [blockId]);
+
+  return (
+    <div style={{ paddingLeft: `${indent}px` }} className="pr-2 pb-1">
+      <pre
+        ref={containerRef}
+        tabIndex={0}
+        className="relative overflow-hidden text-xs leading-4 p-1 rounded bg-neutral-950 text-neutral-200 outline-none focus:ring-1 focus:ring-emerald-700"
+        style={{ height: `${TERM_ROWS}rem` }}
+        onClick={(e) => {
+          e.stopPropagation();
+          e.currentTarget.focus();
+        }}
+        onKeyDown={handleKeyDown}
+        onPaste={handlePaste}
+      >
+        <span ref={probeRef} className="absolute invisible" aria-hidden>M</span>
+        {screen.join('\n')}
+      </pre>
+    </div>
+  );
+});
+
+export default TermView;
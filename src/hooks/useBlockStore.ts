@@ -41,6 +41,7 @@ export interface BlockStore {
   moveBlockUp: (id: string) => void;
   moveBlockDown: (id: string) => void;
   toggleCollapsed: (id: string) => void;
+  setChecked: (id: string, checked: boolean) => void;
 
   // Internal
   _syncFromYDoc: () => void;
@@ -81,7 +82,11 @@ function toBlock(value: unknown): Block | null {
   const id = getValue(value, 'id') as string;
   if (!id) return null;
 
+  // Optional fields the backend sets (status, exitCode, marks, checked, ...) come along as-is
+  const fields = (value instanceof Y.Map ? value.toJSON() : value) as Partial<Block>;
+
   return {
+    ...fields,
     id,
     parentId: getValue(value, 'parentId') as string | null,
     childIds: (getValue(value, 'childIds') as string[]) || [],
@@ -504,4 +509,14 @@ export const useBlockStore = create<BlockStore>((set, get) => ({
       setValueOnYMap(blocksMap, id, 'collapsed', !block.collapsed);
     });
   },
+
+  setChecked: (id: string, checked: boolean) => {
+    const { _doc } = get();
+    if (!_doc) return;
+
+    _doc.transact(() => {
+      const blocksMap = _doc.getMap('blocks');
+      setValueOnYMap(blocksMap, id, 'checked', checked);
+    });
+  },
 }));
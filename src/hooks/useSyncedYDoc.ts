@@ -9,6 +9,7 @@
 
 import { useEffect, useRef, useCallback, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 import * as Y from 'yjs';
 
 // ═══════════════════════════════════════════════════════════════
@@ -196,6 +197,19 @@ export function useSyncedYDoc(
     };
   }, [debouncedSync]);
 
+  // Apply output from runs started in the background (job queues, schedules)
+  useEffect(() => {
+    const unlisten = listen<{ state: string | null }>('job-status', event => {
+      if (!event.payload.state) return;
+      isApplyingRemoteRef.current = true;
+      Y.applyUpdate(docRef.current, base64ToBytes(event.payload.state));
+      isApplyingRemoteRef.current = false;
+    });
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
   return {
     doc: docRef.current,
     isLoaded,
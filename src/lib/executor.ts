@@ -9,7 +9,9 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import * as Y from 'yjs';
+import type { AuditEntry, PendingRun, RunRecord, WorkspacePolicy } from './types';
 
 // ═══════════════════════════════════════════════════════════════
 // UTILITIES
@@ -24,6 +26,23 @@ function base64ToBytes(base64: string): Uint8Array {
   return bytes;
 }
 
+function bytesToBase64(bytes: Uint8Array): string {
+  let binary = '';
+  for (let i = 0; i < bytes.length; i++) {
+    binary += String.fromCharCode(bytes[i]);
+  }
+  return btoa(binary);
+}
+
+/**
+ * Push the doc to the backend before it reads a block
+ * Local edits reach Rust on a debounce, so an Enter right after typing would
+ * otherwise run the block's previous content
+ */
+async function flushDoc(doc: Y.Doc): Promise<void> {
+  await invoke<string>('apply_update', { updateB64: bytesToBase64(Y.encodeStateAsUpdate(doc)) });
+}
+
 /**
  * Normalize smart/curly quotes to straight quotes
  * Plate's typography can convert " to " and " which breaks shell commands
@@ -37,7 +56,7 @@ function normalizeQuotes(str: string): string {
 
 /**
  * Extract command from sh:: block content
- * e.g., "sh:: ls -la" → "ls -la"
+ * e.g., "sh:: ls -la" → "ls -la", "sh::json gh api user" → "gh api user"
  */
 export function extractShellCommand(content: string): string | null {
   const trimmed = content.trim();
@@ -46,11 +65,14 @@ export function extractShellCommand(content: string): string | null {
   let command: string | null = null;
 
   if (lower.startsWith('sh::')) {
-    command = trimmed.slice(4).trim();
+    command = trimmed.slice(4);
   } else if (lower.startsWith('term::')) {
-    command = trimmed.slice(6).trim();
+    command = trimmed.slice(6);
   }
 
+  // An output format attached to the prefix (sh::json, sh::csv) isn't part of the command
+  command = command?.replace(/^(json|csv|tsv|text)(?=\s|$)/i, '').trim() ?? null;
+
   // Normalize smart quotes to straight quotes for shell compatibility
   return command ? normalizeQuotes(command) : null;
 }
@@ -70,6 +92,7 @@ export async function executeShellBlock(
 ): Promise<void> {
   try {
     console.log(`[sh::] Executing: ${command} for block ${blockId}`);
+    await flushDoc(doc);
 
     // Invoke the Rust command
     const updatedStateB64 = await invoke<string>('execute_shell', {
@@ -95,3 +118,462 @@ export function isExecutableShellBlock(content: string): boolean {
   const command = extractShellCommand(content);
   return command !== null && command.length > 0;
 }
+
+/**
+ * Execute a block the backend knows how to run: code for a registered runner
+ * (py::, node::, ruby::, fenced code blocks) or a door alias
+ *
+ * @returns false if the block turned out not to be executable
+ */
+export async function executeBlock(blockId: string, doc: Y.Doc): Promise<boolean> {
+  console.log(`[exec] Executing block ${blockId}`);
+  await flushDoc(doc);
+
+  const updatedStateB64 = await invoke<string | null>('execute_block', { blockId });
+  if (updatedStateB64 === null) return false;
+
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+  console.log(`[exec] Execution complete for block ${blockId}`);
+  return true;
+}
+
+/**
+ * Check if a block might be executable by the backend (any other prefix:: or a
+ * fenced code block). Only the backend knows the runner table and the workspace's
+ * door definitions, so this is a hint
+ */
+export function isBackendExecutable(content: string): boolean {
+  const trimmed = content.trim();
+  if (trimmed.startsWith('```')) return true;
+  return /^[\w-]+::/.test(trimmed) && extractShellCommand(content) === null;
+}
+
+/**
+ * Get every recorded run of a block, oldest first
+ */
+export async function getRunHistory(blockId: string): Promise<RunRecord[]> {
+  return invoke<RunRecord[]>('get_run_history', { blockId });
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXECUTION POLICY
+// ═══════════════════════════════════════════════════════════════
+
+/**
+ * Runs in the current workspace waiting for approval
+ * Blocks waiting for one have status 'confirm'
+ */
+export async function listPendingRuns(): Promise<PendingRun[]> {
+  return invoke<PendingRun[]>('list_pending_runs');
+}
+
+/**
+ * Approve a pending run and execute it
+ */
+export async function approveExecution(blockId: string, token: string, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('approve_execution', { blockId, token });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Drop a pending run without executing it
+ */
+export async function rejectExecution(blockId: string, token: string, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('reject_execution', { blockId, token });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+export async function getWorkspacePolicy(): Promise<WorkspacePolicy> {
+  return invoke<WorkspacePolicy>('get_workspace_policy');
+}
+
+export async function setWorkspacePolicy(policy: WorkspacePolicy): Promise<void> {
+  await invoke('set_workspace_policy', { policy });
+}
+
+/**
+ * Most recent audit log entries, oldest first
+ */
+export async function getAuditLog(limit?: number): Promise<AuditEntry[]> {
+  return invoke<AuditEntry[]>('get_audit_log', { limit: limit ?? null });
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TRUNCATED OUTPUT
+// ═══════════════════════════════════════════════════════════════
+
+/**
+ * Replace a truncated marker with the next page of its saved output
+ */
+export async function expandOutput(blockId: string, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('expand_output', { blockId });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Open a truncated marker's full output with the system's default app
+ * @returns Path of the saved output file
+ */
+export async function openOutput(blockId: string): Promise<string> {
+  return invoke<string>('open_output', { blockId });
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TERM:: BLOCKS
+// ═══════════════════════════════════════════════════════════════
+
+/** Output streamed from a running term:: block (runs under a PTY) */
+export interface TermOutput {
+  blockId: string;
+  /** Raw terminal bytes, for a terminal widget */
+  data: string;
+  /** Emulated visible screen after this chunk */
+  screen: string[];
+}
+
+/**
+ * Subscribe to output from running term:: blocks
+ */
+export function onTermOutput(handler: (output: TermOutput) => void): Promise<UnlistenFn> {
+  return listen<TermOutput>('term-output', event => handler(event.payload));
+}
+
+/**
+ * Send keystrokes to a running term:: block (e.g. "q", or "\u0003" for Ctrl-C)
+ */
+export async function sendTermInput(blockId: string, data: string): Promise<void> {
+  await invoke('send_term_input', { blockId, data });
+}
+
+/**
+ * Resize a running term:: block's terminal (its PTY and the emulated screen)
+ */
+export async function resizeTerm(blockId: string, rows: number, cols: number): Promise<void> {
+  await invoke('resize_term', { blockId, rows, cols });
+}
+
+/**
+ * Check if a block is a term:: block (runs under a PTY)
+ */
+export function isTermBlock(content: string): boolean {
+  return /^term::/i.test(content.trim());
+}
+
+// ═══════════════════════════════════════════════════════════════
+// JOB QUEUE
+// ═══════════════════════════════════════════════════════════════
+
+export type JobStatus = 'queued' | 'running' | 'complete' | 'failed' | 'confirm' | 'skipped' | 'cancelled';
+
+/** Where a run's output goes among the block's children */
+export interface Placement {
+  /** Drop the output of earlier runs first */
+  replace?: boolean;
+  /** Put the output under a timestamped `run` block */
+  grouped?: boolean;
+}
+
+export interface QueueOptions {
+  /** Jobs running at once (1 = in order, each waiting for the last) */
+  concurrency?: number;
+  /** Don't start more jobs once one fails (or waits for confirmation) */
+  stopOnFailure?: boolean;
+  placement?: Placement;
+}
+
+/** A queue as started */
+export interface QueueInfo {
+  queueId: string;
+  /** Blocks that will run, in document order */
+  blockIds: string[];
+}
+
+/** A job changing state */
+export interface JobEvent {
+  queueId: string;
+  blockId: string;
+  index: number;
+  status: JobStatus;
+  exitCode: number | null;
+  error: string | null;
+  /** Updated Y.Doc state (base64), once the job has run */
+  state: string | null;
+}
+
+/** Every job in a queue is done */
+export interface QueueDone {
+  queueId: string;
+  /** Final status of each job, in queue order */
+  statuses: JobStatus[];
+}
+
+/**
+ * Run every executable block under (and including) a block
+ * Resolves once the queue has started - follow it with onJobStatus / onQueueDone
+ */
+export async function runSubtree(blockId: string, options: QueueOptions = {}): Promise<QueueInfo> {
+  return invoke<QueueInfo>('run_subtree', { blockId, options });
+}
+
+/**
+ * Stop a queue from starting more jobs (running ones finish)
+ */
+export async function cancelQueue(queueId: string): Promise<void> {
+  await invoke('cancel_queue', { queueId });
+}
+
+/**
+ * Subscribe to job status changes
+ * (finished jobs' output is applied to the doc by useSyncedYDoc)
+ */
+export function onJobStatus(handler: (event: JobEvent) => void): Promise<UnlistenFn> {
+  return listen<JobEvent>('job-status', event => handler(event.payload));
+}
+
+/**
+ * Subscribe to queues finishing
+ */
+export function onQueueDone(handler: (done: QueueDone) => void): Promise<UnlistenFn> {
+  return listen<QueueDone>('job-queue-done', event => handler(event.payload));
+}
+
+// ═══════════════════════════════════════════════════════════════
+// SCHEDULES
+// ═══════════════════════════════════════════════════════════════
+
+/** An every:: / cron:: block as seen by the scheduler */
+export interface ScheduleInfo {
+  blockId: string;
+  content: string;
+  nextRun: number | null;
+  lastRun: number | null;
+  paused: boolean;
+  /** The latest run is still going */
+  running: boolean;
+  /** Why the schedule can't be parsed */
+  error: string | null;
+}
+
+/**
+ * List the workspace's schedules, soonest first
+ */
+export async function listSchedules(): Promise<ScheduleInfo[]> {
+  return invoke<ScheduleInfo[]>('list_schedules');
+}
+
+/**
+ * Pause or resume an every:: / cron:: block
+ */
+export async function setSchedulePaused(blockId: string, paused: boolean, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('set_schedule_paused', { blockId, paused });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Check if a block is an every:: / cron:: schedule
+ */
+export function isScheduleBlock(content: string): boolean {
+  return /^(every|cron)::/i.test(content.trim());
+}
+
+// ═══════════════════════════════════════════════════════════════
+// WATCH MODE
+// ═══════════════════════════════════════════════════════════════
+
+/** A watch:: block (or a block with its own watch patterns) as seen by the watcher */
+export interface WatchInfo {
+  blockId: string;
+  patterns: string;
+  /** Directory the patterns are relative to */
+  cwd: string;
+  /** Files currently matched */
+  files: number;
+  lastRun: number | null;
+  paused: boolean;
+  /** Files changed and a re-run is waiting */
+  pending: boolean;
+  /** The latest run is still going */
+  running: boolean;
+  error: string | null;
+}
+
+/**
+ * List the workspace's watches
+ */
+export async function listWatches(): Promise<WatchInfo[]> {
+  return invoke<WatchInfo[]>('list_watches');
+}
+
+/**
+ * Make a block re-run when files matching the patterns change (empty to stop)
+ */
+export async function setBlockWatch(blockId: string, patterns: string, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('set_block_watch', { blockId, patterns });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Pause or resume a watch
+ */
+export async function setWatchPaused(blockId: string, paused: boolean, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('set_watch_paused', { blockId, paused });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Check if a block is a watch:: block or watches files itself
+ */
+export function isWatchBlock(content: string, watch?: string): boolean {
+  return /^watch::/i.test(content.trim()) || !!watch?.trim();
+}
+
+// ═══════════════════════════════════════════════════════════════
+// RUN SUMMARY
+// ═══════════════════════════════════════════════════════════════
+
+/** Counts of runs since the app started (or the summary was cleared) */
+export interface RunSummary {
+  running: number;
+  completed: number;
+  failed: number;
+  /** Waiting for confirmation */
+  pending: number;
+}
+
+/**
+ * Get the current run counts
+ */
+export async function getRunSummary(): Promise<RunSummary> {
+  return invoke<RunSummary>('get_run_summary');
+}
+
+/**
+ * Reset the completed and failed counts
+ */
+export async function clearRunSummary(): Promise<RunSummary> {
+  return invoke<RunSummary>('clear_run_summary');
+}
+
+/**
+ * Subscribe to run count changes
+ */
+export function onRunSummary(handler: (summary: RunSummary) => void): Promise<UnlistenFn> {
+  return listen<RunSummary>('run-summary', event => handler(event.payload));
+}
+
+// ═══════════════════════════════════════════════════════════════
+// IMPORT
+// ═══════════════════════════════════════════════════════════════
+
+/** What to import: a file on disk, or the text of one */
+export type ImportSource = { path: string } | { text: string };
+
+/**
+ * Import markdown under a block (or at the top level with no parent)
+ * Front matter becomes properties on a block for the document
+ */
+export async function importMarkdown(source: ImportSource, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_markdown', { parentId, ...source });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Import an OPML file or document under a block (or at the top level with no parent)
+ */
+export async function importOpml(source: ImportSource, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_opml', { parentId, ...source });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Import a Logseq graph folder (or one page file), one block per page
+ * Blocks keep their `id::` as their ID where it's free
+ */
+export async function importLogseq(path: string, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_logseq', { parentId, path });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Import a Roam JSON export, one block per page
+ * Blocks keep their Roam uid as their ID where it's free
+ */
+export async function importRoam(source: ImportSource, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_roam', { parentId, ...source });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Import an org file or org text: headlines nest, TODO keywords, tags and
+ * property drawers become block properties, and `sh` source blocks run as `sh::`
+ */
+export async function importOrg(source: ImportSource, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_org', { parentId, ...source });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+/**
+ * Import every markdown file in a directory, one subtree per file
+ */
+export async function importMarkdownDir(path: string, parentId: string | null, doc: Y.Doc): Promise<void> {
+  const updatedStateB64 = await invoke<string>('import_markdown_dir', { parentId, path });
+  Y.applyUpdate(doc, base64ToBytes(updatedStateB64));
+}
+
+// ═══════════════════════════════════════════════════════════════
+// EXPORT
+// ═══════════════════════════════════════════════════════════════
+
+export interface MarkdownExportOptions {
+  /** Nested bullets (the default, reads back as the same blocks) or headings */
+  style?: 'bullets' | 'headings';
+  /** Write `<!-- collapsed -->` after collapsed blocks */
+  markCollapsed?: boolean;
+  /** Write run output in `output`/`error` fences (default true) */
+  includeOutput?: boolean;
+}
+
+/**
+ * Export a block's subtree (or the whole workspace with no block) as markdown
+ * Writes it to `path` if given, and returns it either way
+ */
+export async function exportMarkdown(
+  blockId: string | null,
+  options: MarkdownExportOptions = {},
+  path?: string
+): Promise<string> {
+  return invoke<string>('export_markdown', { blockId, options, path: path ?? null });
+}
+
+/**
+ * Export a block's subtree (or the whole workspace with no block) as OPML 2.0
+ * Writes it to `path` if given, and returns it either way
+ */
+export async function exportOpml(blockId: string | null, path?: string): Promise<string> {
+  return invoke<string>('export_opml', { blockId, path: path ?? null });
+}
+
+/**
+ * Export a block's subtree (or the whole workspace with no block) as an org document
+ * Writes it to `path` if given, and returns it either way
+ */
+export async function exportOrg(blockId: string | null, path?: string): Promise<string> {
+  return invoke<string>('export_org', { blockId, path: path ?? null });
+}
+
+/**
+ * Export a block's subtree (or the whole workspace with no block) as one
+ * self-contained HTML page, with collapsible nesting and `((id))` links
+ * Writes it to `path` if given, and returns it either way
+ */
+export async function exportHtml(blockId: string | null, path?: string): Promise<string> {
+  return invoke<string>('export_html', { blockId, path: path ?? null });
+}
+
+/**
+ * Export a block's subtree (or the whole workspace) as a folder of HTML pages,
+ * one per top-level block plus an index. Returns the files written
+ */
+export async function exportHtmlSite(blockId: string | null, path: string): Promise<string[]> {
+  return invoke<string[]>('export_html_site', { blockId, path });
+}
@@ -18,10 +18,16 @@ export type BlockType =
   | 'dispatch'  // dispatch:: - agent execution
   | 'web'       // web:: or link:: - iframe embed
   | 'output'    // Output from sh:: or ai:: execution
-  | 'error';    // Error output from execution
+  | 'error'     // Error output from execution
+  | 'truncated' // Marker for output past the size limits (rest saved to a file)
+  | 'table'     // Table from CSV/TSV output - header row, rows as children
+  | 'row'       // A table row
+  | 'quote'     // Blockquote from imported markdown - first line as content, the rest as children
+  | 'code'      // Fenced code block - runnable when its language has a runner
+  | 'run';      // Timestamped group holding one scheduled run's output
 
 /** Executor status */
-export type ExecutorStatus = 'idle' | 'running' | 'complete' | 'error';
+export type ExecutorStatus = 'idle' | 'running' | 'complete' | 'error' | 'confirm';
 
 /** A block in the tree */
 export interface Block {
@@ -49,6 +55,117 @@ export interface Block {
 
   /** Exit code (for sh:: blocks) */
   exitCode?: number;
+
+  /** How many times the block has been run */
+  runCount?: number;
+
+  /** Metadata for the most recent run */
+  lastRun?: RunRecord;
+
+  /** Styled ranges from ANSI colors in command output (UTF-16 offsets) */
+  marks?: BlockMark[];
+
+  /** Full output file behind a truncated marker */
+  spillPath?: string;
+
+  /** Line counts for a truncated marker */
+  shownLines?: number;
+  totalLines?: number;
+
+  /** Header cells of a table block */
+  columns?: string[];
+
+  /** Cells of a row block */
+  cells?: string[];
+
+  /** Task list item state (`- [ ]` / `- [x]`) */
+  checked?: boolean;
+
+  /** GitHub alert kind of a quote block (note, tip, important, warning, caution) */
+  callout?: string;
+
+  /** Fence language of a code block */
+  language?: string;
+
+  /** every:: / cron:: schedule or watch is on hold */
+  paused?: boolean;
+
+  /** Glob patterns this block re-runs on (its own watch, without a watch:: parent) */
+  watch?: string;
+}
+
+/** What happened when a block ran (see get_run_history for older runs) */
+export interface RunRecord {
+  blockId: string;
+  run: number;
+  runner: string;
+  /** The command as run, after door expansion (the code for runner blocks) */
+  command: string;
+  cwd: string | null;
+  session: string | null;
+  startedAt: number;
+  endedAt: number;
+  durationMs: number;
+  exitCode: number;
+  /** Signal that killed the process */
+  signal: number | null;
+  stdoutBytes: number;
+  stderrBytes: number;
+}
+
+/** A run waiting for the user to approve it (see list_pending_runs) */
+export interface PendingRun {
+  blockId: string;
+  /** One-time token for approve_execution / reject_execution */
+  token: string;
+  runner: string;
+  command: string;
+  reason: string;
+}
+
+/** A workspace's execution policy (~/.float-liner/<workspace>/policy.json) */
+export interface WorkspacePolicy {
+  /** Run anything not denied without asking */
+  trusted: boolean;
+  /** Regexes for commands an untrusted workspace may run without confirmation (whole command) */
+  allow: string[];
+  /** Regexes for commands that never run (anywhere in the command) */
+  deny: string[];
+  /** Run every shell command in this sandbox (`sandbox::` blocks can only tighten it) */
+  sandbox?: SandboxConfig | null;
+}
+
+/** Restrictions for sandboxed shell commands (Linux) */
+export interface SandboxConfig {
+  /** readonly: every mount read-only; tmp: read-only, starting in a throwaway /tmp */
+  filesystem: 'full' | 'tmp' | 'readonly';
+  network: boolean;
+  cpuSeconds?: number | null;
+  /** Address space limit in MiB */
+  memoryMb?: number | null;
+  maxProcesses?: number | null;
+}
+
+/** One line of a workspace's audit log */
+export interface AuditEntry {
+  at: number;
+  blockId: string;
+  runner: string;
+  command: string;
+  decision: 'allowed' | 'denied' | 'pending' | 'approved' | 'rejected';
+  reason: string | null;
+}
+
+/** A styled range of a block's content */
+export interface BlockMark {
+  start: number;
+  end: number;
+  bold?: boolean;
+  italic?: boolean;
+  underline?: boolean;
+  strikethrough?: boolean;
+  color?: string;
+  backgroundColor?: string;
 }
 
 /** Parse block type from content prefix */
@@ -60,6 +177,7 @@ export function parseBlockType(content: string): BlockType {
   if (trimmed.startsWith('ctx::')) return 'ctx';
   if (trimmed.startsWith('dispatch::')) return 'dispatch';
   if (trimmed.startsWith('web::') || trimmed.startsWith('link::')) return 'web';
+  if (trimmed.startsWith('```')) return 'code';
 
   return 'text';
 }